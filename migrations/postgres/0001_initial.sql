@@ -0,0 +1,145 @@
+-- Same schema as the SQLite migrations, timestamps are kept as text in SQLite's format.
+-- Databases created from an earlier init script get the tables added since
+CREATE TABLE IF NOT EXISTS terms (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    archived BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS courses (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    code TEXT NOT NULL,
+    semester BIGINT NOT NULL,
+    year BIGINT NOT NULL,
+    credit DOUBLE PRECISION NOT NULL,
+    fk_term_id BIGINT,
+    deleted_at TEXT,
+    FOREIGN KEY (fk_term_id) REFERENCES terms(id)
+);
+
+-- Duplicates from before the index get their id appended, so the index can be created
+UPDATE courses SET code = code || '-' || id
+WHERE deleted_at IS NULL AND EXISTS (
+    SELECT 1 FROM courses AS other
+    WHERE other.deleted_at IS NULL
+        AND other.id < courses.id
+        AND lower(other.code) = lower(courses.code)
+        AND other.semester = courses.semester
+        AND other.year = courses.year
+);
+
+-- Codes repeat across semesters, cloned courses keep theirs
+CREATE UNIQUE INDEX IF NOT EXISTS courses_code ON courses (lower(code), semester, year) WHERE deleted_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS assessments (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    weight DOUBLE PRECISION NOT NULL,
+    take1 TEXT NOT NULL,
+    retake1 TEXT NOT NULL,
+    retake2 TEXT NOT NULL,
+    fk_course_id BIGINT NOT NULL,
+    deleted_at TEXT,
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id BIGSERIAL PRIMARY KEY,
+    weekday BIGINT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    location TEXT NOT NULL,
+    fk_course_id BIGINT NOT NULL,
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
+
+CREATE TABLE IF NOT EXISTS course_editors (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    role_id BIGINT,
+    user_id BIGINT,
+    fk_course_id BIGINT NOT NULL,
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    actor_id BIGINT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    action TEXT NOT NULL,
+    entity TEXT NOT NULL,
+    entity_id BIGINT NOT NULL,
+    before TEXT,
+    after TEXT,
+    reverted BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- Guild defaults use user_id 0, personal choices use guild_id 0
+CREATE TABLE IF NOT EXISTS theme_preferences (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    theme TEXT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS course_subscriptions (
+    user_id BIGINT NOT NULL,
+    fk_course_id BIGINT NOT NULL,
+    PRIMARY KEY (user_id, fk_course_id),
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
+
+-- Enrolling gives the role, reminders are posted in the channel if there is one
+CREATE TABLE IF NOT EXISTS course_roles (
+    guild_id BIGINT NOT NULL,
+    fk_course_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    channel_id BIGINT,
+    PRIMARY KEY (guild_id, fk_course_id),
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
+
+-- Lab groups and the like, a member is in at most one cohort per guild
+CREATE TABLE IF NOT EXISTS cohorts (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS cohorts_name ON cohorts (guild_id, lower(name));
+
+CREATE TABLE IF NOT EXISTS cohort_members (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    fk_cohort_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id),
+    FOREIGN KEY (fk_cohort_id) REFERENCES cohorts(id)
+);
+
+-- Assessments with rows here are only for those cohorts of the guild.
+-- NULL keeps the assessment's date of the attempt, an empty one leaves the attempt out
+CREATE TABLE IF NOT EXISTS cohort_dates (
+    fk_assessment_id BIGINT NOT NULL,
+    fk_cohort_id BIGINT NOT NULL,
+    take1 TEXT,
+    retake1 TEXT,
+    retake2 TEXT,
+    PRIMARY KEY (fk_assessment_id, fk_cohort_id),
+    FOREIGN KEY (fk_assessment_id) REFERENCES assessments(id),
+    FOREIGN KEY (fk_cohort_id) REFERENCES cohorts(id)
+);
+
+-- Attempts already reminded of, so restarts don't post them again
+CREATE TABLE IF NOT EXISTS sent_reminders (
+    guild_id BIGINT NOT NULL,
+    fk_assessment_id BIGINT NOT NULL,
+    date TEXT NOT NULL,
+    PRIMARY KEY (guild_id, fk_assessment_id, date)
+);
+
+-- Used by /search, which matches the same expression
+CREATE INDEX IF NOT EXISTS courses_search ON courses USING GIN (to_tsvector('simple', name || ' ' || code));
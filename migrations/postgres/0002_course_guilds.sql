@@ -0,0 +1,32 @@
+-- Courses and terms belong to the guild they were created in, see migrations/sqlite/0013_course_guilds.sql
+ALTER TABLE courses ADD COLUMN IF NOT EXISTS guild_id BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE terms ADD COLUMN IF NOT EXISTS guild_id BIGINT NOT NULL DEFAULT 0;
+
+UPDATE courses SET guild_id = first.guild_id
+FROM (
+    SELECT DISTINCT ON (entity_id) entity_id, guild_id FROM audit_log
+    WHERE entity = 'course' AND guild_id != 0
+    ORDER BY entity_id, id
+) first
+WHERE first.entity_id = courses.id AND courses.guild_id = 0;
+UPDATE terms SET guild_id = first.guild_id
+FROM (
+    SELECT DISTINCT ON (entity_id) entity_id, guild_id FROM audit_log
+    WHERE entity = 'term' AND guild_id != 0
+    ORDER BY entity_id, id
+) first
+WHERE first.entity_id = terms.id AND terms.guild_id = 0;
+
+CREATE TEMPORARY TABLE known_guilds AS
+SELECT guild_id FROM audit_log WHERE guild_id != 0
+UNION SELECT guild_id FROM course_editors
+UNION SELECT guild_id FROM course_roles;
+UPDATE courses SET guild_id = (SELECT guild_id FROM known_guilds)
+WHERE guild_id = 0 AND (SELECT COUNT(*) FROM known_guilds) = 1;
+UPDATE terms SET guild_id = (SELECT guild_id FROM known_guilds)
+WHERE guild_id = 0 AND (SELECT COUNT(*) FROM known_guilds) = 1;
+DROP TABLE known_guilds;
+
+-- Every listing is by guild
+CREATE INDEX IF NOT EXISTS terms_guild ON terms (guild_id, active);
+CREATE INDEX IF NOT EXISTS courses_guild ON courses (guild_id);
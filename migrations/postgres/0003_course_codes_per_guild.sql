@@ -0,0 +1,3 @@
+-- Guilds pick their codes independently, the same code may exist once per guild
+DROP INDEX IF EXISTS courses_code;
+CREATE UNIQUE INDEX courses_code ON courses (guild_id, lower(code), semester, year) WHERE deleted_at IS NULL;
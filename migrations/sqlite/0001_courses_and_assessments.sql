@@ -1,4 +1,5 @@
-CREATE TABLE courses (
+-- The original schema, databases created before migrations already have these tables
+CREATE TABLE IF NOT EXISTS courses (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     name TEXT NOT NULL,
     code TEXT NOT NULL,
@@ -7,7 +8,7 @@ CREATE TABLE courses (
     credit REAL NOT NULL
 );
 
-CREATE TABLE assessments (
+CREATE TABLE IF NOT EXISTS assessments (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     name TEXT NOT NULL,
     weight REAL NOT NULL,
@@ -17,4 +18,3 @@ CREATE TABLE assessments (
     fk_course_id INTEGER NOT NULL,
     FOREIGN KEY (fk_course_id) REFERENCES courses(id)
 );
-
@@ -0,0 +1,9 @@
+CREATE TABLE sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    weekday INTEGER NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    location TEXT NOT NULL,
+    fk_course_id INTEGER NOT NULL,
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE course_editors (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    role_id INTEGER,
+    user_id INTEGER,
+    fk_course_id INTEGER NOT NULL,
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
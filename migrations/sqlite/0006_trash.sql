@@ -0,0 +1,2 @@
+ALTER TABLE courses ADD COLUMN deleted_at TEXT;
+ALTER TABLE assessments ADD COLUMN deleted_at TEXT;
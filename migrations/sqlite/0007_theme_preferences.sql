@@ -0,0 +1,7 @@
+-- Guild defaults use user_id 0, personal choices use guild_id 0
+CREATE TABLE theme_preferences (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    theme TEXT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
@@ -0,0 +1,58 @@
+-- Live courses and assessments for /search, kept in sync by the triggers below.
+-- Assessment rows carry their course's code, so "MTH101 exam" finds them
+CREATE VIRTUAL TABLE search_index USING fts5(
+    kind UNINDEXED,
+    entity_id UNINDEXED,
+    course_id UNINDEXED,
+    name,
+    code,
+    prefix = '2 3'
+);
+
+CREATE TRIGGER courses_search_insert AFTER INSERT ON courses
+WHEN new.deleted_at IS NULL
+BEGIN
+    INSERT INTO search_index (kind, entity_id, course_id, name, code)
+    VALUES ('course', new.id, new.id, new.name, new.code);
+END;
+
+CREATE TRIGGER courses_search_update AFTER UPDATE OF name, code, deleted_at ON courses
+BEGIN
+    DELETE FROM search_index WHERE kind = 'course' AND entity_id = old.id;
+    INSERT INTO search_index (kind, entity_id, course_id, name, code)
+    SELECT 'course', new.id, new.id, new.name, new.code WHERE new.deleted_at IS NULL;
+    UPDATE search_index SET code = new.code WHERE kind = 'assessment' AND course_id = new.id;
+END;
+
+CREATE TRIGGER courses_search_delete AFTER DELETE ON courses
+BEGIN
+    DELETE FROM search_index WHERE kind = 'course' AND entity_id = old.id;
+END;
+
+CREATE TRIGGER assessments_search_insert AFTER INSERT ON assessments
+WHEN new.deleted_at IS NULL
+BEGIN
+    INSERT INTO search_index (kind, entity_id, course_id, name, code)
+    SELECT 'assessment', new.id, new.fk_course_id, new.name, code
+    FROM courses WHERE id = new.fk_course_id;
+END;
+
+CREATE TRIGGER assessments_search_update AFTER UPDATE OF name, fk_course_id, deleted_at ON assessments
+BEGIN
+    DELETE FROM search_index WHERE kind = 'assessment' AND entity_id = old.id;
+    INSERT INTO search_index (kind, entity_id, course_id, name, code)
+    SELECT 'assessment', new.id, new.fk_course_id, new.name, code
+    FROM courses WHERE id = new.fk_course_id AND new.deleted_at IS NULL;
+END;
+
+CREATE TRIGGER assessments_search_delete AFTER DELETE ON assessments
+BEGIN
+    DELETE FROM search_index WHERE kind = 'assessment' AND entity_id = old.id;
+END;
+
+-- Rows that existed before the triggers
+INSERT INTO search_index (kind, entity_id, course_id, name, code)
+SELECT 'course', id, id, name, code FROM courses WHERE deleted_at IS NULL;
+INSERT INTO search_index (kind, entity_id, course_id, name, code)
+SELECT 'assessment', a.id, a.fk_course_id, a.name, c.code
+FROM assessments a JOIN courses c ON c.id = a.fk_course_id WHERE a.deleted_at IS NULL;
@@ -0,0 +1,13 @@
+-- Duplicates from before the index get their id appended, so the index can be created
+UPDATE courses SET code = code || '-' || id
+WHERE deleted_at IS NULL AND EXISTS (
+    SELECT 1 FROM courses AS other
+    WHERE other.deleted_at IS NULL
+        AND other.id < courses.id
+        AND other.code = courses.code COLLATE NOCASE
+        AND other.semester = courses.semester
+        AND other.year = courses.year
+);
+
+-- Codes repeat across semesters, cloned courses keep theirs
+CREATE UNIQUE INDEX courses_code ON courses (code COLLATE NOCASE, semester, year) WHERE deleted_at IS NULL;
@@ -0,0 +1,6 @@
+CREATE TABLE course_subscriptions (
+    user_id INTEGER NOT NULL,
+    fk_course_id INTEGER NOT NULL,
+    PRIMARY KEY (user_id, fk_course_id),
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
@@ -0,0 +1,17 @@
+-- Enrolling gives the role, reminders are posted in the channel if there is one
+CREATE TABLE course_roles (
+    guild_id INTEGER NOT NULL,
+    fk_course_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    channel_id INTEGER,
+    PRIMARY KEY (guild_id, fk_course_id),
+    FOREIGN KEY (fk_course_id) REFERENCES courses(id)
+);
+
+-- Attempts already reminded of, so restarts don't post them again
+CREATE TABLE sent_reminders (
+    guild_id INTEGER NOT NULL,
+    fk_assessment_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    PRIMARY KEY (guild_id, fk_assessment_id, date)
+);
@@ -0,0 +1,28 @@
+-- Lab groups and the like, a member is in at most one cohort per guild
+CREATE TABLE cohorts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL
+);
+CREATE UNIQUE INDEX cohorts_name ON cohorts (guild_id, name COLLATE NOCASE);
+
+CREATE TABLE cohort_members (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    fk_cohort_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id),
+    FOREIGN KEY (fk_cohort_id) REFERENCES cohorts(id)
+);
+
+-- Assessments with rows here are only for those cohorts of the guild.
+-- NULL keeps the assessment's date of the attempt, an empty one leaves the attempt out
+CREATE TABLE cohort_dates (
+    fk_assessment_id INTEGER NOT NULL,
+    fk_cohort_id INTEGER NOT NULL,
+    take1 TEXT,
+    retake1 TEXT,
+    retake2 TEXT,
+    PRIMARY KEY (fk_assessment_id, fk_cohort_id),
+    FOREIGN KEY (fk_assessment_id) REFERENCES assessments(id),
+    FOREIGN KEY (fk_cohort_id) REFERENCES cohorts(id)
+);
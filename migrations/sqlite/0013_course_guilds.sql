@@ -0,0 +1,38 @@
+-- Courses and terms belong to the guild they were created in, so several guilds can share
+-- a database. Existing rows take the guild from the audit log, or the only guild that used
+-- the bot. Rows that cannot be attributed keep guild 0 and stay hidden until moved
+ALTER TABLE courses ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE terms ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+
+UPDATE courses SET guild_id = (
+    SELECT guild_id FROM audit_log
+    WHERE entity = 'course' AND entity_id = courses.id AND guild_id != 0
+    ORDER BY id LIMIT 1
+)
+WHERE EXISTS (
+    SELECT 1 FROM audit_log
+    WHERE entity = 'course' AND entity_id = courses.id AND guild_id != 0
+);
+UPDATE terms SET guild_id = (
+    SELECT guild_id FROM audit_log
+    WHERE entity = 'term' AND entity_id = terms.id AND guild_id != 0
+    ORDER BY id LIMIT 1
+)
+WHERE EXISTS (
+    SELECT 1 FROM audit_log
+    WHERE entity = 'term' AND entity_id = terms.id AND guild_id != 0
+);
+
+CREATE TEMPORARY TABLE known_guilds AS
+SELECT guild_id FROM audit_log WHERE guild_id != 0
+UNION SELECT guild_id FROM course_editors
+UNION SELECT guild_id FROM course_roles;
+UPDATE courses SET guild_id = (SELECT guild_id FROM known_guilds)
+WHERE guild_id = 0 AND (SELECT COUNT(*) FROM known_guilds) = 1;
+UPDATE terms SET guild_id = (SELECT guild_id FROM known_guilds)
+WHERE guild_id = 0 AND (SELECT COUNT(*) FROM known_guilds) = 1;
+DROP TABLE known_guilds;
+
+-- Every listing is by guild
+CREATE INDEX terms_guild ON terms (guild_id, active);
+CREATE INDEX courses_guild ON courses (guild_id);
@@ -0,0 +1,111 @@
+use poise::serenity_prelude as serenity;
+
+use crate::commands::Data;
+use crate::database_utils::CourseEditor;
+use crate::error::Error;
+use crate::storage::Storage;
+
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+// Discord sends the member's resolved permissions with every interaction
+pub fn is_admin(member: &serenity::Member) -> bool {
+    member
+        .permissions
+        .is_some_and(|permissions| permissions.administrator())
+}
+
+pub fn can_manage_guild(member: &serenity::Member) -> bool {
+    member
+        .permissions
+        .is_some_and(|permissions| permissions.administrator() || permissions.manage_guild())
+}
+
+pub fn grant_applies(editor: &CourseEditor, member: &serenity::Member) -> bool {
+    let user_matches = editor.user_id == Some(member.user.id.0 as i64);
+    let role_matches = editor
+        .role_id
+        .is_some_and(|role_id| member.roles.iter().any(|role| role.0 as i64 == role_id));
+
+    user_matches || role_matches
+}
+
+/// Whether the member is an administrator, otherwise the course grants that apply to them
+async fn grants_of(
+    storage: &dyn Storage,
+    guild_id: serenity::GuildId,
+    member: &serenity::Member,
+) -> Result<(bool, Vec<CourseEditor>), Error> {
+    if is_admin(member) {
+        return Ok((true, Vec::new()));
+    }
+
+    let grants = storage
+        .course_editors(guild_id.0 as i64)
+        .await?
+        .into_iter()
+        .filter(|editor| grant_applies(editor, member))
+        .collect();
+
+    Ok((false, grants))
+}
+
+async fn member_grants(ctx: Context<'_>) -> Result<Option<(bool, Vec<CourseEditor>)>, Error> {
+    match (ctx.guild_id(), ctx.author_member().await) {
+        (Some(guild_id), Some(member)) => Ok(Some(
+            grants_of(ctx.data().storage.as_ref(), guild_id, &member).await?,
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// For interactions that carry the member instead of a command context, false outside of guilds
+pub async fn member_can_edit_course(
+    storage: &dyn Storage,
+    guild_id: Option<serenity::GuildId>,
+    member: Option<&serenity::Member>,
+    course_id: i64,
+) -> Result<bool, Error> {
+    let (admin, grants) = match (guild_id, member) {
+        (Some(guild_id), Some(member)) => grants_of(storage, guild_id, member).await?,
+        _ => return Ok(false),
+    };
+
+    Ok(admin || grants.iter().any(|editor| editor.fk_course_id == course_id))
+}
+
+/// Denials are reported by `on_error` like those of `course_editor_check`
+pub async fn can_edit_course(ctx: Context<'_>, course_id: i64) -> Result<(), Error> {
+    let allowed = match member_grants(ctx).await? {
+        Some((true, _)) => true,
+        Some((false, grants)) => grants.iter().any(|editor| editor.fk_course_id == course_id),
+        None => false,
+    };
+
+    if !allowed {
+        return Err(Error::Permission(format!(
+            "You are not allowed to edit course with id: {}",
+            course_id
+        )));
+    }
+
+    Ok(())
+}
+
+/// Lets through administrators and anyone with at least one course grant,
+/// commands still have to check the specific course with `can_edit_course`.
+/// Denials are reported by `on_error`
+pub async fn course_editor_check(ctx: Context<'_>) -> Result<bool, Error> {
+    let allowed = match member_grants(ctx).await? {
+        Some((true, _)) => true,
+        Some((false, grants)) => !grants.is_empty(),
+        None => false,
+    };
+
+    if !allowed {
+        return Err(Error::Permission(String::from(
+            "You are not allowed to edit any courses",
+        )));
+    }
+
+    Ok(true)
+}
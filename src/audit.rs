@@ -0,0 +1,861 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::course_lookup;
+use crate::database_utils::{
+    self, Assessment, CohortDates, CohortSnapshot, Course, CourseEditor, CourseRole,
+    CourseSnapshot, Session, Subscription, Term,
+};
+use crate::error::Error;
+use crate::rendering::MESSAGE_LIMIT;
+
+pub const COURSE: &str = "course";
+pub const ASSESSMENT: &str = "assessment";
+pub const SESSION: &str = "session";
+pub const TERM: &str = "term";
+pub const COURSE_EDITOR: &str = "course_editor";
+pub const COURSE_ROLE: &str = "course_role";
+pub const SUBSCRIPTION: &str = "subscription";
+pub const COHORT: &str = "cohort";
+// Keyed by assessment, the cohort is in the entry's data
+pub const COHORT_DATES: &str = "cohort_dates";
+
+pub const INSERT: &str = "insert";
+pub const UPDATE: &str = "update";
+pub const DELETE: &str = "delete";
+
+// Discord API limits to 2000 characters per message, see `format_audit_entries`
+pub static AUDIT_ENTRIES_SHOWN: i64 = 15;
+
+/// Who made a change, guild id is 0 for direct messages
+#[derive(Clone, Copy)]
+pub struct Actor {
+    pub guild_id: i64,
+    pub user_id: i64,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor_id: i64,
+    pub created_at: String,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: i64,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub reverted: bool,
+}
+
+#[derive(Clone, Copy, poise::ChoiceParameter)]
+pub enum AuditEntity {
+    Course,
+    Assessment,
+    Session,
+    Term,
+    #[name = "Course editor"]
+    CourseEditor,
+    #[name = "Course role"]
+    CourseRole,
+    Subscription,
+    Cohort,
+    #[name = "Cohort dates"]
+    CohortDates,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Course => COURSE,
+            AuditEntity::Assessment => ASSESSMENT,
+            AuditEntity::Session => SESSION,
+            AuditEntity::Term => TERM,
+            AuditEntity::CourseEditor => COURSE_EDITOR,
+            AuditEntity::CourseRole => COURSE_ROLE,
+            AuditEntity::Subscription => SUBSCRIPTION,
+            AuditEntity::Cohort => COHORT,
+            AuditEntity::CohortDates => COHORT_DATES,
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Option<String> {
+    serde_json::to_string(value).ok()
+}
+
+fn from_json<T: DeserializeOwned>(value: &Option<String>) -> Result<T, Error> {
+    match value {
+        Some(value) => Ok(serde_json::from_str(value)?),
+        None => Err(Error::Internal(String::from(
+            "Audit entry has no data to restore",
+        ))),
+    }
+}
+
+async fn record(
+    connection: &mut SqliteConnection,
+    actor: &Actor,
+    action: &str,
+    entity: &str,
+    entity_id: i64,
+    before: Option<String>,
+    after: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (guild_id, actor_id, action, entity, entity_id, before, after)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        actor.guild_id,
+        actor.user_id,
+        action,
+        entity,
+        entity_id,
+        before,
+        after
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn record_insert<T: Serialize>(
+    connection: &mut SqliteConnection,
+    actor: &Actor,
+    entity: &str,
+    entity_id: i64,
+    after: &T,
+) -> Result<(), sqlx::Error> {
+    record(
+        connection,
+        actor,
+        INSERT,
+        entity,
+        entity_id,
+        None,
+        to_json(after),
+    )
+    .await
+}
+
+pub async fn record_update<T: Serialize>(
+    connection: &mut SqliteConnection,
+    actor: &Actor,
+    entity: &str,
+    entity_id: i64,
+    before: &T,
+    after: &T,
+) -> Result<(), sqlx::Error> {
+    record(
+        connection,
+        actor,
+        UPDATE,
+        entity,
+        entity_id,
+        to_json(before),
+        to_json(after),
+    )
+    .await
+}
+
+pub async fn record_delete<T: Serialize>(
+    connection: &mut SqliteConnection,
+    actor: &Actor,
+    entity: &str,
+    entity_id: i64,
+    before: &T,
+) -> Result<(), sqlx::Error> {
+    record(
+        connection,
+        actor,
+        DELETE,
+        entity,
+        entity_id,
+        to_json(before),
+        None,
+    )
+    .await
+}
+
+/// Newest entries first, filters that are `None` match everything
+pub async fn get_audit_entries(
+    pool: &SqlitePool,
+    guild_id: i64,
+    entity: Option<&str>,
+    entity_id: Option<i64>,
+    actor_id: Option<i64>,
+) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let entries = sqlx::query_as::<_, AuditEntry>(
+        r#"
+        SELECT * FROM audit_log
+        WHERE guild_id = ?
+            AND (? IS NULL OR entity = ?)
+            AND (? IS NULL OR entity_id = ?)
+            AND (? IS NULL OR actor_id = ?)
+        ORDER BY id DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(guild_id)
+    .bind(entity)
+    .bind(entity)
+    .bind(entity_id)
+    .bind(entity_id)
+    .bind(actor_id)
+    .bind(actor_id)
+    .bind(AUDIT_ENTRIES_SHOWN)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Row changes made when reverting an entry, backends run them inside the undo's transaction.
+/// Removing returns `None` if the row is already gone
+#[async_trait]
+pub trait UndoRows: Send {
+    async fn record(
+        &mut self,
+        actor: &Actor,
+        action: &str,
+        entity: &str,
+        entity_id: i64,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> Result<(), Error>;
+
+    /// The actor's latest entry of the guild before the given id
+    async fn previous_entry(
+        &mut self,
+        guild_id: i64,
+        actor_id: i64,
+        id: i64,
+    ) -> Result<Option<AuditEntry>, Error>;
+
+    async fn live_courses(&mut self, guild_id: i64) -> Result<Vec<Course>, Error>;
+    async fn remove_course(&mut self, id: i64) -> Result<Option<CourseSnapshot>, Error>;
+    async fn update_course(&mut self, course: &Course) -> Result<(), Error>;
+    async fn restore_course(&mut self, snapshot: &CourseSnapshot) -> Result<(), Error>;
+    async fn remove_assessment(&mut self, id: i64) -> Result<Option<Assessment>, Error>;
+    async fn restore_assessment(&mut self, assessment: &Assessment) -> Result<(), Error>;
+    async fn remove_session(&mut self, id: i64) -> Result<Option<Session>, Error>;
+    async fn restore_session(&mut self, session: &Session) -> Result<(), Error>;
+    async fn remove_course_editor(&mut self, id: i64) -> Result<Option<CourseEditor>, Error>;
+    async fn restore_course_editor(&mut self, editor: &CourseEditor) -> Result<(), Error>;
+    async fn remove_course_role(
+        &mut self,
+        guild_id: i64,
+        fk_course_id: i64,
+    ) -> Result<Option<CourseRole>, Error>;
+    /// Replaces whatever link the course has in the role's guild
+    async fn restore_course_role(&mut self, role: &CourseRole) -> Result<(), Error>;
+    async fn remove_subscription(
+        &mut self,
+        user_id: i64,
+        fk_course_id: i64,
+    ) -> Result<Option<Subscription>, Error>;
+    async fn restore_subscription(&mut self, subscription: &Subscription) -> Result<(), Error>;
+    async fn remove_cohort(&mut self, id: i64) -> Result<Option<CohortSnapshot>, Error>;
+    async fn restore_cohort(&mut self, snapshot: &CohortSnapshot) -> Result<(), Error>;
+    async fn remove_cohort_dates(
+        &mut self,
+        fk_assessment_id: i64,
+        fk_cohort_id: i64,
+    ) -> Result<Option<CohortDates>, Error>;
+    /// Replaces the dates the assessment has for the cohort
+    async fn restore_cohort_dates(&mut self, dates: &CohortDates) -> Result<(), Error>;
+    async fn remove_term(&mut self, id: i64) -> Result<Option<Term>, Error>;
+    async fn update_term(&mut self, term: &Term) -> Result<(), Error>;
+    async fn restore_term(&mut self, term: &Term) -> Result<(), Error>;
+}
+
+// Another course may have taken the code while this one was edited or in the trash
+async fn ensure_unique_code(rows: &mut dyn UndoRows, course: &Course) -> Result<(), Error> {
+    let courses = rows.live_courses(course.guild_id).await?;
+    course_lookup::check_unique_code(&courses, course)
+}
+
+// Inserting or activating a term first deactivates the guild's active ones, so their entries
+// come right before its own. Archiving deactivates too but also sets the term archived
+async fn reactivate_previous_terms(
+    rows: &mut dyn UndoRows,
+    entry: &AuditEntry,
+    actor: &Actor,
+) -> Result<(), Error> {
+    let mut id = entry.id;
+    while let Some(previous) = rows
+        .previous_entry(actor.guild_id, entry.actor_id, id)
+        .await?
+    {
+        id = previous.id;
+        if (previous.entity.as_str(), previous.action.as_str()) != (TERM, UPDATE) {
+            break;
+        }
+        let before: Term = from_json(&previous.before)?;
+        let after: Term = from_json(&previous.after)?;
+        if !before.active || after.active || after.archived {
+            break;
+        }
+
+        rows.update_term(&before).await?;
+        rows.record(
+            actor,
+            UPDATE,
+            TERM,
+            before.id,
+            to_json(&after),
+            to_json(&before),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Makes the opposite change of the entry and records it as a new entry
+pub async fn revert(
+    rows: &mut dyn UndoRows,
+    entry: &AuditEntry,
+    actor: &Actor,
+) -> Result<(), Error> {
+    let entity_id = entry.entity_id;
+
+    match (entry.entity.as_str(), entry.action.as_str()) {
+        (COURSE, INSERT) => {
+            if let Some(snapshot) = rows.remove_course(entity_id).await? {
+                rows.record(actor, DELETE, COURSE, entity_id, to_json(&snapshot), None)
+                    .await?;
+            }
+        }
+        (COURSE, UPDATE) => {
+            let before: Course = from_json(&entry.before)?;
+            let current: Course = from_json(&entry.after)?;
+            ensure_unique_code(rows, &before).await?;
+            rows.update_course(&before).await?;
+            rows.record(
+                actor,
+                UPDATE,
+                COURSE,
+                entity_id,
+                to_json(&current),
+                to_json(&before),
+            )
+            .await?;
+        }
+        (COURSE, DELETE) => {
+            let snapshot: CourseSnapshot = from_json(&entry.before)?;
+            ensure_unique_code(rows, &snapshot.course).await?;
+            rows.restore_course(&snapshot).await?;
+            rows.record(
+                actor,
+                INSERT,
+                COURSE,
+                entity_id,
+                None,
+                to_json(&snapshot.course),
+            )
+            .await?;
+        }
+        (ASSESSMENT, INSERT) => {
+            if let Some(assessment) = rows.remove_assessment(entity_id).await? {
+                rows.record(
+                    actor,
+                    DELETE,
+                    ASSESSMENT,
+                    entity_id,
+                    to_json(&assessment),
+                    None,
+                )
+                .await?;
+            }
+        }
+        (ASSESSMENT, DELETE) => {
+            let assessment: Assessment = from_json(&entry.before)?;
+            rows.restore_assessment(&assessment).await?;
+            rows.record(
+                actor,
+                INSERT,
+                ASSESSMENT,
+                entity_id,
+                None,
+                to_json(&assessment),
+            )
+            .await?;
+        }
+        (SESSION, INSERT) => {
+            if let Some(session) = rows.remove_session(entity_id).await? {
+                rows.record(actor, DELETE, SESSION, entity_id, to_json(&session), None)
+                    .await?;
+            }
+        }
+        (SESSION, DELETE) => {
+            let session: Session = from_json(&entry.before)?;
+            rows.restore_session(&session).await?;
+            rows.record(actor, INSERT, SESSION, entity_id, None, to_json(&session))
+                .await?;
+        }
+        (COURSE_EDITOR, INSERT) => {
+            if let Some(editor) = rows.remove_course_editor(entity_id).await? {
+                rows.record(
+                    actor,
+                    DELETE,
+                    COURSE_EDITOR,
+                    entity_id,
+                    to_json(&editor),
+                    None,
+                )
+                .await?;
+            }
+        }
+        (COURSE_EDITOR, DELETE) => {
+            let editor: CourseEditor = from_json(&entry.before)?;
+            rows.restore_course_editor(&editor).await?;
+            rows.record(
+                actor,
+                INSERT,
+                COURSE_EDITOR,
+                entity_id,
+                None,
+                to_json(&editor),
+            )
+            .await?;
+        }
+        // Course roles are keyed by course, the entry's guild is the actor's since undo is per guild
+        (COURSE_ROLE, INSERT) => {
+            if let Some(role) = rows.remove_course_role(actor.guild_id, entity_id).await? {
+                rows.record(actor, DELETE, COURSE_ROLE, entity_id, to_json(&role), None)
+                    .await?;
+            }
+        }
+        (COURSE_ROLE, UPDATE) => {
+            let before: CourseRole = from_json(&entry.before)?;
+            let current: CourseRole = from_json(&entry.after)?;
+            rows.restore_course_role(&before).await?;
+            rows.record(
+                actor,
+                UPDATE,
+                COURSE_ROLE,
+                entity_id,
+                to_json(&current),
+                to_json(&before),
+            )
+            .await?;
+        }
+        (COURSE_ROLE, DELETE) => {
+            let role: CourseRole = from_json(&entry.before)?;
+            rows.restore_course_role(&role).await?;
+            rows.record(actor, INSERT, COURSE_ROLE, entity_id, None, to_json(&role))
+                .await?;
+        }
+        (SUBSCRIPTION, INSERT) => {
+            let subscription: Subscription = from_json(&entry.after)?;
+            if let Some(subscription) = rows
+                .remove_subscription(subscription.user_id, subscription.fk_course_id)
+                .await?
+            {
+                rows.record(
+                    actor,
+                    DELETE,
+                    SUBSCRIPTION,
+                    entity_id,
+                    to_json(&subscription),
+                    None,
+                )
+                .await?;
+            }
+        }
+        (SUBSCRIPTION, DELETE) => {
+            let subscription: Subscription = from_json(&entry.before)?;
+            rows.restore_subscription(&subscription).await?;
+            rows.record(
+                actor,
+                INSERT,
+                SUBSCRIPTION,
+                entity_id,
+                None,
+                to_json(&subscription),
+            )
+            .await?;
+        }
+        (COHORT, INSERT) => {
+            if let Some(snapshot) = rows.remove_cohort(entity_id).await? {
+                rows.record(actor, DELETE, COHORT, entity_id, to_json(&snapshot), None)
+                    .await?;
+            }
+        }
+        (COHORT, DELETE) => {
+            let snapshot: CohortSnapshot = from_json(&entry.before)?;
+            rows.restore_cohort(&snapshot).await?;
+            rows.record(
+                actor,
+                INSERT,
+                COHORT,
+                entity_id,
+                None,
+                to_json(&snapshot.cohort),
+            )
+            .await?;
+        }
+        (COHORT_DATES, INSERT) => {
+            let dates: CohortDates = from_json(&entry.after)?;
+            if let Some(dates) = rows
+                .remove_cohort_dates(dates.fk_assessment_id, dates.fk_cohort_id)
+                .await?
+            {
+                rows.record(
+                    actor,
+                    DELETE,
+                    COHORT_DATES,
+                    entity_id,
+                    to_json(&dates),
+                    None,
+                )
+                .await?;
+            }
+        }
+        (COHORT_DATES, UPDATE) => {
+            let before: CohortDates = from_json(&entry.before)?;
+            let current: CohortDates = from_json(&entry.after)?;
+            rows.restore_cohort_dates(&before).await?;
+            rows.record(
+                actor,
+                UPDATE,
+                COHORT_DATES,
+                entity_id,
+                to_json(&current),
+                to_json(&before),
+            )
+            .await?;
+        }
+        (COHORT_DATES, DELETE) => {
+            let dates: CohortDates = from_json(&entry.before)?;
+            rows.restore_cohort_dates(&dates).await?;
+            rows.record(
+                actor,
+                INSERT,
+                COHORT_DATES,
+                entity_id,
+                None,
+                to_json(&dates),
+            )
+            .await?;
+        }
+        (TERM, INSERT) => {
+            if let Some(term) = rows.remove_term(entity_id).await? {
+                rows.record(actor, DELETE, TERM, entity_id, to_json(&term), None)
+                    .await?;
+                if term.active {
+                    reactivate_previous_terms(rows, entry, actor).await?;
+                }
+            }
+        }
+        (TERM, UPDATE) => {
+            let before: Term = from_json(&entry.before)?;
+            let current: Term = from_json(&entry.after)?;
+            rows.update_term(&before).await?;
+            rows.record(
+                actor,
+                UPDATE,
+                TERM,
+                entity_id,
+                to_json(&current),
+                to_json(&before),
+            )
+            .await?;
+            if current.active && !before.active {
+                reactivate_previous_terms(rows, entry, actor).await?;
+            }
+        }
+        (TERM, DELETE) => {
+            let term: Term = from_json(&entry.before)?;
+            rows.restore_term(&term).await?;
+            rows.record(actor, INSERT, TERM, entity_id, None, to_json(&term))
+                .await?;
+        }
+        (entity, action) => {
+            return Err(Error::Validation(format!(
+                "Undoing {} of {} is not supported",
+                action, entity
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if the entry was already undone
+pub fn check_undoable(entry: Option<AuditEntry>, id: i64) -> Result<AuditEntry, Error> {
+    let entry = entry.ok_or(Error::NotFound(format!(
+        "Audit entry not found with id: {}",
+        id
+    )))?;
+
+    if entry.reverted {
+        return Err(Error::Validation(format!(
+            "Audit entry {} was already undone",
+            id
+        )));
+    }
+
+    Ok(entry)
+}
+
+/// Reverts a single audit entry, the revert itself is recorded as a new entry
+pub async fn undo(pool: &SqlitePool, id: i64, actor: &Actor) -> Result<AuditEntry, Error> {
+    let mut transaction = pool.begin().await?;
+
+    let entry =
+        sqlx::query_as::<_, AuditEntry>("SELECT * FROM audit_log WHERE id = ? AND guild_id = ?")
+            .bind(id)
+            .bind(actor.guild_id)
+            .fetch_optional(&mut *transaction)
+            .await?;
+    let entry = check_undoable(entry, id)?;
+
+    let connection: &mut SqliteConnection = &mut transaction;
+    revert(connection, &entry, actor).await?;
+
+    sqlx::query!("UPDATE audit_log SET reverted = 1 WHERE id = ?", id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(entry)
+}
+
+#[async_trait]
+impl UndoRows for SqliteConnection {
+    async fn record(
+        &mut self,
+        actor: &Actor,
+        action: &str,
+        entity: &str,
+        entity_id: i64,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> Result<(), Error> {
+        Ok(record(self, actor, action, entity, entity_id, before, after).await?)
+    }
+
+    async fn previous_entry(
+        &mut self,
+        guild_id: i64,
+        actor_id: i64,
+        id: i64,
+    ) -> Result<Option<AuditEntry>, Error> {
+        let entry = sqlx::query_as::<_, AuditEntry>(
+            r#"
+            SELECT * FROM audit_log
+            WHERE guild_id = ? AND actor_id = ? AND id < ?
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(guild_id)
+        .bind(actor_id)
+        .bind(id)
+        .fetch_optional(self)
+        .await?;
+
+        Ok(entry)
+    }
+
+    async fn live_courses(&mut self, guild_id: i64) -> Result<Vec<Course>, Error> {
+        Ok(database_utils::live_course_rows(self, guild_id).await?)
+    }
+
+    async fn remove_course(&mut self, id: i64) -> Result<Option<CourseSnapshot>, Error> {
+        Ok(database_utils::remove_course_rows(self, id).await?)
+    }
+
+    async fn update_course(&mut self, course: &Course) -> Result<(), Error> {
+        Ok(database_utils::update_course_row(self, course).await?)
+    }
+
+    async fn restore_course(&mut self, snapshot: &CourseSnapshot) -> Result<(), Error> {
+        Ok(database_utils::restore_course_rows(self, snapshot).await?)
+    }
+
+    async fn remove_assessment(&mut self, id: i64) -> Result<Option<Assessment>, Error> {
+        Ok(database_utils::remove_assessment_row(self, id).await?)
+    }
+
+    async fn restore_assessment(&mut self, assessment: &Assessment) -> Result<(), Error> {
+        Ok(database_utils::restore_assessment_row(self, assessment).await?)
+    }
+
+    async fn remove_session(&mut self, id: i64) -> Result<Option<Session>, Error> {
+        Ok(database_utils::remove_session_row(self, id).await?)
+    }
+
+    async fn restore_session(&mut self, session: &Session) -> Result<(), Error> {
+        Ok(database_utils::restore_session_row(self, session).await?)
+    }
+
+    async fn remove_course_editor(&mut self, id: i64) -> Result<Option<CourseEditor>, Error> {
+        Ok(database_utils::remove_course_editor_row(self, id).await?)
+    }
+
+    async fn restore_course_editor(&mut self, editor: &CourseEditor) -> Result<(), Error> {
+        Ok(database_utils::restore_course_editor_row(self, editor).await?)
+    }
+
+    async fn remove_course_role(
+        &mut self,
+        guild_id: i64,
+        fk_course_id: i64,
+    ) -> Result<Option<CourseRole>, Error> {
+        Ok(database_utils::remove_course_role_row(self, guild_id, fk_course_id).await?)
+    }
+
+    async fn restore_course_role(&mut self, role: &CourseRole) -> Result<(), Error> {
+        Ok(database_utils::restore_course_role_row(self, role).await?)
+    }
+
+    async fn remove_subscription(
+        &mut self,
+        user_id: i64,
+        fk_course_id: i64,
+    ) -> Result<Option<Subscription>, Error> {
+        Ok(database_utils::remove_subscription_row(self, user_id, fk_course_id).await?)
+    }
+
+    async fn restore_subscription(&mut self, subscription: &Subscription) -> Result<(), Error> {
+        database_utils::restore_subscription_row(self, subscription).await?;
+        Ok(())
+    }
+
+    async fn remove_cohort(&mut self, id: i64) -> Result<Option<CohortSnapshot>, Error> {
+        Ok(database_utils::remove_cohort_rows(self, id).await?)
+    }
+
+    async fn restore_cohort(&mut self, snapshot: &CohortSnapshot) -> Result<(), Error> {
+        Ok(database_utils::restore_cohort_rows(self, snapshot).await?)
+    }
+
+    async fn remove_cohort_dates(
+        &mut self,
+        fk_assessment_id: i64,
+        fk_cohort_id: i64,
+    ) -> Result<Option<CohortDates>, Error> {
+        Ok(database_utils::remove_cohort_dates_row(self, fk_assessment_id, fk_cohort_id).await?)
+    }
+
+    async fn restore_cohort_dates(&mut self, dates: &CohortDates) -> Result<(), Error> {
+        Ok(database_utils::restore_cohort_dates_row(self, dates).await?)
+    }
+
+    async fn remove_term(&mut self, id: i64) -> Result<Option<Term>, Error> {
+        Ok(database_utils::remove_term_row(self, id).await?)
+    }
+
+    async fn update_term(&mut self, term: &Term) -> Result<(), Error> {
+        Ok(database_utils::update_term_row(self, term).await?)
+    }
+
+    async fn restore_term(&mut self, term: &Term) -> Result<(), Error> {
+        Ok(database_utils::restore_term_row(self, term).await?)
+    }
+}
+
+// Snapshots keep the course under "course" and the cohort under "cohort",
+// everything else is stored as is
+fn entry_label(entry: &AuditEntry) -> String {
+    let data = entry.after.as_ref().or(entry.before.as_ref());
+    let value: serde_json::Value = match data.and_then(|data| serde_json::from_str(data).ok()) {
+        Some(value) => value,
+        None => return String::new(),
+    };
+    let value = value
+        .get("course")
+        .or(value.get("cohort"))
+        .unwrap_or(&value);
+
+    match (value.get("code"), value.get("name")) {
+        (Some(code), Some(name)) => format!(
+            " ({} {})",
+            code.as_str().unwrap_or(""),
+            name.as_str().unwrap_or("")
+        ),
+        (None, Some(name)) => format!(" ({})", name.as_str().unwrap_or("")),
+        _ => String::new(),
+    }
+}
+
+fn format_entry(entry: &AuditEntry) -> String {
+    let action = match entry.action.as_str() {
+        INSERT => "inserted",
+        UPDATE => "updated",
+        DELETE => "deleted",
+        other => other,
+    };
+
+    format!(
+        "`#{}` {} <@{}> {} {} {}{}{}\n",
+        entry.id,
+        entry.created_at,
+        entry.actor_id,
+        action,
+        entry.entity.replace('_', " "),
+        entry.entity_id,
+        entry_label(entry),
+        if entry.reverted { " *(undone)*" } else { "" }
+    )
+}
+
+/// Older entries that do not fit in one message are counted instead
+pub fn format_audit_entries(entries: &[AuditEntry]) -> String {
+    if entries.is_empty() {
+        return String::from("No audit entries found");
+    }
+
+    let not_shown = |hidden: usize| format!("...and {} older entries not shown", hidden);
+    let mut content = String::from("# Audit log\n");
+    for (index, entry) in entries.iter().enumerate() {
+        let line = format_entry(entry);
+        let rest = entries.len() - index - 1;
+        let room = match rest {
+            0 => 0,
+            _ => not_shown(rest).chars().count(),
+        };
+        if content.chars().count() + line.chars().count() + room > MESSAGE_LIMIT {
+            content += &not_shown(entries.len() - index);
+            break;
+        }
+        content += &line;
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_audit_entries_fit_in_one_message() {
+        let course = serde_json::json!({ "code": "ABC101", "name": "N".repeat(100) });
+        let entries: Vec<AuditEntry> = (1..=AUDIT_ENTRIES_SHOWN)
+            .map(|id| AuditEntry {
+                id,
+                actor_id: 123456789012345678,
+                created_at: String::from("2024-01-01 12:00:00"),
+                action: String::from(UPDATE),
+                entity: String::from(COURSE),
+                entity_id: id,
+                before: Some(course.to_string()),
+                after: Some(course.to_string()),
+                reverted: true,
+            })
+            .collect();
+
+        let content = format_audit_entries(&entries);
+        assert!(content.chars().count() <= MESSAGE_LIMIT);
+        assert!(content.contains("`#1` "));
+        assert!(content.ends_with("older entries not shown"));
+    }
+}
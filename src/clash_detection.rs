@@ -0,0 +1,374 @@
+use chrono::NaiveDate;
+
+use crate::database_utils::{Assessment, Course};
+use crate::rendering::MESSAGE_LIMIT;
+use crate::utils::week_start;
+
+// Discord API limits to 2000 characters per message, the heat map gets what the clashes leave
+pub static CLASHES_SHOWN: usize = 5;
+pub static WORKLOAD_WEEKS_SHOWN: usize = 26;
+
+const ATTEMPTS: [&str; 3] = ["take 1", "retake 1", "retake 2"];
+const HEAT_MAP_WIDTH: f64 = 20.0;
+
+#[derive(Clone)]
+pub struct Clash {
+    pub date: NaiveDate,
+    pub first_attempt: &'static str,
+    pub second_attempt: &'static str,
+    pub first: Assessment,
+    pub second: Assessment,
+}
+
+/// Total weight of a week within one semester/year
+pub struct WeekLoad {
+    pub week_start: NaiveDate,
+    pub semester: i64,
+    pub year: i64,
+    pub weight: f64,
+}
+
+fn attempt_dates(assessment: &Assessment) -> [Option<NaiveDate>; 3] {
+    [&assessment.take1, &assessment.retake1, &assessment.retake2]
+        .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+fn term(courses: &[Course], assessment: &Assessment) -> Option<(i64, i64)> {
+    courses
+        .iter()
+        .find(|course| course.id == assessment.fk_course_id)
+        .map(|course| (course.semester, course.year))
+}
+
+fn same_term(courses: &[Course], first: &Assessment, second: &Assessment) -> bool {
+    match (term(courses, first), term(courses, second)) {
+        (Some(first), Some(second)) => first == second,
+        _ => false,
+    }
+}
+
+/// Finds pairs of assessments of the same semester/year with any two attempts on the same day,
+/// a retake can clash with another assessment's first take
+pub fn find_clashes(courses: &[Course], assessments: &[Assessment]) -> Vec<Clash> {
+    let mut clashes = Vec::new();
+
+    for (index, first) in assessments.iter().enumerate() {
+        for second in &assessments[index + 1..] {
+            if !same_term(courses, first, second) {
+                continue;
+            }
+
+            let second_dates = attempt_dates(second);
+            for (first_attempt, first_date) in attempt_dates(first).into_iter().enumerate() {
+                for (second_attempt, second_date) in second_dates.iter().enumerate() {
+                    if let (Some(first_date), Some(second_date)) = (first_date, second_date) {
+                        if first_date == *second_date {
+                            clashes.push(Clash {
+                                date: first_date,
+                                first_attempt: ATTEMPTS[first_attempt],
+                                second_attempt: ATTEMPTS[second_attempt],
+                                first: first.clone(),
+                                second: second.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    clashes.sort_by_key(|clash| clash.date);
+    clashes
+}
+
+/// Sums assessment weights per week and semester/year using the first attempt date,
+/// students only take the courses of one term so terms are never added up
+pub fn weekly_workload(courses: &[Course], assessments: &[Assessment]) -> Vec<WeekLoad> {
+    let mut weeks: Vec<WeekLoad> = Vec::new();
+
+    for assessment in assessments {
+        let (date, (semester, year)) =
+            match (attempt_dates(assessment)[0], term(courses, assessment)) {
+                (Some(date), Some(term)) => (date, term),
+                _ => continue,
+            };
+
+        let monday = week_start(date);
+        match weeks.iter_mut().find(|week| {
+            week.week_start == monday && week.semester == semester && week.year == year
+        }) {
+            Some(week) => week.weight += assessment.weight,
+            None => weeks.push(WeekLoad {
+                week_start: monday,
+                semester,
+                year,
+                weight: assessment.weight,
+            }),
+        }
+    }
+
+    weeks.sort_by_key(|week| (week.week_start, week.year, week.semester));
+    weeks
+}
+
+fn assessment_label(courses: &[Course], assessment: &Assessment) -> String {
+    match courses
+        .iter()
+        .find(|course| course.id == assessment.fk_course_id)
+    {
+        Some(course) => format!("{} {}", course.code, assessment.name),
+        None => assessment.name.clone(),
+    }
+}
+
+pub fn format_clashes(courses: &[Course], clashes: &[Clash]) -> String {
+    if clashes.is_empty() {
+        return String::from("No clashes found\n");
+    }
+
+    let mut content = String::new();
+    for clash in clashes.iter().take(CLASHES_SHOWN) {
+        content += &format!(
+            "- **{}**: {} ({}) / {} ({})\n",
+            clash.date.format("%Y-%m-%d"),
+            assessment_label(courses, &clash.first),
+            clash.first_attempt,
+            assessment_label(courses, &clash.second),
+            clash.second_attempt
+        );
+    }
+
+    if clashes.len() > CLASHES_SHOWN {
+        content += &format!("...and {} more\n", clashes.len() - CLASHES_SHOWN);
+    }
+
+    content
+}
+
+/// Heat map of at most `room` characters, weeks that do not fit are counted instead
+pub fn build_workload_heat_map(weeks: &[WeekLoad], max_weight: f64, room: usize) -> String {
+    const FENCE: &str = "```ansi\n```";
+    let mut heat_map = String::new();
+    let mut shown = 0;
+
+    for week in weeks.iter().take(WORKLOAD_WEEKS_SHOWN) {
+        let ratio = if max_weight > 0.0 {
+            week.weight / max_weight
+        } else {
+            1.0
+        };
+        let filled = ((ratio * HEAT_MAP_WIDTH).round() as usize).min(HEAT_MAP_WIDTH as usize);
+
+        let color = if ratio > 1.0 {
+            "\u{001b}[31m"
+        } else if ratio >= 0.75 {
+            "\u{001b}[33m"
+        } else {
+            "\u{001b}[32m"
+        };
+
+        let line = format!(
+            "{} S{} {} {}{}{}{} {}\n",
+            week.week_start.format("%Y-%m-%d"),
+            week.semester,
+            week.year,
+            color,
+            "█".repeat(filled),
+            "\u{001b}[0m",
+            "░".repeat(HEAT_MAP_WIDTH as usize - filled),
+            week.weight
+        );
+        // Room is kept for the fences and the count of the weeks left out
+        let left_out = format!("...and {} more weeks\n", weeks.len());
+        let used = FENCE.chars().count() + heat_map.chars().count() + left_out.chars().count();
+        if used + line.chars().count() > room {
+            break;
+        }
+        heat_map += &line;
+        shown += 1;
+    }
+
+    if weeks.is_empty() {
+        heat_map = String::from("No dated assessments\n");
+    }
+    if weeks.len() > shown {
+        heat_map += &format!("...and {} more weeks\n", weeks.len() - shown);
+    }
+
+    String::from("```ansi\n") + &heat_map + "```"
+}
+
+/// The `/clashes` message, the heat map is cut to keep it within a single message
+pub fn format_report(
+    courses: &[Course],
+    clashes: &[Clash],
+    weeks: &[WeekLoad],
+    max_weight: f64,
+) -> String {
+    let content = format!(
+        "# Clashes\n{}# Weekly workload (limit {})\n",
+        format_clashes(courses, clashes),
+        max_weight
+    );
+    let room = MESSAGE_LIMIT.saturating_sub(content.chars().count());
+
+    content + &build_workload_heat_map(weeks, max_weight, room)
+}
+
+/// Warnings shown after inserting an assessment, empty if there is nothing to report
+pub fn format_insert_warnings(
+    courses: &[Course],
+    assessments: &[Assessment],
+    inserted: &Assessment,
+    max_weight: f64,
+) -> String {
+    let is_inserted = |assessment: &Assessment| {
+        assessment.fk_course_id == inserted.fk_course_id && assessment.name == inserted.name
+    };
+
+    let clashes: Vec<Clash> = find_clashes(courses, assessments)
+        .into_iter()
+        .filter(|clash| is_inserted(&clash.first) || is_inserted(&clash.second))
+        .collect();
+
+    let mut content = String::new();
+    if !clashes.is_empty() {
+        content += &format!(
+            "**Warning, clashes found:**\n{}",
+            format_clashes(courses, &clashes)
+        );
+    }
+
+    if let Some(date) = attempt_dates(inserted)[0] {
+        let term_assessments = term_assessments(courses, assessments, inserted.fk_course_id);
+        let monday = week_start(date);
+        if let Some(week) = weekly_workload(courses, &term_assessments)
+            .into_iter()
+            .find(|week| week.week_start == monday && week.weight > max_weight)
+        {
+            content += &format!(
+                "**Warning, week of {} has total weight {} (limit {})**\n",
+                monday.format("%Y-%m-%d"),
+                week.weight,
+                max_weight
+            );
+        }
+    }
+
+    content
+}
+
+/// Assessments of courses in the same semester/year as the given course
+pub fn term_assessments(
+    courses: &[Course],
+    assessments: &[Assessment],
+    course_id: i64,
+) -> Vec<Assessment> {
+    let course = match courses.iter().find(|course| course.id == course_id) {
+        Some(course) => course,
+        None => return Vec::new(),
+    };
+
+    assessments
+        .iter()
+        .filter(|assessment| {
+            courses.iter().any(|other| {
+                other.id == assessment.fk_course_id
+                    && other.semester == course.semester
+                    && other.year == course.year
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    fn courses() -> Vec<Course> {
+        [("CSE101", 1), ("MTH101", 1), ("PHY201", 2)]
+            .iter()
+            .enumerate()
+            .map(|(index, (code, semester))| Course {
+                id: index as i64 + 1,
+                semester: *semester,
+                ..course(code, None)
+            })
+            .collect()
+    }
+
+    fn dated(name: &str, fk_course_id: i64, dates: [&str; 3]) -> Assessment {
+        Assessment {
+            take1: dates[0].to_string(),
+            retake1: dates[1].to_string(),
+            retake2: dates[2].to_string(),
+            ..assessment(name, fk_course_id)
+        }
+    }
+
+    #[test]
+    fn retakes_clash_with_other_attempts() {
+        let assessments = [
+            dated("Exam", 1, ["2024-01-10", "2024-02-10", ""]),
+            dated("Quiz", 2, ["2024-02-10", "", ""]),
+            dated("Lab", 3, ["2024-01-10", "", ""]),
+        ];
+
+        let clashes = find_clashes(&courses(), &assessments);
+        assert_eq!(clashes.len(), 1);
+        assert_eq!(
+            (clashes[0].first_attempt, clashes[0].second_attempt),
+            ("retake 1", "take 1")
+        );
+        assert_eq!(
+            format_clashes(&courses(), &clashes),
+            "- **2024-02-10**: CSE101 Exam (retake 1) / MTH101 Quiz (take 1)\n"
+        );
+    }
+
+    #[test]
+    fn workload_is_summed_per_term() {
+        let assessments = [
+            dated("Exam", 1, ["2024-01-10", "", ""]),
+            dated("Quiz", 2, ["2024-01-11", "", ""]),
+            dated("Lab", 3, ["2024-01-12", "", ""]),
+        ];
+
+        let weeks = weekly_workload(&courses(), &assessments);
+        let loads: Vec<(i64, f64)> = weeks
+            .iter()
+            .map(|week| (week.semester, week.weight))
+            .collect();
+        assert_eq!(loads, [(1, 100.0), (2, 50.0)]);
+    }
+
+    #[test]
+    fn report_fits_in_one_message() {
+        let courses: Vec<Course> = (1..=2)
+            .map(|id| Course {
+                id,
+                ..course(&"C".repeat(10), None)
+            })
+            .collect();
+        let assessments: Vec<Assessment> = (0..40)
+            .map(|index| {
+                let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+                    + chrono::Duration::weeks(index / 2);
+                let date = date.format("%Y-%m-%d").to_string();
+                Assessment {
+                    weight: 999.0,
+                    ..dated(&"y".repeat(80), index % 2 + 1, [&date, &date, &date])
+                }
+            })
+            .collect();
+
+        let clashes = find_clashes(&courses, &assessments);
+        let weeks = weekly_workload(&courses, &assessments);
+        let report = format_report(&courses, &clashes, &weeks, 0.5);
+        assert!(report.chars().count() <= MESSAGE_LIMIT);
+        assert!(report.contains("more weeks"));
+        assert!(report.ends_with("```"));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::database_utils::{Assessment, Cohort, CohortDates};
+
+/// The assessment with the cohort's dates, attempts without one keep the assessment's date
+fn with_dates(assessment: &Assessment, dates: &CohortDates) -> Assessment {
+    let date = |cohort_date: &Option<String>, own: &String| match cohort_date {
+        Some(date) => date.clone(),
+        None => own.clone(),
+    };
+
+    Assessment {
+        take1: date(&dates.take1, &assessment.take1),
+        retake1: date(&dates.retake1, &assessment.retake1),
+        retake2: date(&dates.retake2, &assessment.retake2),
+        ..assessment.clone()
+    }
+}
+
+/// The assessments as a member of the cohort sees them. Assessments assigned to cohorts are only
+/// shown to those cohorts, with their dates. Members without a cohort see every assessment as it is
+pub fn cohort_assessments(
+    assessments: Vec<Assessment>,
+    all_dates: &[CohortDates],
+    cohort_id: Option<i64>,
+) -> Vec<Assessment> {
+    let cohort_id = match cohort_id {
+        Some(cohort_id) => cohort_id,
+        None => return assessments,
+    };
+
+    assessments
+        .into_iter()
+        .filter_map(|assessment| {
+            let mut assigned = all_dates
+                .iter()
+                .filter(|dates| dates.fk_assessment_id == assessment.id)
+                .peekable();
+            if assigned.peek().is_none() {
+                return Some(assessment);
+            }
+
+            assigned
+                .find(|dates| dates.fk_cohort_id == cohort_id)
+                .map(|dates| with_dates(&assessment, dates))
+        })
+        .collect()
+}
+
+/// Every cohort's version of the assessments, labelled with the cohort's name.
+/// Assessments not assigned to any cohort are everyone's and have no label
+pub fn cohort_versions(
+    assessments: &[Assessment],
+    cohorts: &[Cohort],
+    all_dates: &[CohortDates],
+) -> Vec<(Assessment, Option<String>)> {
+    let mut versions = Vec::new();
+    for assessment in assessments {
+        let assigned: Vec<&CohortDates> = all_dates
+            .iter()
+            .filter(|dates| dates.fk_assessment_id == assessment.id)
+            .collect();
+        if assigned.is_empty() {
+            versions.push((assessment.clone(), None));
+            continue;
+        }
+
+        for dates in assigned {
+            let name = cohorts
+                .iter()
+                .find(|cohort| cohort.id == dates.fk_cohort_id)
+                .map(|cohort| cohort.name.clone());
+            versions.push((with_dates(assessment, dates), name));
+        }
+    }
+
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    fn dates(fk_assessment_id: i64, fk_cohort_id: i64, take1: Option<&str>) -> CohortDates {
+        CohortDates {
+            fk_assessment_id,
+            fk_cohort_id,
+            take1: take1.map(String::from),
+            retake1: None,
+            retake2: Some(String::new()),
+        }
+    }
+
+    #[test]
+    fn cohorts_see_their_assessments_with_their_dates() {
+        let assessments = vec![
+            Assessment {
+                id: 1,
+                ..assessment("Exam", 1)
+            },
+            Assessment {
+                id: 2,
+                ..assessment("Lab", 1)
+            },
+        ];
+        let all_dates = [dates(2, 10, Some("2024-02-01")), dates(2, 20, None)];
+
+        let names = |assessments: &[Assessment]| -> Vec<String> {
+            assessments
+                .iter()
+                .map(|assessment| assessment.name.clone())
+                .collect()
+        };
+        let everyone = cohort_assessments(assessments.clone(), &all_dates, None);
+        assert_eq!(names(&everyone), ["Exam", "Lab"]);
+        assert_eq!(everyone[1].take1, "2024-01-10");
+        let other = cohort_assessments(assessments.clone(), &all_dates, Some(30));
+        assert_eq!(names(&other), ["Exam"]);
+
+        let group_a = cohort_assessments(assessments.clone(), &all_dates, Some(10));
+        assert_eq!(names(&group_a), ["Exam", "Lab"]);
+        let lab = &group_a[1];
+        assert_eq!(
+            (
+                lab.take1.as_str(),
+                lab.retake1.as_str(),
+                lab.retake2.as_str()
+            ),
+            ("2024-02-01", "2024-01-20", "")
+        );
+        let group_b = cohort_assessments(assessments.clone(), &all_dates, Some(20));
+        assert_eq!(group_b[1].take1, "2024-01-10");
+
+        let cohorts = [
+            Cohort {
+                id: 10,
+                guild_id: 1,
+                name: String::from("Group A"),
+            },
+            Cohort {
+                id: 20,
+                guild_id: 1,
+                name: String::from("Group B"),
+            },
+        ];
+        let labels: Vec<Option<String>> = cohort_versions(&assessments, &cohorts, &all_dates)
+            .into_iter()
+            .map(|(_, label)| label)
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                None,
+                Some(String::from("Group A")),
+                Some(String::from("Group B"))
+            ]
+        );
+    }
+}
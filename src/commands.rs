@@ -1,15 +1,413 @@
+use std::borrow::Cow;
 use std::result::Result;
+use std::sync::Arc;
 
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use poise::serenity_prelude as serenity;
 
+use crate::access_control;
+use crate::audit::{self as audit_log, Actor};
+use crate::clash_detection;
+use crate::cohorts;
+use crate::config;
+use crate::course_lookup;
+use crate::course_roles;
 use crate::database_utils;
+use crate::error::Error;
+use crate::ical;
+use crate::list_view::{AssessmentSort, AssessmentView, CourseSort, CourseView};
+use crate::rendering::{self, RenderOptions, ThemeName};
+use crate::storage::Storage;
 use crate::utils;
 
-pub struct Data {} // User data, which is stored and accessible in all command invocations
-type Error = Box<dyn std::error::Error + Send + Sync>;
+// User data, which is stored and accessible in all command invocations
+pub struct Data {
+    pub storage: Arc<dyn Storage>,
+}
 type Context<'a> = poise::Context<'a, Data, Error>;
 
-#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+fn actor(ctx: Context<'_>) -> Actor {
+    Actor {
+        guild_id: ctx.guild_id().map_or(0, |guild_id| guild_id.0 as i64),
+        user_id: ctx.author().id.0 as i64,
+    }
+}
+
+/// A page of a list command, `total` is the number of listed items
+pub struct ListPage {
+    pub content: String,
+    pub total: usize,
+}
+
+pub struct AssessmentListPage {
+    pub content: String,
+    pub total: usize,
+    /// Offered in the course select menu
+    pub courses: Vec<database_utils::Course>,
+}
+
+/// Inserts the course into the active term unless it already has a term
+pub async fn create_course(
+    storage: &dyn Storage,
+    course: database_utils::Course,
+    actor: &Actor,
+) -> Result<u64, Error> {
+    let fk_term_id = match course.fk_term_id {
+        Some(id) => match storage.term(actor.guild_id, id).await? {
+            Some(term) => Some(term.id),
+            None => return Err(Error::NotFound(format!("Term not found with id: {}", id))),
+        },
+        None => storage
+            .active_term(actor.guild_id)
+            .await?
+            .map(|term| term.id),
+    };
+
+    let course = database_utils::Course {
+        fk_term_id,
+        ..course
+    };
+    course_lookup::ensure_unique_code(storage, actor.guild_id, &course).await?;
+
+    storage.insert_course(&course, actor).await
+}
+
+/// What `remove_course` asks to confirm, `None` if the course does not exist
+pub async fn course_removal_prompt(
+    storage: &dyn Storage,
+    guild_id: i64,
+    id: i64,
+) -> Result<Option<String>, Error> {
+    let course = match storage.course(guild_id, id).await? {
+        Some(course) => course,
+        None => return Ok(None),
+    };
+    let assessments = storage.course_assessments(course.id).await?;
+
+    Ok(Some(utils::format_remove_course_confirmation(
+        &course,
+        &assessments,
+    )))
+}
+
+pub fn invalid_date_warnings(assessment: &database_utils::Assessment) -> Vec<String> {
+    [
+        ("take1", &assessment.take1),
+        ("retake1", &assessment.retake1),
+        ("retake2", &assessment.retake2),
+    ]
+    .into_iter()
+    .filter(|(_, date)| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err())
+    .map(|(field, date)| format!("Warning, invalid date format of {}: {}", field, date))
+    .collect()
+}
+
+/// Clash and workload warnings for an assessment that was just inserted
+pub async fn insert_warnings(
+    storage: &dyn Storage,
+    guild_id: i64,
+    assessment: &database_utils::Assessment,
+) -> Result<String, Error> {
+    let courses = storage.all_courses(guild_id).await?;
+    let assessments = storage.all_assessments(guild_id).await?;
+
+    Ok(clash_detection::format_insert_warnings(
+        &courses,
+        &assessments,
+        assessment,
+        config::get().colors.max_weekly_weight,
+    ))
+}
+
+/// Render options with the theme picked by the user or their guild
+pub async fn render_options(storage: &dyn Storage, actor: &Actor) -> Result<RenderOptions, Error> {
+    let theme = storage
+        .theme_preference(actor.guild_id, actor.user_id)
+        .await?
+        .and_then(|name| name.parse::<ThemeName>().ok())
+        .unwrap_or_default();
+
+    Ok(RenderOptions::from_config(theme.theme()))
+}
+
+/// `None` if the page does not exist
+pub async fn course_list_page(
+    storage: &dyn Storage,
+    guild_id: i64,
+    page: usize,
+    view: &CourseView,
+    options: &RenderOptions,
+) -> Result<Option<ListPage>, Error> {
+    let (term, mut courses) = storage.viewed_courses(guild_id, view.term_id).await?;
+    view.apply(&mut courses);
+    let per_page = config::get().pages.courses;
+
+    if page == 0 || page > rendering::page_count(courses.len(), per_page) {
+        return Ok(None);
+    }
+
+    Ok(Some(ListPage {
+        content: rendering::courses_page(&courses, page, per_page, term.as_ref())
+            .render(&options.theme),
+        total: courses.len(),
+    }))
+}
+
+/// The assessments as the actor's cohort sees them, see `cohorts::cohort_assessments`
+async fn member_assessments(
+    storage: &dyn Storage,
+    actor: &Actor,
+    assessments: Vec<database_utils::Assessment>,
+) -> Result<
+    (
+        Vec<database_utils::Assessment>,
+        Option<database_utils::Cohort>,
+    ),
+    Error,
+> {
+    let cohort = storage.member_cohort(actor.guild_id, actor.user_id).await?;
+    let all_dates = storage.cohort_dates(actor.guild_id).await?;
+    let assessments = cohorts::cohort_assessments(
+        assessments,
+        &all_dates,
+        cohort.as_ref().map(|cohort| cohort.id),
+    );
+
+    Ok((assessments, cohort))
+}
+
+/// The select menu offers courses of the view's term, or of the course's own term if not given.
+/// Assessments are shown as the actor's cohort sees them. `None` if the page does not exist
+pub async fn assessment_list_page(
+    storage: &dyn Storage,
+    actor: &Actor,
+    course_id: i64,
+    page: usize,
+    view: &AssessmentView,
+    options: &RenderOptions,
+) -> Result<Option<AssessmentListPage>, Error> {
+    let assessments = storage.all_assessments(actor.guild_id).await?;
+    let assessments = assessments
+        .into_iter()
+        .filter(|assessment| assessment.fk_course_id == course_id)
+        .collect();
+    let (mut assessments, cohort) = member_assessments(storage, actor, assessments).await?;
+    view.apply(&mut assessments, options.today);
+    let course = storage.course(actor.guild_id, course_id).await?;
+    let course_term_id = course.as_ref().and_then(|course| course.fk_term_id);
+    let (_term, courses) = storage
+        .viewed_courses(actor.guild_id, view.term_id.or(course_term_id))
+        .await?;
+
+    let per_page = config::get().pages.assessments;
+
+    if page == 0 || page > rendering::page_count(assessments.len(), per_page) {
+        return Ok(None);
+    }
+
+    let mut course_name = match course {
+        Some(course) => course.name,
+        None => "No course selected".to_string(),
+    };
+    if let Some(cohort) = cohort {
+        course_name += &format!(" · {}", cohort.name);
+    }
+
+    Ok(Some(AssessmentListPage {
+        content: rendering::assessments_page(&assessments, page, per_page, &course_name, options)
+            .render(&options.theme),
+        total: assessments.len(),
+        courses,
+    }))
+}
+
+/// What `/course` shows, `None` if the course does not exist
+pub async fn course_dashboard(
+    storage: &dyn Storage,
+    guild_id: i64,
+    course_id: i64,
+    options: &RenderOptions,
+) -> Result<Option<String>, Error> {
+    let course = match storage.course(guild_id, course_id).await? {
+        Some(course) => course,
+        None => return Ok(None),
+    };
+    let term = match course.fk_term_id {
+        Some(id) => storage.term(guild_id, id).await?,
+        None => None,
+    };
+    let assessments = storage.course_assessments(course.id).await?;
+    let sessions = storage.course_sessions(course.id).await?;
+
+    Ok(Some(rendering::course_dashboard(
+        &course,
+        term.as_ref(),
+        &assessments,
+        &sessions,
+        options,
+    )))
+}
+
+/// The course and its dates as an iCalendar file, `None` if the course does not exist
+pub async fn course_calendar(
+    storage: &dyn Storage,
+    guild_id: i64,
+    course_id: i64,
+) -> Result<Option<(database_utils::Course, String)>, Error> {
+    let course = match storage.course(guild_id, course_id).await? {
+        Some(course) => course,
+        None => return Ok(None),
+    };
+    let term = match course.fk_term_id {
+        Some(id) => storage.term(guild_id, id).await?,
+        None => None,
+    };
+    let assessments = storage.course_assessments(course.id).await?;
+    let sessions = storage.course_sessions(course.id).await?;
+
+    let calendar = ical::course_calendar(
+        &course,
+        term.as_ref(),
+        &assessments,
+        &sessions,
+        config::today(),
+        chrono::Utc::now(),
+    );
+
+    Ok(Some((course, calendar)))
+}
+
+pub const NOT_ENROLLED: &str = "You are not enrolled in any courses, pick some with /enroll";
+
+/// Courses offered by `/enroll`, those of the term the user is not enrolled in yet,
+/// or by `/unenroll`, the ones they are. Both are narrowed down by the view's filters
+pub async fn enrollment_choices(
+    storage: &dyn Storage,
+    actor: &Actor,
+    enroll: bool,
+    view: &CourseView,
+) -> Result<Vec<database_utils::Course>, Error> {
+    let enrolled = storage
+        .subscribed_courses(actor.guild_id, actor.user_id)
+        .await?;
+    let mut courses = match enroll {
+        true => {
+            let (_term, courses) = storage.viewed_courses(actor.guild_id, view.term_id).await?;
+            courses
+                .into_iter()
+                .filter(|course| enrolled.iter().all(|enrolled| enrolled.id != course.id))
+                .collect()
+        }
+        false => enrolled,
+    };
+    view.apply(&mut courses);
+
+    Ok(courses)
+}
+
+/// Prompt and course menu of `/enroll` or `/unenroll`, one course is picked at a time.
+/// The action and the view follow the course id in the menu id, so the handler can offer the rest
+pub fn enrollment_menu(
+    courses: &[database_utils::Course],
+    action: &str,
+    view: &CourseView,
+) -> (String, serenity::CreateActionRow) {
+    let mut content = match action {
+        "enroll" => String::from("Select a course to enroll in"),
+        _ => String::from("Select a course to leave"),
+    };
+    if courses.len() > utils::SELECT_MENU_OPTIONS {
+        content += &format!(
+            ", only the first {} of {} fit, narrow them down with `contains`",
+            utils::SELECT_MENU_OPTIONS,
+            courses.len()
+        );
+    }
+
+    // No course is selected yet, which the menu reports as `Err`
+    let select_menu =
+        utils::create_courses_select_menu(courses, 0, &format!("{};{}", action, view.encode()))
+            .unwrap_or_else(|menu| menu);
+
+    (content, select_menu)
+}
+
+/// What `/my_deadlines` shows, with the dates of the actor's cohort
+pub async fn my_deadlines_content(
+    storage: &dyn Storage,
+    actor: &Actor,
+    options: &RenderOptions,
+) -> Result<String, Error> {
+    let courses = storage
+        .subscribed_courses(actor.guild_id, actor.user_id)
+        .await?;
+    if courses.is_empty() {
+        return Ok(String::from(NOT_ENROLLED));
+    }
+
+    let assessments = storage.all_assessments(actor.guild_id).await?;
+    let (assessments, _cohort) = member_assessments(storage, actor, assessments).await?;
+    let page = rendering::deadlines_page(&courses, &assessments, options);
+    if page.table.rows.is_empty() {
+        return Ok(String::from("No upcoming deadlines in your courses"));
+    }
+
+    Ok(page.render(&options.theme))
+}
+
+/// Applies the fields of the edit modal, with the same limits as `insert_course`
+pub fn edited_course(
+    course: &database_utils::Course,
+    fields: &[(String, String)],
+) -> Result<database_utils::Course, Error> {
+    let field = |id: &str| {
+        fields
+            .iter()
+            .find(|(field_id, _)| field_id == id)
+            .map(|(_, value)| value.trim())
+            .ok_or(Error::Validation(format!("Missing field: {}", id)))
+    };
+    let invalid = |id: &str, limits: &str| Error::Validation(format!("{} must be {}", id, limits));
+
+    let name = field("name")?;
+    if !(3..=80).contains(&name.chars().count()) {
+        return Err(invalid("Name", "3 to 80 characters"));
+    }
+    let code = field("code")?;
+    if !(3..=10).contains(&code.chars().count()) {
+        return Err(invalid("Code", "3 to 10 characters"));
+    }
+    let semester = field("semester")?
+        .parse::<i64>()
+        .ok()
+        .filter(|semester| (1..=20).contains(semester))
+        .ok_or(invalid("Semester", "a number from 1 to 20"))?;
+    let year = field("year")?
+        .parse::<i64>()
+        .ok()
+        .filter(|year| (1000..=9999).contains(year))
+        .ok_or(invalid("Year", "a number from 1000 to 9999"))?;
+    let credit = field("credit")?
+        .parse::<f64>()
+        .ok()
+        .filter(|credit| (0.0..=999.0).contains(credit))
+        .ok_or(invalid("Credit", "a number from 0 to 999"))?;
+
+    Ok(database_utils::Course {
+        name: name.to_string(),
+        code: code.to_string(),
+        semester,
+        year,
+        credit,
+        ..course.clone()
+    })
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
 pub async fn insert_course(
     ctx: Context<'_>,
 
@@ -37,8 +435,9 @@ pub async fn insert_course(
     #[min = 0.0]
     #[max = 999.0]
     credit: f64,
+
+    #[description = "Term ID, defaults to the active term"] term_id: Option<i64>,
 ) -> Result<(), Error> {
-    let pool = database_utils::establish_connection().await?;
     let course = database_utils::Course {
         id: 0,
         name,
@@ -46,35 +445,61 @@ pub async fn insert_course(
         semester,
         year,
         credit,
+        fk_term_id: term_id,
+        deleted_at: None,
+        guild_id: 0,
     };
 
-    let rows_affected = database_utils::insert_course(&pool, &course).await?;
+    let rows_affected = create_course(ctx.data().storage.as_ref(), course, &actor(ctx)).await?;
 
     let response = format!("Inserted {} rows", rows_affected);
     ctx.say(response).await?;
     Ok(())
 }
 
-#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
-pub async fn remove_course(ctx: Context<'_>, id: i32) -> Result<(), Error> {
-    let pool = database_utils::establish_connection().await?;
-    let course = database_utils::delete_course(&pool, id).await?;
-
-    match course {
-        Some(course) => {
-            let response = format!("Deleted course: {}, ID: {}", course.name, course.id);
-            ctx.say(response).await?;
-        }
+/// Asks for confirmation first, see `interaction_handlers::confirmation_handler`
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn remove_course(
+    ctx: Context<'_>,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
+) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let guild_id = actor(ctx).guild_id;
+    let id = course_lookup::resolve_course(storage, guild_id, &course)
+        .await?
+        .id;
+    let content = match course_removal_prompt(storage, guild_id, id).await? {
+        Some(content) => content,
         None => {
             let response = format!("Course not found with id: {}", id);
             ctx.say(response).await?;
+            return Ok(());
         }
-    }
+    };
+
+    let (confirm_button, cancel_button) = utils::create_confirmation_buttons("remove_course", id);
+
+    ctx.send(|m| {
+        m.content(content).components(|c| {
+            c.create_action_row(|row| row.add_button(confirm_button).add_button(cancel_button))
+        })
+    })
+    .await?;
 
     Ok(())
 }
 
-#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "access_control::course_editor_check"
+)]
 pub async fn insert_assessment(
     ctx: Context<'_>,
 
@@ -97,64 +522,60 @@ pub async fn insert_assessment(
 
     #[description = "Date of the retake. Format: YYYY-MM-DD"] retake2: String,
 
-    #[description = "Course ID"]
-    #[min = 0]
-    #[max = 999]
-    fk_course_id: i64,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
 ) -> Result<(), Error> {
-    let pool = database_utils::establish_connection().await?;
-
-    match NaiveDate::parse_from_str(&take1, "%Y-%m-%d") {
-        Ok(_) => {}
-        Err(_) => {
-            let response = format!("Warning, invalid date format of take1: {}", take1);
-            ctx.send(|m| m.content(response).ephemeral(true)).await?;
-        }
-    }
-
-    match NaiveDate::parse_from_str(&retake1, "%Y-%m-%d") {
-        Ok(_) => {}
-        Err(_) => {
-            let response = format!("Warning, invalid date format of retake1: {}", retake1);
-            ctx.send(|m| m.content(response).ephemeral(true)).await?;
-        }
-    }
-
-    match NaiveDate::parse_from_str(&retake2, "%Y-%m-%d") {
-        Ok(_) => {}
-        Err(_) => {
-            let response = format!("Warning, invalid date format of retake2: {}", retake2);
-            ctx.send(|m| m.content(response).ephemeral(true)).await?;
-        }
-    }
+    let fk_course_id =
+        course_lookup::resolve_course(ctx.data().storage.as_ref(), actor(ctx).guild_id, &course)
+            .await?
+            .id;
+    access_control::can_edit_course(ctx, fk_course_id).await?;
 
     let assessment = database_utils::Assessment {
         id: 0,
         name,
         weight,
-        take1: take1,
-        retake1: retake1,
-        retake2: retake2,
+        take1,
+        retake1,
+        retake2,
         fk_course_id,
+        deleted_at: None,
     };
 
-    let rows_affected = database_utils::insert_assessment(&pool, &assessment).await?;
+    for warning in invalid_date_warnings(&assessment) {
+        ctx.send(|m| m.content(warning).ephemeral(true)).await?;
+    }
+
+    let storage = ctx.data().storage.as_ref();
+    let actor = actor(ctx);
+    let rows_affected = storage.insert_assessment(&assessment, &actor).await?;
+    let warnings = insert_warnings(storage, actor.guild_id, &assessment).await?;
 
-    let response = format!("Inserted {} rows", rows_affected);
+    let response = format!("Inserted {} rows\n{}", rows_affected, warnings);
     ctx.send(|m| m.content(response).ephemeral(true)).await?;
 
     Ok(())
 }
 
-#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "access_control::course_editor_check"
+)]
 pub async fn remove_assessment(ctx: Context<'_>, id: i32) -> Result<(), Error> {
-    let pool = database_utils::establish_connection().await?;
-    let assessment = database_utils::delete_assessment(&pool, id).await?;
+    let storage = ctx.data().storage.as_ref();
+
+    if let Some(assessment) = storage.assessment(actor(ctx).guild_id, id as i64).await? {
+        access_control::can_edit_course(ctx, assessment.fk_course_id).await?;
+    }
+
+    let assessment = storage.delete_assessment(id as i64, &actor(ctx)).await?;
 
     match assessment {
         Some(assessment) => {
             let response = format!(
-                "Deleted assessment: {}, ID: {}",
+                "Moved assessment to trash: {}, ID: {}",
                 assessment.name, assessment.id
             );
             ctx.say(response).await?;
@@ -168,36 +589,72 @@ pub async fn remove_assessment(ctx: Context<'_>, id: i32) -> Result<(), Error> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 #[poise::command(
     slash_command,
     default_member_permissions = "SEND_MESSAGES",
     user_cooldown = "5"
 )]
-pub async fn list_courses(ctx: Context<'_>, page: Option<usize>) -> Result<(), Error> {
-    let connection = database_utils::establish_connection().await?;
-    let courses = database_utils::get_all_courses(&connection).await?;
+pub async fn list_courses(
+    ctx: Context<'_>,
+    page: Option<usize>,
+    #[description = "Term ID, defaults to the active term"] term_id: Option<i64>,
+    #[description = "Order of the courses, by id unless given"] sort: Option<CourseSort>,
+    #[description = "Only courses of this semester"] semester: Option<i64>,
+    #[description = "Only courses of this year"] year: Option<i64>,
+    #[description = "Only courses with at least this many credits"] min_credit: Option<f64>,
+    #[description = "Only courses with at most this many credits"] max_credit: Option<f64>,
+    // Kept short so the view fits into the 100 characters of a button id
+    #[description = "Only courses whose name or code contains this"]
+    #[max_length = 25]
+    contains: Option<String>,
+) -> Result<(), Error> {
     let page = page.unwrap_or(1);
+    let view = CourseView {
+        term_id,
+        sort: sort.unwrap_or_default(),
+        semester,
+        year,
+        min_credit,
+        max_credit,
+        query: contains,
+    };
+    let options = render_options(ctx.data().storage.as_ref(), &actor(ctx)).await?;
+    let listing = match course_list_page(
+        ctx.data().storage.as_ref(),
+        actor(ctx).guild_id,
+        page,
+        &view,
+        &options,
+    )
+    .await?
+    {
+        Some(listing) => listing,
+        None => {
+            let response = format!("Page {} does not exist", page);
+            ctx.say(response).await?;
+            return Ok(());
+        }
+    };
 
-    if page > (courses.len() / utils::COURSES_PER_PAGE) + 1 {
-        let response = format!("Page {} does not exist", page);
-        ctx.say(response).await?;
+    if listing.total == 0 && view.is_filtered() {
+        ctx.say("No courses match the filters").await?;
         return Ok(());
     }
 
-    let content = utils::format_course_response(&courses, page)?;
-
-    // If there are less than 5 courses, just send it
-    if courses.len() <= utils::COURSES_PER_PAGE {
-        ctx.say(content).await?;
+    // If everything fits on one page, just send it
+    if listing.total <= config::get().pages.courses {
+        ctx.say(listing.content).await?;
         return Ok(());
     }
 
     // Adding buttons
-    let (previous_button, next_button, refresh_button) =
-        utils::create_buttons(page, courses.len() / utils::COURSES_PER_PAGE);
+    let last_page = rendering::page_count(listing.total, config::get().pages.courses);
+    let (previous_button, next_button, _refresh_button) =
+        utils::create_buttons(page, last_page - 1, &view.encode());
 
     ctx.send(|m| {
-        m.content(content).components(|c| {
+        m.content(listing.content).components(|c| {
             c.create_action_row(|row| row.add_button(previous_button).add_button(next_button))
         })
     })
@@ -213,37 +670,60 @@ pub async fn list_courses(ctx: Context<'_>, page: Option<usize>) -> Result<(), E
 )]
 pub async fn list_assessments(
     ctx: Context<'_>,
-    course_id: i64,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
     page: Option<usize>,
+    #[description = "Term whose courses are offered, defaults to the course's term"]
+    term_id: Option<i64>,
+    #[description = "Order of the assessments, by id unless given"] sort: Option<AssessmentSort>,
+    #[description = "Only assessments whose name contains this"]
+    #[max_length = 25]
+    contains: Option<String>,
 ) -> Result<(), Error> {
-    let connection = database_utils::establish_connection().await?;
-    let assessments = database_utils::get_course_assessments(&connection, course_id).await?;
-    let courses = database_utils::get_all_courses(&connection).await?;
-
     let page = page.unwrap_or(1);
-
-    if page > (assessments.len() / utils::ASSESSMENTS_PER_PAGE) + 1 {
-        let response = format!("Page {} does not exist", page);
-        ctx.say(response).await?;
-        return Ok(());
-    }
-
-    let course_name = match courses.iter().find(|course| course.id == course_id) {
-        Some(course) => course.name.clone(),
-        None => "No course selected".to_string(),
+    let course_id =
+        course_lookup::resolve_course(ctx.data().storage.as_ref(), actor(ctx).guild_id, &course)
+            .await?
+            .id;
+    let view = AssessmentView {
+        term_id,
+        sort: sort.unwrap_or_default(),
+        query: contains,
+    };
+    let options = render_options(ctx.data().storage.as_ref(), &actor(ctx)).await?;
+    let listing = match assessment_list_page(
+        ctx.data().storage.as_ref(),
+        &actor(ctx),
+        course_id,
+        page,
+        &view,
+        &options,
+    )
+    .await?
+    {
+        Some(listing) => listing,
+        None => {
+            let response = format!("Page {} does not exist", page);
+            ctx.say(response).await?;
+            return Ok(());
+        }
     };
 
-    let mut content = utils::format_assessment_response(&assessments, page, &course_name)?;
-
-    let select_menu = match utils::create_courses_select_menu(&courses, course_id) {
+    let mut content = listing.content;
+    let select_menu = match utils::create_courses_select_menu(
+        &listing.courses,
+        course_id,
+        &view.encode(),
+    ) {
         Ok(menu) => menu,
         Err(e) => {
-            content = format!("Error creating select menu: Course not found. Please select a course from the list below.");
+            content = "Error creating select menu: Course not found. Please select a course from the list below.".to_string();
             e
         }
     };
 
-    if assessments.len() <= utils::ASSESSMENTS_PER_PAGE {
+    if listing.total <= config::get().pages.assessments {
         ctx.send(|m| {
             m.content(content)
                 .components(|c| c.add_action_row(select_menu))
@@ -252,5 +732,1913 @@ pub async fn list_assessments(
         return Ok(());
     }
 
+    let last_page = rendering::page_count(listing.total, config::get().pages.assessments);
+    let (previous_button, next_button, _refresh_button) =
+        utils::create_buttons(page, last_page - 1, &view.encode());
+
+    ctx.send(|m| {
+        m.content(content).components(|c| {
+            c.create_action_row(|row| row.add_button(previous_button).add_button(next_button))
+                .add_action_row(select_menu)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Everything about one course, with buttons to enroll, export its dates and edit it
+#[poise::command(
+    slash_command,
+    rename = "course",
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn show_course(
+    ctx: Context<'_>,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
+) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let actor = actor(ctx);
+    let course = course_lookup::resolve_course(storage, actor.guild_id, &course).await?;
+
+    let options = render_options(storage, &actor).await?;
+    let content = course_dashboard(storage, actor.guild_id, course.id, &options)
+        .await?
+        .ok_or(Error::NotFound(format!(
+            "Course not found: {}",
+            course.code
+        )))?;
+
+    let subscribed = storage.is_subscribed(actor.user_id, course.id).await?;
+    let member = ctx.author_member().await;
+    let can_edit = access_control::member_can_edit_course(
+        storage,
+        ctx.guild_id(),
+        member.as_deref(),
+        course.id,
+    )
+    .await?;
+    let buttons = utils::create_course_buttons(course.id, subscribed, can_edit);
+
+    // The buttons reflect the caller's enrollment and edit access, only they can use them
+    ctx.send(|m| {
+        m.content(content)
+            .components(|c| c.add_action_row(buttons))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Finds courses by name or code and assessments by name, best matches first
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn search(
+    ctx: Context<'_>,
+    #[description = "Words to look for, each may be the start of a word"]
+    #[max_length = 100]
+    query: String,
+) -> Result<(), Error> {
+    if database_utils::search_words(&query).is_empty() {
+        return Err(Error::Validation(String::from(
+            "Search for at least one letter or number",
+        )));
+    }
+
+    let hits = ctx
+        .data()
+        .storage
+        .search(actor(ctx).guild_id, &query, utils::SEARCH_RESULTS)
+        .await?;
+    if hits.is_empty() {
+        ctx.say(format!("No courses or assessments match \"{}\"", query))
+            .await?;
+        return Ok(());
+    }
+
+    let options = render_options(ctx.data().storage.as_ref(), &actor(ctx)).await?;
+    let content = rendering::search_page(&query, &hits).render(&options.theme);
+    let rows = utils::create_search_buttons(&hits);
+    ctx.send(|m| {
+        m.content(content).components(|c| {
+            for row in rows {
+                c.add_action_row(row);
+            }
+            c
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "access_control::course_editor_check"
+)]
+pub async fn insert_session(
+    ctx: Context<'_>,
+
+    #[description = "Day of the week, 1 = Monday, 7 = Sunday"]
+    #[min = 1]
+    #[max = 7]
+    weekday: i64,
+
+    #[description = "Start time. Format: HH:MM"]
+    #[min_length = 5]
+    #[max_length = 5]
+    start_time: String,
+
+    #[description = "End time. Format: HH:MM"]
+    #[min_length = 5]
+    #[max_length = 5]
+    end_time: String,
+
+    #[description = "Room or place of the session"]
+    #[max_length = 40]
+    location: String,
+
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
+) -> Result<(), Error> {
+    let start = NaiveTime::parse_from_str(&start_time, "%H:%M");
+    let end = NaiveTime::parse_from_str(&end_time, "%H:%M");
+
+    match (start, end) {
+        (Ok(start), Ok(end)) if start < end => {}
+        _ => {
+            let response = format!(
+                "Invalid session time: {} - {}. Format: HH:MM, start before end",
+                start_time, end_time
+            );
+            ctx.send(|m| m.content(response).ephemeral(true)).await?;
+            return Ok(());
+        }
+    }
+
+    let fk_course_id =
+        course_lookup::resolve_course(ctx.data().storage.as_ref(), actor(ctx).guild_id, &course)
+            .await?
+            .id;
+    access_control::can_edit_course(ctx, fk_course_id).await?;
+
+    let session = database_utils::Session {
+        id: 0,
+        weekday,
+        start_time,
+        end_time,
+        location,
+        fk_course_id,
+    };
+
+    let rows_affected = ctx
+        .data()
+        .storage
+        .insert_session(&session, &actor(ctx))
+        .await?;
+    // The course may have been deleted since it was resolved
+    if rows_affected == 0 {
+        return Err(Error::NotFound(format!(
+            "Course not found with id: {}",
+            fk_course_id
+        )));
+    }
+
+    let response = format!("Inserted {} rows", rows_affected);
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
     Ok(())
 }
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "access_control::course_editor_check"
+)]
+pub async fn remove_session(ctx: Context<'_>, id: i64) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+
+    if let Some(session) = storage.session(actor(ctx).guild_id, id).await? {
+        access_control::can_edit_course(ctx, session.fk_course_id).await?;
+    }
+
+    let session = storage.delete_session(id, &actor(ctx)).await?;
+
+    match session {
+        Some(session) => {
+            let response = format!(
+                "Deleted session: {} {}-{}, ID: {}",
+                session.weekday, session.start_time, session.end_time, session.id
+            );
+            ctx.say(response).await?;
+        }
+        None => {
+            let response = format!("Session not found with id: {}", id);
+            ctx.say(response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn timetable_image(
+    ctx: Context<'_>,
+    #[description = "Any date in the week to show. Format: YYYY-MM-DD"] date: Option<String>,
+) -> Result<(), Error> {
+    send_week_image(ctx, date, None).await
+}
+
+/// Only the courses the caller is enrolled in, see `/enroll`
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn my_timetable(
+    ctx: Context<'_>,
+    #[description = "Any date in the week to show. Format: YYYY-MM-DD"] date: Option<String>,
+) -> Result<(), Error> {
+    let courses = ctx
+        .data()
+        .storage
+        .subscribed_courses(actor(ctx).guild_id, actor(ctx).user_id)
+        .await?;
+    if courses.is_empty() {
+        ctx.send(|m| m.content(NOT_ENROLLED).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let course_ids = courses.iter().map(|course| course.id).collect();
+    send_week_image(ctx, date, Some(course_ids)).await
+}
+
+/// Upcoming attempts of the courses the caller is enrolled in
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn my_deadlines(ctx: Context<'_>) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let actor = actor(ctx);
+    let options = render_options(storage, &actor).await?;
+
+    let content = my_deadlines_content(storage, &actor, &options).await?;
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Adds courses to the caller's `/my_timetable` and `/my_deadlines`
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn enroll(
+    ctx: Context<'_>,
+    #[description = "Term whose courses are offered, defaults to the active term"] term_id: Option<
+        i64,
+    >,
+    #[description = "Only courses whose name or code contains this"]
+    #[max_length = 25]
+    contains: Option<String>,
+) -> Result<(), Error> {
+    let view = CourseView {
+        term_id,
+        query: contains,
+        ..CourseView::default()
+    };
+    let courses = enrollment_choices(ctx.data().storage.as_ref(), &actor(ctx), true, &view).await?;
+    if courses.is_empty() {
+        let response = match view.is_filtered() {
+            true => "No courses you can enroll in match the filter",
+            false => "You are already enrolled in every course of the term",
+        };
+        ctx.send(|m| m.content(response).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    send_enrollment_menu(ctx, &courses, "enroll", &view).await
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn unenroll(
+    ctx: Context<'_>,
+    #[description = "Only courses whose name or code contains this"]
+    #[max_length = 25]
+    contains: Option<String>,
+) -> Result<(), Error> {
+    let view = CourseView {
+        query: contains,
+        ..CourseView::default()
+    };
+    let courses =
+        enrollment_choices(ctx.data().storage.as_ref(), &actor(ctx), false, &view).await?;
+    if courses.is_empty() {
+        let response = match view.is_filtered() {
+            true => "None of your courses match the filter",
+            false => NOT_ENROLLED,
+        };
+        ctx.send(|m| m.content(response).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    send_enrollment_menu(ctx, &courses, "unenroll", &view).await
+}
+
+async fn send_enrollment_menu(
+    ctx: Context<'_>,
+    courses: &[database_utils::Course],
+    action: &str,
+    view: &CourseView,
+) -> Result<(), Error> {
+    let (content, select_menu) = enrollment_menu(courses, action, view);
+
+    ctx.send(|m| {
+        m.content(content)
+            .components(|c| c.add_action_row(select_menu))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Week image of the given courses, every course if `None`
+async fn send_week_image(
+    ctx: Context<'_>,
+    date: Option<String>,
+    course_ids: Option<Vec<i64>>,
+) -> Result<(), Error> {
+    let today = config::today();
+    let date = match date {
+        Some(date) => match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                let response = format!("Invalid date format: {}", date);
+                ctx.send(|m| m.content(response).ephemeral(true)).await?;
+                return Ok(());
+            }
+        },
+        None => today,
+    };
+
+    let storage = ctx.data().storage.as_ref();
+    let guild_id = actor(ctx).guild_id;
+    let mut courses = storage.all_courses(guild_id).await?;
+    let mut sessions = storage.all_sessions(guild_id).await?;
+    let mut assessments = storage.all_assessments(guild_id).await?;
+    if let Some(course_ids) = course_ids {
+        courses.retain(|course| course_ids.contains(&course.id));
+        sessions.retain(|session| course_ids.contains(&session.fk_course_id));
+        assessments.retain(|assessment| course_ids.contains(&assessment.fk_course_id));
+    }
+
+    let image =
+        crate::timetable_image::render_week(&courses, &sessions, &assessments, date, today)?;
+    let monday = utils::week_start(date);
+
+    ctx.send(|m| {
+        m.content(format!("# Week of {}", monday.format("%Y-%m-%d")))
+            .attachment(serenity::AttachmentType::Bytes {
+                data: Cow::Owned(image),
+                filename: "timetable.png".to_string(),
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn calendar(
+    ctx: Context<'_>,
+    #[description = "Month to show. Format: YYYY-MM"] month: Option<String>,
+) -> Result<(), Error> {
+    let options = render_options(ctx.data().storage.as_ref(), &actor(ctx)).await?;
+    let first_day = match month {
+        Some(month) => match NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                let response = format!("Invalid month format: {}", month);
+                ctx.send(|m| m.content(response).ephemeral(true)).await?;
+                return Ok(());
+            }
+        },
+        None => options.today.with_day(1).unwrap(),
+    };
+    let month = match utils::month_index(first_day) {
+        Some(month) if month <= utils::CALENDAR_LAST_MONTH => month,
+        _ => {
+            let response = format!("Invalid month: {}. Years go from 0 to 9999", first_day);
+            ctx.send(|m| m.content(response).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+
+    let assessments = ctx
+        .data()
+        .storage
+        .all_assessments(actor(ctx).guild_id)
+        .await?;
+
+    let content = utils::format_calendar_response(&assessments, first_day, None, &options);
+    let select_menu = utils::create_calendar_day_select_menu(&assessments, first_day);
+    let (previous_button, next_button) = utils::create_calendar_buttons(month);
+
+    ctx.send(|m| {
+        m.content(content).components(|c| {
+            c.create_action_row(|row| row.add_button(previous_button).add_button(next_button));
+            if let Some(select_menu) = select_menu {
+                c.add_action_row(select_menu);
+            }
+            c
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn clashes(
+    ctx: Context<'_>,
+    #[description = "Only check courses of this semester"] semester: Option<i64>,
+    #[description = "Only check courses of this year"] year: Option<i64>,
+    #[description = "Total assessment weight allowed per week"]
+    #[min = 0.0]
+    #[max = 999.0]
+    max_weekly_weight: Option<f64>,
+) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let guild_id = actor(ctx).guild_id;
+    let courses: Vec<database_utils::Course> = storage
+        .all_courses(guild_id)
+        .await?
+        .into_iter()
+        .filter(|course| semester.is_none_or(|semester| course.semester == semester))
+        .filter(|course| year.is_none_or(|year| course.year == year))
+        .collect();
+    let assessments: Vec<database_utils::Assessment> = storage
+        .all_assessments(guild_id)
+        .await?
+        .into_iter()
+        .filter(|assessment| {
+            courses
+                .iter()
+                .any(|course| course.id == assessment.fk_course_id)
+        })
+        .collect();
+
+    let max_weight = max_weekly_weight.unwrap_or(config::get().colors.max_weekly_weight);
+    let found = clash_detection::find_clashes(&courses, &assessments);
+    let workload = clash_detection::weekly_workload(&courses, &assessments);
+
+    let content = clash_detection::format_report(&courses, &found, &workload, max_weight);
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn insert_term(
+    ctx: Context<'_>,
+
+    #[description = "Name of the term, e.g. Autumn 2023"]
+    #[min_length = 3]
+    #[max_length = 40]
+    name: String,
+
+    #[description = "First day of the term. Format: YYYY-MM-DD"] start_date: String,
+
+    #[description = "Last day of the term. Format: YYYY-MM-DD"] end_date: String,
+
+    #[description = "Make this the active term"] active: Option<bool>,
+) -> Result<(), Error> {
+    let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d");
+    let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d");
+
+    match (start, end) {
+        (Ok(start), Ok(end)) if start <= end => {}
+        _ => {
+            let response = format!(
+                "Invalid term dates: {} - {}. Format: YYYY-MM-DD, start before end",
+                start_date, end_date
+            );
+            ctx.send(|m| m.content(response).ephemeral(true)).await?;
+            return Ok(());
+        }
+    }
+
+    let term = database_utils::Term {
+        id: 0,
+        name,
+        start_date,
+        end_date,
+        active: active.unwrap_or(false),
+        archived: false,
+        guild_id: 0,
+    };
+
+    let rows_affected = ctx.data().storage.insert_term(&term, &actor(ctx)).await?;
+
+    let response = format!("Inserted {} rows", rows_affected);
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn activate_term(ctx: Context<'_>, id: i64) -> Result<(), Error> {
+    let term = ctx.data().storage.activate_term(id, &actor(ctx)).await?;
+
+    match term {
+        Some(term) => {
+            let response = format!("Active term: {}, ID: {}", term.name, term.id);
+            ctx.say(response).await?;
+        }
+        None => {
+            let response = format!("Term not found with id: {}", id);
+            ctx.say(response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn archive_term(ctx: Context<'_>, id: i64) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let term = storage.archive_term(id, &actor(ctx)).await?;
+
+    match term {
+        Some(term) => {
+            let courses = storage.term_courses(term.guild_id, term.id).await?;
+            let response = format!(
+                "Archived term: {}, ID: {} with {} courses",
+                term.name,
+                term.id,
+                courses.len()
+            );
+            ctx.say(response).await?;
+        }
+        None => {
+            let response = format!("Term not found with id: {}", id);
+            ctx.say(response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn list_terms(ctx: Context<'_>) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let terms = storage.all_terms(actor(ctx).guild_id).await?;
+
+    if terms.is_empty() {
+        ctx.say("No terms found").await?;
+        return Ok(());
+    }
+
+    let options = render_options(storage, &actor(ctx)).await?;
+    let content = format!(
+        "# Terms list\n{}",
+        rendering::terms_table(&terms).render(&options.theme)
+    );
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn trash(ctx: Context<'_>) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let guild_id = actor(ctx).guild_id;
+    let courses = storage.deleted_courses(guild_id).await?;
+    let assessments = storage.deleted_assessments(guild_id).await?;
+
+    if courses.is_empty() && assessments.is_empty() {
+        ctx.say("Trash is empty").await?;
+        return Ok(());
+    }
+
+    let options = render_options(storage, &actor(ctx)).await?;
+    let content = rendering::trash_message(
+        &courses,
+        &assessments,
+        config::get().scheduler.trash_retention_days,
+        &options.theme,
+    );
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+/// `None` if the course is not in the trash, fails if its code was taken in the meantime
+pub async fn restore_trashed_course(
+    storage: &dyn Storage,
+    actor: &Actor,
+    id: i64,
+) -> Result<Option<database_utils::Course>, Error> {
+    let trashed = storage
+        .deleted_courses(actor.guild_id)
+        .await?
+        .into_iter()
+        .find(|course| course.id == id);
+    if let Some(course) = &trashed {
+        course_lookup::ensure_unique_code(storage, actor.guild_id, course).await?;
+    }
+
+    storage.restore_course(id, actor).await
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn restore_course(ctx: Context<'_>, id: i64) -> Result<(), Error> {
+    let course = restore_trashed_course(ctx.data().storage.as_ref(), &actor(ctx), id).await?;
+
+    match course {
+        Some(course) => {
+            let response = format!("Restored course: {}, ID: {}", course.name, course.id);
+            ctx.say(response).await?;
+        }
+        None => {
+            let response = format!("Course not found in trash with id: {}", id);
+            ctx.say(response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn restore_assessment(ctx: Context<'_>, id: i64) -> Result<(), Error> {
+    let assessment = ctx
+        .data()
+        .storage
+        .restore_assessment(id, &actor(ctx))
+        .await?;
+
+    match assessment {
+        Some(assessment) => {
+            let response = format!(
+                "Restored assessment: {}, ID: {}",
+                assessment.name, assessment.id
+            );
+            ctx.say(response).await?;
+        }
+        None => {
+            let response = format!("Assessment not found in trash with id: {}", id);
+            ctx.say(response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn clone_courses(
+    ctx: Context<'_>,
+
+    #[description = "Semester to copy courses from"]
+    #[min = 1]
+    #[max = 20]
+    from_semester: i64,
+
+    #[description = "Year to copy courses from"]
+    #[min = 1000]
+    #[max = 9999]
+    from_year: i64,
+
+    #[description = "Semester of the new courses"]
+    #[min = 1]
+    #[max = 20]
+    to_semester: i64,
+
+    #[description = "Year of the new courses"]
+    #[min = 1000]
+    #[max = 9999]
+    to_year: i64,
+
+    #[description = "Days to move assessment dates by, defaults to whole weeks between the years"]
+    shift_days: Option<i64>,
+
+    #[description = "Leave assessment dates empty instead of moving them"] clear_dates: Option<
+        bool,
+    >,
+
+    #[description = "Term ID of the new courses"] term_id: Option<i64>,
+) -> Result<(), Error> {
+    if (from_semester, from_year) == (to_semester, to_year) {
+        let response = "Source and target semester/year must differ";
+        ctx.send(|m| m.content(response).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let storage = ctx.data().storage.as_ref();
+    if let Some(id) = term_id {
+        if storage.term(actor(ctx).guild_id, id).await?.is_none() {
+            return Err(Error::NotFound(format!("Term not found with id: {}", id)));
+        }
+    }
+
+    // 52 weeks per year keeps assessments on the same weekday
+    let shift_days = match clear_dates.unwrap_or(false) {
+        true => None,
+        false => Some(shift_days.unwrap_or((to_year - from_year) * 364)),
+    };
+
+    let cloned = storage
+        .clone_courses(
+            (from_semester, from_year),
+            (to_semester, to_year),
+            term_id,
+            shift_days,
+            &actor(ctx),
+        )
+        .await?;
+
+    if cloned.is_empty() {
+        let response = format!(
+            "No courses found in semester {} of {}",
+            from_semester, from_year
+        );
+        ctx.say(response).await?;
+        return Ok(());
+    }
+
+    let mut response = format!(
+        "Cloned {} courses into semester {} of {}\n",
+        cloned
+            .iter()
+            .filter(|course| course.new_id.is_some())
+            .count(),
+        to_semester,
+        to_year
+    );
+    for course in &cloned {
+        response += &match course.new_id {
+            Some(new_id) => format!(
+                "- {}: ID {} -> {} ({} assessments, {} sessions)\n",
+                course.code, course.old_id, new_id, course.assessments, course.sessions
+            ),
+            None => format!("- {}: already exists, skipped\n", course.code),
+        };
+    }
+    response += &match shift_days {
+        Some(days) => format!("Assessment dates moved by {} days", days),
+        None => String::from("Assessment dates were cleared"),
+    };
+
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    subcommands("permissions_list", "permissions_grant", "permissions_revoke")
+)]
+pub async fn permissions(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// List who can edit courses besides administrators
+#[poise::command(slash_command, guild_only, rename = "list")]
+pub async fn permissions_list(
+    ctx: Context<'_>,
+    #[description = "Only show grants of this course, by ID or code"]
+    #[max_length = 20]
+    course: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a guild")?;
+    let course_id = match course {
+        Some(course) => Some(
+            course_lookup::resolve_course(ctx.data().storage.as_ref(), guild_id.0 as i64, &course)
+                .await?
+                .id,
+        ),
+        None => None,
+    };
+    let storage = ctx.data().storage.as_ref();
+    let courses = storage.all_courses(guild_id.0 as i64).await?;
+    let editors: Vec<database_utils::CourseEditor> = storage
+        .course_editors(guild_id.0 as i64)
+        .await?
+        .into_iter()
+        .filter(|editor| course_id.is_none_or(|id| editor.fk_course_id == id))
+        .collect();
+
+    if editors.is_empty() {
+        ctx.say("No course editors found").await?;
+        return Ok(());
+    }
+
+    let mut content = String::from("# Course editors\n");
+    for editor in &editors {
+        let course = match courses
+            .iter()
+            .find(|course| course.id == editor.fk_course_id)
+        {
+            Some(course) => format!("{} {}", course.code, course.name),
+            None => format!("Course {}", editor.fk_course_id),
+        };
+        let grantee = match (editor.role_id, editor.user_id) {
+            (Some(role_id), _) => format!("<@&{}>", role_id),
+            (_, Some(user_id)) => format!("<@{}>", user_id),
+            _ => String::from("nobody"),
+        };
+
+        content += &format!("- ID {}: {} -> {}\n", editor.id, course, grantee);
+    }
+
+    // Mentions are only used for display, nobody should be pinged
+    ctx.send(|m| m.content(content).allowed_mentions(|a| a.empty_parse()))
+        .await?;
+
+    Ok(())
+}
+
+/// Allow a role or a user to manage assessments and sessions of a course
+#[poise::command(slash_command, guild_only, rename = "grant")]
+pub async fn permissions_grant(
+    ctx: Context<'_>,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
+    #[description = "Role allowed to edit the course"] role: Option<serenity::Role>,
+    #[description = "User allowed to edit the course"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a guild")?;
+
+    let (role_id, user_id) = match (role, user) {
+        (Some(role), None) => (Some(role.id.0 as i64), None),
+        (None, Some(user)) => (None, Some(user.id.0 as i64)),
+        _ => {
+            let response = "Pick either a role or a user";
+            ctx.send(|m| m.content(response).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+
+    let course_id =
+        course_lookup::resolve_course(ctx.data().storage.as_ref(), guild_id.0 as i64, &course)
+            .await?
+            .id;
+
+    let editor = database_utils::CourseEditor {
+        id: 0,
+        guild_id: guild_id.0 as i64,
+        role_id,
+        user_id,
+        fk_course_id: course_id,
+    };
+
+    let rows_affected = ctx
+        .data()
+        .storage
+        .insert_course_editor(&editor, &actor(ctx))
+        .await?;
+
+    let response = format!("Inserted {} rows", rows_affected);
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Remove a course editor grant
+#[poise::command(slash_command, guild_only, rename = "revoke")]
+pub async fn permissions_revoke(
+    ctx: Context<'_>,
+    #[description = "Grant ID from /permissions list"] id: i64,
+) -> Result<(), Error> {
+    let editor = ctx
+        .data()
+        .storage
+        .delete_course_editor(id, &actor(ctx))
+        .await?;
+
+    match editor {
+        Some(editor) => {
+            let response = format!(
+                "Deleted grant ID: {} of course ID: {}",
+                editor.id, editor.fk_course_id
+            );
+            ctx.say(response).await?;
+        }
+        None => {
+            let response = format!("Grant not found with id: {}", id);
+            ctx.say(response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    subcommands(
+        "course_role_list",
+        "course_role_link",
+        "course_role_unlink",
+        "course_role_reconcile"
+    )
+)]
+pub async fn course_role(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// List the roles and reminder channels linked to courses
+#[poise::command(slash_command, guild_only, rename = "list")]
+pub async fn course_role_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a guild")?;
+    let storage = ctx.data().storage.as_ref();
+    let courses = storage.all_courses(guild_id.0 as i64).await?;
+    let links = storage.course_roles(guild_id.0 as i64).await?;
+
+    if links.is_empty() {
+        ctx.say("No course roles linked").await?;
+        return Ok(());
+    }
+
+    let mut content = String::from("# Course roles\n");
+    for link in &links {
+        let course = match courses.iter().find(|course| course.id == link.fk_course_id) {
+            Some(course) => format!("{} {}", course.code, course.name),
+            None => format!("Course {}", link.fk_course_id),
+        };
+        let channel = match link.channel_id {
+            Some(channel_id) => format!(", reminders in <#{}>", channel_id),
+            None => String::new(),
+        };
+
+        content += &format!("- {} -> <@&{}>{}\n", course, link.role_id, channel);
+    }
+
+    // Mentions are only used for display, nobody should be pinged
+    ctx.send(|m| m.content(content).allowed_mentions(|a| a.empty_parse()))
+        .await?;
+
+    Ok(())
+}
+
+/// Give a role to members enrolling in a course, optionally with a channel for reminders
+#[poise::command(slash_command, guild_only, rename = "link")]
+pub async fn course_role_link(
+    ctx: Context<'_>,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
+    #[description = "Role of the members enrolled in the course"] role: serenity::Role,
+    #[description = "Channel for deadline reminders, which ping the role"] channel: Option<
+        serenity::GuildChannel,
+    >,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a guild")?;
+    if channel
+        .as_ref()
+        .is_some_and(|channel| !channel.is_text_based())
+    {
+        return Err(Error::Validation(String::from(
+            "Reminders can only be posted in text channels",
+        )));
+    }
+    let course =
+        course_lookup::resolve_course(ctx.data().storage.as_ref(), guild_id.0 as i64, &course)
+            .await?;
+
+    let link = database_utils::CourseRole {
+        guild_id: guild_id.0 as i64,
+        fk_course_id: course.id,
+        role_id: role.id.0 as i64,
+        channel_id: channel.map(|channel| channel.id.0 as i64),
+    };
+    ctx.data()
+        .storage
+        .set_course_role(&link, &actor(ctx))
+        .await?;
+
+    let reminders = match link.channel_id {
+        Some(channel_id) => format!("reminders in <#{}>", channel_id),
+        None => String::from("no reminders"),
+    };
+    let response = format!(
+        "Linked {} to <@&{}>, {}. Members already enrolled get the role with `/course_role reconcile`",
+        course.code, link.role_id, reminders
+    );
+    ctx.send(|m| m.content(response).allowed_mentions(|a| a.empty_parse()))
+        .await?;
+
+    Ok(())
+}
+
+/// Stop giving the course's role, members keep it until the next reconcile
+#[poise::command(slash_command, guild_only, rename = "unlink")]
+pub async fn course_role_unlink(
+    ctx: Context<'_>,
+    #[description = "Course ID or code"]
+    #[max_length = 20]
+    course: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a guild")?;
+    let course =
+        course_lookup::resolve_course(ctx.data().storage.as_ref(), guild_id.0 as i64, &course)
+            .await?;
+
+    let response = match ctx
+        .data()
+        .storage
+        .delete_course_role(course.id, &actor(ctx))
+        .await?
+    {
+        Some(link) => format!("Unlinked {} from <@&{}>", course.code, link.role_id),
+        None => format!("No role is linked to {}", course.code),
+    };
+    ctx.send(|m| m.content(response).allowed_mentions(|a| a.empty_parse()))
+        .await?;
+
+    Ok(())
+}
+
+/// Give linked roles to enrolled members and take them from everyone else
+#[poise::command(slash_command, guild_only, rename = "reconcile")]
+pub async fn course_role_reconcile(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a guild")?;
+    // Members are changed one request at a time, which can take longer than Discord waits for a reply
+    ctx.defer().await?;
+
+    let reconciliation = course_roles::reconcile(
+        &ctx.serenity_context().http,
+        ctx.data().storage.as_ref(),
+        guild_id,
+    )
+    .await?;
+
+    let mut response = format!(
+        "Gave {} and removed {} course role(s)",
+        reconciliation.added, reconciliation.removed
+    );
+    if reconciliation.failed > 0 {
+        response += &format!(
+            ", {} change(s) failed. The bot needs Manage Roles and a role above the course roles",
+            reconciliation.failed
+        );
+    }
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    subcommands("cohort_create", "cohort_delete", "cohort_list")
+)]
+pub async fn cohort(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add a cohort members can join with `/my_cohort`
+#[poise::command(slash_command, guild_only, rename = "create")]
+pub async fn cohort_create(
+    ctx: Context<'_>,
+    #[description = "Name of the cohort, like a lab group"]
+    #[max_length = 50]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = actor(ctx).guild_id;
+    let name = name.trim();
+    let storage = ctx.data().storage.as_ref();
+    if storage.find_cohort(guild_id, name).await?.is_some() {
+        return Err(Error::Validation(format!("Cohort {} already exists", name)));
+    }
+
+    let cohort = storage.insert_cohort(name, &actor(ctx)).await?;
+    ctx.say(format!("Created cohort {}, ID: {}", cohort.name, cohort.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Delete a cohort with its members and dates, its assessments are shown to everyone again
+#[poise::command(slash_command, guild_only, rename = "delete")]
+pub async fn cohort_delete(
+    ctx: Context<'_>,
+    #[description = "Name of the cohort"]
+    #[max_length = 50]
+    name: String,
+) -> Result<(), Error> {
+    let storage = ctx.data().storage.as_ref();
+    let cohort = find_cohort(storage, actor(ctx).guild_id, &name).await?;
+
+    storage.delete_cohort(cohort.id, &actor(ctx)).await?;
+    ctx.say(format!("Deleted cohort {}", cohort.name)).await?;
+
+    Ok(())
+}
+
+/// List the cohorts with the assessments assigned to them
+#[poise::command(slash_command, guild_only, rename = "list")]
+pub async fn cohort_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = actor(ctx).guild_id;
+    let storage = ctx.data().storage.as_ref();
+    let cohorts = storage.cohorts(guild_id).await?;
+    if cohorts.is_empty() {
+        ctx.say("No cohorts, add one with `/cohort create`").await?;
+        return Ok(());
+    }
+
+    let all_dates = storage.cohort_dates(guild_id).await?;
+    let mut content = String::from("# Cohorts\n");
+    for cohort in &cohorts {
+        let assessment_ids: Vec<String> = all_dates
+            .iter()
+            .filter(|dates| dates.fk_cohort_id == cohort.id)
+            .map(|dates| dates.fk_assessment_id.to_string())
+            .collect();
+        let assigned = match assessment_ids.is_empty() {
+            true => String::from("no assessments of its own"),
+            false => format!("assessments {}", assessment_ids.join(", ")),
+        };
+
+        content += &format!("- {} (ID: {}), {}\n", cohort.name, cohort.id, assigned);
+    }
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+async fn find_cohort(
+    storage: &dyn Storage,
+    guild_id: i64,
+    name: &str,
+) -> Result<database_utils::Cohort, Error> {
+    storage
+        .find_cohort(guild_id, name.trim())
+        .await?
+        .ok_or(Error::NotFound(format!("Cohort not found: {}", name)))
+}
+
+/// A date of `/assign_cohort`, "-" leaves the attempt out for the cohort
+fn cohort_date(attempt: &str, value: Option<String>) -> Result<Option<String>, Error> {
+    let value = match value {
+        Some(value) => value.trim().to_string(),
+        None => return Ok(None),
+    };
+    if value == "-" {
+        return Ok(Some(String::new()));
+    }
+    if NaiveDate::parse_from_str(&value, "%Y-%m-%d").is_err() {
+        return Err(Error::Validation(format!(
+            "{} must be a date like 2024-01-31, or - to leave it out",
+            attempt
+        )));
+    }
+
+    Ok(Some(value))
+}
+
+/// Show an assessment only to the cohorts assigned to it, optionally with their own dates
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "access_control::course_editor_check"
+)]
+pub async fn assign_cohort(
+    ctx: Context<'_>,
+    #[description = "Assessment ID"] assessment_id: i64,
+    #[description = "Name of the cohort"]
+    #[max_length = 50]
+    cohort: String,
+    #[description = "YYYY-MM-DD, - to leave it out, the assessment's date if not given"]
+    take1: Option<String>,
+    #[description = "YYYY-MM-DD, - to leave it out, the assessment's date if not given"]
+    retake1: Option<String>,
+    #[description = "YYYY-MM-DD, - to leave it out, the assessment's date if not given"]
+    retake2: Option<String>,
+) -> Result<(), Error> {
+    let assessment = ctx
+        .data()
+        .storage
+        .assessment(actor(ctx).guild_id, assessment_id)
+        .await?
+        .ok_or(Error::NotFound(format!(
+            "Assessment not found with id: {}",
+            assessment_id
+        )))?;
+    access_control::can_edit_course(ctx, assessment.fk_course_id).await?;
+
+    let storage = ctx.data().storage.as_ref();
+    let cohort = find_cohort(storage, actor(ctx).guild_id, &cohort).await?;
+    let dates = database_utils::CohortDates {
+        fk_assessment_id: assessment.id,
+        fk_cohort_id: cohort.id,
+        take1: cohort_date("take1", take1)?,
+        retake1: cohort_date("retake1", retake1)?,
+        retake2: cohort_date("retake2", retake2)?,
+    };
+    storage.set_cohort_dates(&dates, &actor(ctx)).await?;
+
+    let shown = cohorts::cohort_assessments(vec![assessment], &[dates], Some(cohort.id));
+    let response = format!(
+        "Assigned {} to {}, other cohorts no longer see it unless assigned too. Dates: {}",
+        shown[0].name,
+        cohort.name,
+        utils::assessment_attempts(&shown[0])
+            .iter()
+            .map(|(attempt, date)| format!("{} {}", attempt, date.format("%Y-%m-%d")))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+/// Stop showing an assessment to a cohort, once no cohort is left it is shown to everyone
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "access_control::course_editor_check"
+)]
+pub async fn unassign_cohort(
+    ctx: Context<'_>,
+    #[description = "Assessment ID"] assessment_id: i64,
+    #[description = "Name of the cohort"]
+    #[max_length = 50]
+    cohort: String,
+) -> Result<(), Error> {
+    let assessment = ctx
+        .data()
+        .storage
+        .assessment(actor(ctx).guild_id, assessment_id)
+        .await?
+        .ok_or(Error::NotFound(format!(
+            "Assessment not found with id: {}",
+            assessment_id
+        )))?;
+    access_control::can_edit_course(ctx, assessment.fk_course_id).await?;
+
+    let storage = ctx.data().storage.as_ref();
+    let cohort = find_cohort(storage, actor(ctx).guild_id, &cohort).await?;
+    let response = match storage
+        .delete_cohort_dates(assessment.id, cohort.id, &actor(ctx))
+        .await?
+    {
+        true => format!("Unassigned {} from {}", assessment.name, cohort.name),
+        false => format!("{} is not assigned to {}", assessment.name, cohort.name),
+    };
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+/// Show your cohort, join one, or leave it with "none"
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "SEND_MESSAGES",
+    user_cooldown = "5"
+)]
+pub async fn my_cohort(
+    ctx: Context<'_>,
+    #[description = "Name of the cohort to join, none to leave yours"]
+    #[max_length = 50]
+    cohort: Option<String>,
+) -> Result<(), Error> {
+    let actor = actor(ctx);
+    let storage = ctx.data().storage.as_ref();
+
+    let response = match cohort {
+        None => match storage.member_cohort(actor.guild_id, actor.user_id).await? {
+            Some(cohort) => format!("You are in {}", cohort.name),
+            None => String::from("You are not in a cohort"),
+        },
+        Some(name) if name.trim().eq_ignore_ascii_case("none") => {
+            storage
+                .set_member_cohort(actor.guild_id, actor.user_id, None)
+                .await?;
+            String::from("You left your cohort, you now see the dates everyone sees")
+        }
+        Some(name) => {
+            let cohort = find_cohort(storage, actor.guild_id, &name).await?;
+            storage
+                .set_member_cohort(actor.guild_id, actor.user_id, Some(cohort.id))
+                .await?;
+            format!(
+                "You joined {}, /list_assessments and /my_deadlines show its dates",
+                cohort.name
+            )
+        }
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn audit(
+    ctx: Context<'_>,
+    #[description = "Only show changes of this kind of data"] entity: Option<
+        audit_log::AuditEntity,
+    >,
+    #[description = "Only show changes of this ID"] entity_id: Option<i64>,
+    #[description = "Only show changes made by this user"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let entries = ctx
+        .data()
+        .storage
+        .audit_entries(
+            actor(ctx).guild_id,
+            entity.as_ref().map(|entity| entity.as_str()),
+            entity_id,
+            user.map(|user| user.id.0 as i64),
+        )
+        .await?;
+
+    let content = audit_log::format_audit_entries(&entries);
+    ctx.send(|m| m.content(content).allowed_mentions(|a| a.empty_parse()))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn undo(
+    ctx: Context<'_>,
+    #[description = "Audit entry ID from /audit"] id: i64,
+) -> Result<(), Error> {
+    // Failures are reported to the user by `on_error`
+    let entry = ctx.data().storage.undo(id, &actor(ctx)).await?;
+
+    let response = format!(
+        "Undone {} of {} ID: {}",
+        entry.action,
+        entry.entity.replace('_', " "),
+        entry.entity_id
+    );
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThemeScope {
+    #[name = "me"]
+    User,
+    #[name = "server"]
+    Guild,
+}
+
+/// Pick how due dates are colored in tables and calendars
+#[poise::command(slash_command, user_cooldown = "5")]
+pub async fn theme(
+    ctx: Context<'_>,
+    #[description = "Color theme, leave empty to go back to the default"] theme: Option<ThemeName>,
+    #[description = "Just for you (default) or the server default, which needs Manage Server"]
+    scope: Option<ThemeScope>,
+) -> Result<(), Error> {
+    let actor = actor(ctx);
+    let scope = scope.unwrap_or(ThemeScope::User);
+
+    let (guild_id, user_id) = match scope {
+        ThemeScope::User => (0, actor.user_id),
+        ThemeScope::Guild => {
+            let member = ctx
+                .author_member()
+                .await
+                .ok_or(Error::Permission(String::from(
+                    "The server theme can only be set in a server",
+                )))?;
+            if !access_control::can_manage_guild(&member) {
+                return Err(Error::Permission(String::from(
+                    "Only members with Manage Server can change the server theme",
+                )));
+            }
+            (actor.guild_id, 0)
+        }
+    };
+
+    let storage = ctx.data().storage.as_ref();
+    storage
+        .set_theme_preference(guild_id, user_id, theme.map(|theme| theme.name()))
+        .await?;
+
+    let target = match scope {
+        ThemeScope::User => "you",
+        ThemeScope::Guild => "this server",
+    };
+    let options = render_options(storage, &actor).await?;
+    let response = format!(
+        "Theme for {} set to **{}**\n{}",
+        target,
+        theme.unwrap_or_default().name(),
+        rendering::legend(&options)
+    );
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Re-syncs or clears the slash commands in this guild or globally without a restart
+#[poise::command(slash_command, owners_only, hide_in_help)]
+pub async fn register(ctx: Context<'_>) -> Result<(), Error> {
+    poise::builtins::register_application_commands_buttons(ctx).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::Theme;
+    use crate::storage::fixtures::*;
+    use crate::storage::memory::MemoryStorage;
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            today: chrono::NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            ..RenderOptions::from_config(Theme::DEFAULT)
+        }
+    }
+
+    async fn storage_with_courses(count: usize) -> MemoryStorage {
+        let storage = MemoryStorage::new();
+        for index in 0..count {
+            storage
+                .insert_course(&course(&format!("C{:02}", index), None), &ACTOR)
+                .await
+                .unwrap();
+        }
+        storage
+    }
+
+    fn fields(values: [&str; 5]) -> Vec<(String, String)> {
+        ["name", "code", "semester", "year", "credit"]
+            .iter()
+            .zip(values)
+            .map(|(id, value)| (id.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn edited_course_keeps_the_id_and_term_and_checks_limits() {
+        let original = database_utils::Course {
+            id: 7,
+            ..course("ABC", Some(3))
+        };
+
+        let edited = edited_course(
+            &original,
+            &fields(["Algebra", " ALG1 ", "2", "2025", "7.5"]),
+        )
+        .unwrap();
+        assert_eq!((edited.id, edited.fk_term_id), (7, Some(3)));
+        assert_eq!((edited.code.as_str(), edited.credit), ("ALG1", 7.5));
+
+        assert!(edited_course(&original, &fields(["Algebra", "ALG1", "21", "2025", "1"])).is_err());
+        assert!(edited_course(&original, &fields(["Al", "ALG1", "2", "2025", "1"])).is_err());
+        assert!(edited_course(&original, &fields(["Algebra", "ALG1", "2", "2025", "x"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn course_dashboard_shows_edits_and_sessions() {
+        let storage = storage_with_courses(1).await;
+        let mut course = storage.all_courses(ACTOR.guild_id).await.unwrap().remove(0);
+        storage.add_session(database_utils::Session {
+            id: 0,
+            weekday: 1,
+            start_time: String::from("08:00"),
+            end_time: String::from("09:00"),
+            location: String::from("Hall A"),
+            fk_course_id: course.id,
+        });
+
+        course.name = String::from("Renamed");
+        storage.update_course(&course, &ACTOR).await.unwrap();
+        let content = course_dashboard(&storage, ACTOR.guild_id, course.id, &options())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(content.starts_with("# C00 Renamed\n"));
+        assert!(content.contains("Hall A"));
+        assert!(course_dashboard(&storage, ACTOR.guild_id, 999, &options())
+            .await
+            .unwrap()
+            .is_none());
+
+        storage
+            .set_subscribed(course.id, true, &ACTOR)
+            .await
+            .unwrap();
+        assert!(storage.is_subscribed(2, course.id).await.unwrap());
+        storage
+            .set_subscribed(course.id, false, &ACTOR)
+            .await
+            .unwrap();
+        assert!(!storage.is_subscribed(2, course.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn enrollment_limits_choices_and_deadlines_to_the_users_courses() {
+        let storage = storage_with_courses(3).await;
+        let courses = storage.all_courses(ACTOR.guild_id).await.unwrap();
+        let codes = |courses: Vec<database_utils::Course>| -> Vec<String> {
+            courses.into_iter().map(|course| course.code).collect()
+        };
+        assert_eq!(
+            my_deadlines_content(&storage, &ACTOR, &options())
+                .await
+                .unwrap(),
+            NOT_ENROLLED
+        );
+
+        for course in &courses[..2] {
+            storage
+                .set_subscribed(course.id, true, &ACTOR)
+                .await
+                .unwrap();
+        }
+        let all = CourseView::default();
+        let offered = enrollment_choices(&storage, &ACTOR, true, &all)
+            .await
+            .unwrap();
+        assert_eq!(codes(offered), ["C02"]);
+        let enrolled = enrollment_choices(&storage, &ACTOR, false, &all)
+            .await
+            .unwrap();
+        assert_eq!(codes(enrolled), ["C00", "C01"]);
+        let filtered = CourseView {
+            query: Some(String::from("c01")),
+            ..CourseView::default()
+        };
+        let enrolled = enrollment_choices(&storage, &ACTOR, false, &filtered)
+            .await
+            .unwrap();
+        assert_eq!(codes(enrolled), ["C01"]);
+        assert_eq!(
+            my_deadlines_content(&storage, &ACTOR, &options())
+                .await
+                .unwrap(),
+            "No upcoming deadlines in your courses"
+        );
+
+        for (name, course) in [("Quiz", &courses[0]), ("Not enrolled", &courses[2])] {
+            let assessment = database_utils::Assessment {
+                take1: String::from("2024-03-20"),
+                ..assessment(name, course.id)
+            };
+            storage
+                .insert_assessment(&assessment, &ACTOR)
+                .await
+                .unwrap();
+        }
+        let content = my_deadlines_content(&storage, &ACTOR, &options())
+            .await
+            .unwrap();
+        assert!(content.starts_with("# My deadlines\n"));
+        assert!(content.contains("Quiz"));
+        assert!(!content.contains("Not enrolled"));
+
+        storage.delete_course(courses[1].id, &ACTOR).await.unwrap();
+        let enrolled = storage.subscribed_courses(ACTOR.guild_id, 2).await.unwrap();
+        assert_eq!(codes(enrolled), ["C00"]);
+    }
+
+    #[tokio::test]
+    async fn assessment_page_shows_the_dates_of_the_actors_cohort() {
+        let storage = storage_with_courses(1).await;
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        for name in ["Exam", "Lab"] {
+            storage
+                .insert_assessment(&assessment(name, course_id), &ACTOR)
+                .await
+                .unwrap();
+        }
+        let lab = storage.course_assessments(course_id).await.unwrap()[1].clone();
+        let group_a = storage.add_cohort(ACTOR.guild_id, "Group A");
+        let group_b = storage.add_cohort(ACTOR.guild_id, "Group B");
+        storage.add_cohort_dates(database_utils::CohortDates {
+            fk_assessment_id: lab.id,
+            fk_cohort_id: group_a.id,
+            take1: Some(String::from("2024-02-01")),
+            retake1: None,
+            retake2: None,
+        });
+        storage.join_cohort(&group_a, ACTOR.user_id);
+        let other = Actor {
+            user_id: 3,
+            ..ACTOR
+        };
+        storage.join_cohort(&group_b, other.user_id);
+
+        let page = |actor: Actor| {
+            let storage = &storage;
+            async move {
+                assessment_list_page(
+                    storage,
+                    &actor,
+                    course_id,
+                    1,
+                    &AssessmentView::default(),
+                    &options(),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+            }
+        };
+        let listing = page(ACTOR).await;
+        assert_eq!(listing.total, 2);
+        assert!(listing.content.contains("Group A"));
+        assert!(listing.content.contains("2024-02-01"));
+        let listing = page(other).await;
+        assert_eq!(listing.total, 1);
+        assert!(!listing.content.contains("Lab"));
+    }
+
+    #[tokio::test]
+    async fn create_course_defaults_to_the_active_term() {
+        let storage = MemoryStorage::new();
+        let active = storage.add_term(term("Current", true));
+        let other = storage.add_term(term("Other", false));
+
+        create_course(&storage, course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        create_course(&storage, course("DEF", Some(other.id)), &ACTOR)
+            .await
+            .unwrap();
+
+        let courses = storage.all_courses(ACTOR.guild_id).await.unwrap();
+        assert_eq!(courses[0].fk_term_id, Some(active.id));
+        assert_eq!(courses[1].fk_term_id, Some(other.id));
+    }
+
+    #[tokio::test]
+    async fn removal_prompt_lists_the_assessments() {
+        let storage = storage_with_courses(1).await;
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        for name in ["Midterm", "Final exam"] {
+            storage
+                .insert_assessment(&assessment(name, course_id), &ACTOR)
+                .await
+                .unwrap();
+        }
+
+        let prompt = course_removal_prompt(&storage, ACTOR.guild_id, course_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(prompt.contains("C00 C00 name"));
+        assert!(prompt.contains("its 2 assessment(s)"));
+        assert!(prompt.contains("- Midterm"));
+        assert!(prompt.contains("- Final exam"));
+
+        assert!(
+            course_removal_prompt(&storage, ACTOR.guild_id, course_id + 100)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn invalid_dates_are_reported_per_field() {
+        let mut assessment = assessment("Exam", 1);
+        assert!(invalid_date_warnings(&assessment).is_empty());
+
+        assessment.retake1 = String::from("next week");
+        assert_eq!(
+            invalid_date_warnings(&assessment),
+            vec![String::from(
+                "Warning, invalid date format of retake1: next week"
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn course_pages_stop_after_the_last_course() {
+        let per_page = config::get().pages.courses;
+        let storage = storage_with_courses(per_page + 1).await;
+
+        let first = course_list_page(
+            &storage,
+            ACTOR.guild_id,
+            1,
+            &CourseView::default(),
+            &options(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(first.total, per_page + 1);
+        assert!(first.content.contains("(Page 1/2)"));
+        assert!(!first.content.contains(&format!("C{:02}", per_page)));
+
+        let second = course_list_page(
+            &storage,
+            ACTOR.guild_id,
+            2,
+            &CourseView::default(),
+            &options(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(second.content.contains(&format!("C{:02}", per_page)));
+
+        assert!(course_list_page(
+            &storage,
+            ACTOR.guild_id,
+            0,
+            &CourseView::default(),
+            &options()
+        )
+        .await
+        .unwrap()
+        .is_none());
+        assert!(course_list_page(
+            &storage,
+            ACTOR.guild_id,
+            3,
+            &CourseView::default(),
+            &options()
+        )
+        .await
+        .unwrap()
+        .is_none());
+    }
+
+    #[tokio::test]
+    async fn assessments_sort_by_next_attempt() {
+        let storage = storage_with_courses(1).await;
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        for (name, take1, retake1) in [
+            ("Finished", "2024-01-10", "2024-02-10"),
+            ("Later", "2024-03-01", "2024-05-01"),
+            ("Soon", "2024-03-16", "2024-04-16"),
+            ("Undated", "TBA", "TBA"),
+        ] {
+            let assessment = database_utils::Assessment {
+                take1: take1.to_string(),
+                retake1: retake1.to_string(),
+                retake2: String::new(),
+                ..assessment(name, course_id)
+            };
+            storage
+                .insert_assessment(&assessment, &ACTOR)
+                .await
+                .unwrap();
+        }
+
+        let listing = assessment_list_page(
+            &storage,
+            &ACTOR,
+            course_id,
+            1,
+            &AssessmentView {
+                sort: AssessmentSort::NextAttempt,
+                ..AssessmentView::default()
+            },
+            &options(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let positions: Vec<usize> = ["Soon", "Later", "Finished", "Undated"]
+            .iter()
+            .map(|name| listing.content.find(name).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(listing.content.contains("tomorrow"));
+    }
+
+    #[tokio::test]
+    async fn assessment_page_offers_courses_of_the_course_term() {
+        let storage = MemoryStorage::new();
+        let old = storage.add_term(term("Old", false));
+        storage.add_term(term("Current", true));
+        create_course(&storage, course("OLD", Some(old.id)), &ACTOR)
+            .await
+            .unwrap();
+        create_course(&storage, course("NEW", None), &ACTOR)
+            .await
+            .unwrap();
+        let old_course = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].clone();
+
+        let listing = assessment_list_page(
+            &storage,
+            &ACTOR,
+            old_course.id,
+            1,
+            &AssessmentView::default(),
+            &options(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(listing.total, 0);
+        assert!(listing.content.starts_with("# OLD name"));
+        let codes: Vec<_> = listing.courses.iter().map(|c| c.code.as_str()).collect();
+        assert_eq!(codes, vec!["OLD"]);
+    }
+
+    #[tokio::test]
+    async fn restoring_a_course_whose_code_was_taken_fails() {
+        let storage = MemoryStorage::new();
+        let id = storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap() as i64;
+        storage.delete_course(id, &ACTOR).await.unwrap();
+        storage
+            .insert_course(&course("abc", None), &ACTOR)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            restore_trashed_course(&storage, &ACTOR, id).await,
+            Err(Error::Validation(_))
+        ));
+        assert_eq!(
+            storage.deleted_courses(ACTOR.guild_id).await.unwrap().len(),
+            1
+        );
+        assert!(restore_trashed_course(&storage, &ACTOR, 999)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
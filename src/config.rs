@@ -0,0 +1,327 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::error::Error;
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationScope {
+    /// Commands are available everywhere, Discord may take up to an hour to propagate changes
+    Global,
+    /// Commands are registered instantly in the guilds listed in `guild_ids`
+    Guilds,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistrationConfig {
+    pub scope: RegistrationScope,
+    pub guild_ids: Vec<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PagesConfig {
+    pub courses: usize,
+    pub assessments: usize,
+}
+
+/// Days until a due date is colored as this week or next week, anything later is green
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorsConfig {
+    pub this_week_days: i64,
+    pub next_week_days: i64,
+    /// Weekly assessment weight at which the workload heat map is fully red
+    pub max_weekly_weight: f64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    pub trash_purge_interval_minutes: u64,
+    pub trash_retention_days: i64,
+    pub confirmation_timeout_seconds: i64,
+    pub reminder_interval_minutes: u64,
+    /// Attempts are reminded of once, when they are this many days away or closer
+    pub reminder_days_before: i64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database_url: String,
+    pub timezone: String,
+    pub registration: RegistrationConfig,
+    pub pages: PagesConfig,
+    pub colors: ColorsConfig,
+    pub scheduler: SchedulerConfig,
+}
+
+impl Default for RegistrationConfig {
+    fn default() -> Self {
+        RegistrationConfig {
+            scope: RegistrationScope::Global,
+            guild_ids: Vec::new(),
+        }
+    }
+}
+
+impl Default for PagesConfig {
+    fn default() -> Self {
+        PagesConfig {
+            courses: 5,
+            assessments: 9,
+        }
+    }
+}
+
+impl Default for ColorsConfig {
+    fn default() -> Self {
+        ColorsConfig {
+            this_week_days: 7,
+            next_week_days: 14,
+            max_weekly_weight: 50.0,
+        }
+    }
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            trash_purge_interval_minutes: 60,
+            trash_retention_days: 30,
+            confirmation_timeout_seconds: 60,
+            reminder_interval_minutes: 60,
+            reminder_days_before: 1,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database_url: String::from("sqlite://courses.db"),
+            timezone: String::from("UTC"),
+            registration: RegistrationConfig::default(),
+            pages: PagesConfig::default(),
+            colors: ColorsConfig::default(),
+            scheduler: SchedulerConfig::default(),
+        }
+    }
+}
+
+fn override_from_env<T: FromStr>(name: &str, target: &mut T, problems: &mut Vec<String>) {
+    if let Ok(value) = std::env::var(name) {
+        match value.parse() {
+            Ok(value) => *target = value,
+            Err(_) => problems.push(format!("{} has an invalid value: {}", name, value)),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the TOML file at `CONFIG_PATH` (default `config.toml`, optional) and applies env overrides
+    pub fn load() -> Result<Config, Error> {
+        let path = std::env::var("CONFIG_PATH").unwrap_or(String::from(DEFAULT_CONFIG_PATH));
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).map_err(|e| {
+                Error::Validation(format!("Could not parse config file {}: {}", path, e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(e) => {
+                return Err(Error::Validation(format!(
+                    "Could not read config file {}: {}",
+                    path, e
+                )))
+            }
+        };
+
+        let mut problems = Vec::new();
+        config.apply_env(&mut problems);
+        problems.extend(config.validate());
+
+        if !problems.is_empty() {
+            return Err(Error::Validation(format!(
+                "Invalid configuration:\n  - {}",
+                problems.join("\n  - ")
+            )));
+        }
+
+        Ok(config)
+    }
+
+    fn apply_env(&mut self, problems: &mut Vec<String>) {
+        override_from_env("DATABASE_URL", &mut self.database_url, problems);
+        override_from_env("TIMEZONE", &mut self.timezone, problems);
+        override_from_env("COURSES_PER_PAGE", &mut self.pages.courses, problems);
+        override_from_env(
+            "ASSESSMENTS_PER_PAGE",
+            &mut self.pages.assessments,
+            problems,
+        );
+        override_from_env("THIS_WEEK_DAYS", &mut self.colors.this_week_days, problems);
+        override_from_env("NEXT_WEEK_DAYS", &mut self.colors.next_week_days, problems);
+        override_from_env(
+            "MAX_WEEKLY_WEIGHT",
+            &mut self.colors.max_weekly_weight,
+            problems,
+        );
+        override_from_env(
+            "TRASH_PURGE_INTERVAL_MINUTES",
+            &mut self.scheduler.trash_purge_interval_minutes,
+            problems,
+        );
+        override_from_env(
+            "TRASH_RETENTION_DAYS",
+            &mut self.scheduler.trash_retention_days,
+            problems,
+        );
+        override_from_env(
+            "CONFIRMATION_TIMEOUT_SECONDS",
+            &mut self.scheduler.confirmation_timeout_seconds,
+            problems,
+        );
+        override_from_env(
+            "REMINDER_INTERVAL_MINUTES",
+            &mut self.scheduler.reminder_interval_minutes,
+            problems,
+        );
+        override_from_env(
+            "REMINDER_DAYS_BEFORE",
+            &mut self.scheduler.reminder_days_before,
+            problems,
+        );
+
+        if let Ok(scope) = std::env::var("REGISTRATION_SCOPE") {
+            match scope.as_str() {
+                "global" => self.registration.scope = RegistrationScope::Global,
+                "guilds" => self.registration.scope = RegistrationScope::Guilds,
+                _ => problems.push(format!(
+                    "REGISTRATION_SCOPE must be global or guilds, got: {}",
+                    scope
+                )),
+            }
+        }
+        if let Ok(guild_ids) = std::env::var("REGISTRATION_GUILD_IDS") {
+            match guild_ids
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::parse)
+                .collect()
+            {
+                Ok(guild_ids) => self.registration.guild_ids = guild_ids,
+                Err(_) => problems.push(format!(
+                    "REGISTRATION_GUILD_IDS must be a comma separated list of ids, got: {}",
+                    guild_ids
+                )),
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if is_postgres_url(&self.database_url) {
+            if !cfg!(feature = "postgres") {
+                problems.push(String::from(
+                    "database_url is a postgres url, but the bot was built without the postgres feature",
+                ));
+            }
+        } else if !self.database_url.starts_with("sqlite:") {
+            problems.push(format!(
+                "database_url must be a sqlite: or postgres:// url, got: {}",
+                self.database_url
+            ));
+        }
+        if Tz::from_str(&self.timezone).is_err() {
+            problems.push(format!(
+                "timezone must be an IANA name such as Europe/Tallinn, got: {}",
+                self.timezone
+            ));
+        }
+        if self.registration.scope == RegistrationScope::Guilds
+            && self.registration.guild_ids.is_empty()
+        {
+            problems.push(String::from(
+                "registration.guild_ids must not be empty when registration.scope is guilds",
+            ));
+        }
+        // Discord API limits to 2000 characters per message
+        if !(1..=10).contains(&self.pages.courses) {
+            problems.push(String::from("pages.courses must be between 1 and 10"));
+        }
+        if !(1..=15).contains(&self.pages.assessments) {
+            problems.push(String::from("pages.assessments must be between 1 and 15"));
+        }
+        if self.colors.this_week_days < 0 || self.colors.next_week_days < self.colors.this_week_days
+        {
+            problems.push(String::from(
+                "colors.next_week_days must be at least colors.this_week_days, which must not be negative",
+            ));
+        }
+        if self.colors.max_weekly_weight <= 0.0 {
+            problems.push(String::from("colors.max_weekly_weight must be positive"));
+        }
+        if self.scheduler.trash_purge_interval_minutes == 0 {
+            problems.push(String::from(
+                "scheduler.trash_purge_interval_minutes must be positive",
+            ));
+        }
+        if self.scheduler.trash_retention_days < 0 {
+            problems.push(String::from(
+                "scheduler.trash_retention_days must not be negative",
+            ));
+        }
+        if self.scheduler.confirmation_timeout_seconds <= 0 {
+            problems.push(String::from(
+                "scheduler.confirmation_timeout_seconds must be positive",
+            ));
+        }
+        if self.scheduler.reminder_interval_minutes == 0 {
+            problems.push(String::from(
+                "scheduler.reminder_interval_minutes must be positive",
+            ));
+        }
+        if self.scheduler.reminder_days_before < 0 {
+            problems.push(String::from(
+                "scheduler.reminder_days_before must not be negative",
+            ));
+        }
+
+        problems
+    }
+
+    pub fn tz(&self) -> Tz {
+        Tz::from_str(&self.timezone).unwrap_or(Tz::UTC)
+    }
+}
+
+pub fn is_postgres_url(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+/// Makes the loaded configuration available through `get`, only the first call has an effect
+pub fn init(config: Config) {
+    CONFIG.set(config).ok();
+}
+
+/// The configuration loaded at startup, defaults if `init` was never called
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Today's date in the configured timezone
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&get().tz()).date_naive()
+}
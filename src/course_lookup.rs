@@ -0,0 +1,235 @@
+use crate::database_utils::Course;
+use crate::error::Error;
+use crate::storage::Storage;
+
+// At most this many "did you mean" suggestions
+const SUGGESTIONS: usize = 3;
+
+/// Edits needed to turn one string into the other
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Codes close to the input, or starting with it, closest first
+pub fn suggestions(input: &str, courses: &[Course]) -> Vec<String> {
+    let input = input.trim().to_lowercase();
+    let allowed = (input.chars().count() / 3).max(1);
+
+    let mut close: Vec<(usize, &str)> = courses
+        .iter()
+        .map(|course| {
+            let code = course.code.to_lowercase();
+            let distance = match code.starts_with(&input) {
+                true => 0,
+                false => distance(&input, &code),
+            };
+            (distance, course.code.as_str())
+        })
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    close.sort();
+
+    let mut codes: Vec<String> = Vec::new();
+    for (_, code) in close {
+        if !codes.iter().any(|known| known.eq_ignore_ascii_case(code)) {
+            codes.push(code.to_string());
+        }
+    }
+    codes.truncate(SUGGESTIONS);
+
+    codes
+}
+
+/// Looks a course of the guild up by code, ignoring case, or by id when no code matches.
+/// Codes repeat across semesters, the course of the active term wins, otherwise the most recent one
+pub async fn resolve_course(
+    storage: &dyn Storage,
+    guild_id: i64,
+    input: &str,
+) -> Result<Course, Error> {
+    let input = input.trim();
+
+    let courses = storage.all_courses(guild_id).await?;
+    let active_term_id = storage.active_term(guild_id).await?.map(|term| term.id);
+    let found = courses
+        .iter()
+        .filter(|course| course.code.eq_ignore_ascii_case(input))
+        .max_by_key(|course| {
+            (
+                active_term_id.is_some() && course.fk_term_id == active_term_id,
+                course.year,
+                course.semester,
+            )
+        });
+
+    if let Some(course) = found {
+        return Ok(course.clone());
+    }
+
+    // All-digit codes exist, the id is only tried once no code matched
+    match input.parse::<i64>() {
+        Ok(id) => storage
+            .course(guild_id, id)
+            .await?
+            .ok_or(Error::NotFound(format!("Course not found with id: {}", id))),
+        Err(_) => {
+            let suggestions = suggestions(input, &courses);
+            let hint = match suggestions.is_empty() {
+                true => String::new(),
+                false => format!(". Did you mean {}?", suggestions.join(", ")),
+            };
+            Err(Error::NotFound(format!(
+                "Course not found: {}{}",
+                input, hint
+            )))
+        }
+    }
+}
+
+/// Codes are unique among the guild's courses of a semester and year, ignoring case
+pub async fn ensure_unique_code(
+    storage: &dyn Storage,
+    guild_id: i64,
+    course: &Course,
+) -> Result<(), Error> {
+    check_unique_code(&storage.all_courses(guild_id).await?, course)
+}
+
+/// `ensure_unique_code` against live courses the caller already fetched,
+/// like an undo reading them inside its transaction
+pub fn check_unique_code(courses: &[Course], course: &Course) -> Result<(), Error> {
+    let taken = courses.iter().find(|other| {
+        other.id != course.id
+            && other.semester == course.semester
+            && other.year == course.year
+            && other.code.eq_ignore_ascii_case(&course.code)
+    });
+
+    match taken {
+        Some(other) => Err(Error::Validation(format!(
+            "Course code {} is already used by {} (ID: {}) in semester {} of {}",
+            other.code, other.name, other.id, other.semester, other.year
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+    use crate::storage::memory::MemoryStorage;
+
+    async fn insert(storage: &MemoryStorage, code: &str, year: i64, fk_term_id: Option<i64>) {
+        let course = Course {
+            year,
+            ..course(code, fk_term_id)
+        };
+        storage.insert_course(&course, &ACTOR).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn codes_resolve_to_the_active_term_then_the_latest_year() {
+        let storage = MemoryStorage::new();
+        let active = storage.add_term(term("Current", true));
+        insert(&storage, "CSE101", 2023, None).await;
+        insert(&storage, "CSE101", 2024, Some(active.id)).await;
+        insert(&storage, "CSE101", 2025, None).await;
+        insert(&storage, "PHY201", 2023, None).await;
+        insert(&storage, "PHY201", 2024, None).await;
+
+        let course = resolve_course(&storage, ACTOR.guild_id, " cse101 ")
+            .await
+            .unwrap();
+        assert_eq!(course.year, 2024);
+        let course = resolve_course(&storage, ACTOR.guild_id, "PHY201")
+            .await
+            .unwrap();
+        assert_eq!(course.year, 2024);
+        let course = resolve_course(&storage, ACTOR.guild_id, &course.id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(course.code, "PHY201");
+    }
+
+    #[tokio::test]
+    async fn near_misses_suggest_codes() {
+        let storage = MemoryStorage::new();
+        for code in ["CSE101", "CSE102", "MTH101"] {
+            insert(&storage, code, 2024, None).await;
+        }
+
+        let error = resolve_course(&storage, ACTOR.guild_id, "CSE10")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.user_message(0),
+            "Course not found: CSE10. Did you mean CSE101, CSE102?"
+        );
+        let error = resolve_course(&storage, ACTOR.guild_id, "MHT101")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.user_message(0),
+            "Course not found: MHT101. Did you mean MTH101?"
+        );
+        let error = resolve_course(&storage, ACTOR.guild_id, "BIO999")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.user_message(0), "Course not found: BIO999");
+    }
+
+    #[tokio::test]
+    async fn all_digit_codes_win_over_ids() {
+        let storage = MemoryStorage::new();
+        insert(&storage, "ABC", 2024, None).await;
+        insert(&storage, "1", 2024, None).await;
+
+        let course = resolve_course(&storage, ACTOR.guild_id, "1").await.unwrap();
+        assert_eq!(course.code, "1");
+        let course = resolve_course(&storage, ACTOR.guild_id, "2").await.unwrap();
+        assert_eq!(course.code, "1");
+        assert!(resolve_course(&storage, ACTOR.guild_id, "3").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn codes_are_unique_per_guild_semester_and_year() {
+        let storage = MemoryStorage::new();
+        insert(&storage, "CSE101", 2024, None).await;
+
+        let same_year = course("cse101", None);
+        let next_year = Course {
+            year: 2025,
+            ..course("CSE101", None)
+        };
+        assert!(ensure_unique_code(&storage, ACTOR.guild_id, &same_year)
+            .await
+            .is_err());
+        assert!(ensure_unique_code(&storage, ACTOR.guild_id, &next_year)
+            .await
+            .is_ok());
+        assert!(ensure_unique_code(&storage, ACTOR.guild_id + 1, &same_year)
+            .await
+            .is_ok());
+
+        let existing = storage.all_courses(ACTOR.guild_id).await.unwrap().remove(0);
+        assert!(ensure_unique_code(&storage, ACTOR.guild_id, &existing)
+            .await
+            .is_ok());
+    }
+}
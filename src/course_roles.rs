@@ -0,0 +1,211 @@
+use poise::serenity_prelude as serenity;
+
+use crate::database_utils::CourseRole;
+use crate::error::Error;
+use crate::storage::Storage;
+
+// Shown in the guild's audit log
+const REASON: &str = "Course enrollment";
+// Discord API returns at most 1000 members per request
+const MEMBERS_PER_REQUEST: u64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoleChange {
+    pub role_id: u64,
+    pub add: bool,
+}
+
+#[derive(Default)]
+pub struct Reconciliation {
+    pub added: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+/// What makes the member's linked roles match the courses they are enrolled in.
+/// Courses may share a role, it stays while any of them is enrolled in
+pub fn member_role_changes(
+    links: &[CourseRole],
+    member_roles: &[u64],
+    enrolled: &[i64],
+) -> Vec<RoleChange> {
+    let mut role_ids: Vec<u64> = links.iter().map(|link| link.role_id as u64).collect();
+    role_ids.sort();
+    role_ids.dedup();
+
+    role_ids
+        .into_iter()
+        .filter_map(|role_id| {
+            let wanted = links.iter().any(|link| {
+                link.role_id as u64 == role_id && enrolled.contains(&link.fk_course_id)
+            });
+            let has = member_roles.contains(&role_id);
+
+            (wanted != has).then_some(RoleChange {
+                role_id,
+                add: wanted,
+            })
+        })
+        .collect()
+}
+
+async fn apply(
+    http: &serenity::Http,
+    guild_id: serenity::GuildId,
+    user_id: u64,
+    change: RoleChange,
+) -> Result<(), serenity::Error> {
+    match change.add {
+        true => {
+            http.add_member_role(guild_id.0, user_id, change.role_id, Some(REASON))
+                .await
+        }
+        false => {
+            http.remove_member_role(guild_id.0, user_id, change.role_id, Some(REASON))
+                .await
+        }
+    }
+}
+
+/// Gives or takes the roles linked to `course_ids` after the member enrolled in or left them.
+/// Returns the roles that could not be changed, enrollment is kept either way
+pub async fn sync_member(
+    http: &serenity::Http,
+    storage: &dyn Storage,
+    guild_id: Option<serenity::GuildId>,
+    member: Option<&serenity::Member>,
+    course_ids: &[i64],
+) -> Result<Vec<u64>, Error> {
+    let (guild_id, member) = match (guild_id, member) {
+        (Some(guild_id), Some(member)) => (guild_id, member),
+        _ => return Ok(Vec::new()),
+    };
+
+    let links = storage.course_roles(guild_id.0 as i64).await?;
+    let touched: Vec<u64> = links
+        .iter()
+        .filter(|link| course_ids.contains(&link.fk_course_id))
+        .map(|link| link.role_id as u64)
+        .collect();
+    if touched.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let enrolled: Vec<i64> = storage
+        .subscribed_courses(guild_id.0 as i64, member.user.id.0 as i64)
+        .await?
+        .iter()
+        .map(|course| course.id)
+        .collect();
+    let member_roles: Vec<u64> = member.roles.iter().map(|role| role.0).collect();
+
+    let mut failed = Vec::new();
+    for change in member_role_changes(&links, &member_roles, &enrolled) {
+        if !touched.contains(&change.role_id) {
+            continue;
+        }
+        if let Err(error) = apply(http, guild_id, member.user.id.0, change).await {
+            tracing::warn!(role = change.role_id, %error, "failed to update course role");
+            failed.push(change.role_id);
+        }
+    }
+
+    Ok(failed)
+}
+
+/// Fixes roles changed by hand, every member ends up with the linked roles of their courses only.
+/// Listing members needs the Server Members intent enabled for the bot
+pub async fn reconcile(
+    http: &serenity::Http,
+    storage: &dyn Storage,
+    guild_id: serenity::GuildId,
+) -> Result<Reconciliation, Error> {
+    let links = storage.course_roles(guild_id.0 as i64).await?;
+    let subscriptions = storage.linked_subscriptions(guild_id.0 as i64).await?;
+    let mut reconciliation = Reconciliation::default();
+    if links.is_empty() {
+        return Ok(reconciliation);
+    }
+
+    let mut after: Option<serenity::UserId> = None;
+    loop {
+        let members = guild_id
+            .members(http, Some(MEMBERS_PER_REQUEST), after)
+            .await?;
+
+        for member in members.iter().filter(|member| !member.user.bot) {
+            let enrolled: Vec<i64> = subscriptions
+                .iter()
+                .filter(|(user_id, _)| *user_id == member.user.id.0 as i64)
+                .map(|(_, course_id)| *course_id)
+                .collect();
+            let member_roles: Vec<u64> = member.roles.iter().map(|role| role.0).collect();
+
+            for change in member_role_changes(&links, &member_roles, &enrolled) {
+                match apply(http, guild_id, member.user.id.0, change).await {
+                    Ok(()) if change.add => reconciliation.added += 1,
+                    Ok(()) => reconciliation.removed += 1,
+                    Err(error) => {
+                        tracing::warn!(role = change.role_id, %error, "failed to reconcile course role");
+                        reconciliation.failed += 1;
+                    }
+                }
+            }
+        }
+
+        if (members.len() as u64) < MEMBERS_PER_REQUEST {
+            break;
+        }
+        after = members.last().map(|member| member.user.id);
+    }
+
+    Ok(reconciliation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(fk_course_id: i64, role_id: i64) -> CourseRole {
+        CourseRole {
+            guild_id: 1,
+            fk_course_id,
+            role_id,
+            channel_id: None,
+        }
+    }
+
+    #[test]
+    fn roles_follow_enrollment_and_shared_roles_stay() {
+        let links = [link(1, 10), link(2, 20), link(3, 20)];
+
+        assert_eq!(
+            member_role_changes(&links, &[], &[1, 3]),
+            [
+                RoleChange {
+                    role_id: 10,
+                    add: true
+                },
+                RoleChange {
+                    role_id: 20,
+                    add: true
+                },
+            ]
+        );
+        // Left course 2 but is still enrolled in course 3, which shares its role
+        assert!(member_role_changes(&links, &[10, 20, 99], &[1, 3]).is_empty());
+        assert_eq!(
+            member_role_changes(&links, &[10, 20, 99], &[]),
+            [
+                RoleChange {
+                    role_id: 10,
+                    add: false
+                },
+                RoleChange {
+                    role_id: 20,
+                    add: false
+                },
+            ]
+        );
+    }
+}
@@ -1,8 +1,10 @@
-#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use sqlx::{Result, SqliteConnection, SqlitePool};
 
-use sqlx::{Result, SqlitePool};
+use crate::audit::{self, Actor};
+use crate::utils;
 
-#[derive(sqlx::FromRow, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
 pub struct Course {
     pub id: i64,
     pub name: String,
@@ -10,9 +12,26 @@ pub struct Course {
     pub semester: i64,
     pub year: i64,
     pub credit: f64,
+    pub fk_term_id: Option<i64>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    #[serde(default)]
+    pub guild_id: i64,
 }
 
-#[derive(sqlx::FromRow, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct Term {
+    pub id: i64,
+    pub name: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub active: bool,
+    pub archived: bool,
+    #[serde(default)]
+    pub guild_id: i64,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
 pub struct Assessment {
     pub id: i64,
     pub name: String,
@@ -21,34 +40,155 @@ pub struct Assessment {
     pub retake1: String,
     pub retake2: String,
     pub fk_course_id: i64,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub id: i64,
+    // 1 = Monday, 7 = Sunday
+    pub weekday: i64,
+    pub start_time: String,
+    pub end_time: String,
+    pub location: String,
+    pub fk_course_id: i64,
 }
 
-pub async fn establish_connection() -> Result<SqlitePool> {
-    let options = sqlx::sqlite::SqliteConnectOptions::new()
-        .filename("./courses.db")
-        .create_if_missing(true);
+// Either a role or a user allowed to edit a course
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct CourseEditor {
+    pub id: i64,
+    pub guild_id: i64,
+    pub role_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub fk_course_id: i64,
+}
 
-    let pool = SqlitePool::connect_with(options).await?;
+// A course together with everything deleted along with it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CourseSnapshot {
+    pub course: Course,
+    pub assessments: Vec<Assessment>,
+    pub sessions: Vec<Session>,
+    pub editors: Vec<CourseEditor>,
+}
 
-    Ok(pool)
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Subscription {
+    pub user_id: i64,
+    pub fk_course_id: i64,
 }
 
-pub async fn get_all_courses(pool: &SqlitePool) -> Result<Vec<Course>, sqlx::Error> {
-    let courses = sqlx::query_as::<_, Course>("SELECT * FROM courses")
-        .fetch_all(pool)
-        .await?;
+// Enrolling in the course through the bot gives the role in the guild,
+// reminders of its assessments are posted in the channel if there is one
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CourseRole {
+    pub guild_id: i64,
+    pub fk_course_id: i64,
+    pub role_id: i64,
+    pub channel_id: Option<i64>,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Cohort {
+    pub id: i64,
+    pub guild_id: i64,
+    pub name: String,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CohortMember {
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub fk_cohort_id: i64,
+}
+
+// A cohort together with the members and dates deleted along with it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CohortSnapshot {
+    pub cohort: Cohort,
+    pub members: Vec<CohortMember>,
+    pub dates: Vec<CohortDates>,
+}
+
+// Assigns an assessment to a cohort, see `cohorts::cohort_assessments`.
+// `None` keeps the assessment's date of the attempt, an empty string leaves the attempt out
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CohortDates {
+    pub fk_assessment_id: i64,
+    pub fk_cohort_id: i64,
+    pub take1: Option<String>,
+    pub retake1: Option<String>,
+    pub retake2: Option<String>,
+}
+
+// A course or assessment found by `/search`, assessments carry their course's code
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    // "course" or "assessment"
+    pub kind: String,
+    pub entity_id: i64,
+    pub course_id: i64,
+    pub name: String,
+    pub code: String,
+}
+
+pub async fn get_all_courses(pool: &SqlitePool, guild_id: i64) -> Result<Vec<Course>, sqlx::Error> {
+    let courses = sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE guild_id = ? AND deleted_at IS NULL",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(courses)
+}
+
+/// Same as `get_all_courses`, inside an undo's transaction
+pub async fn live_course_rows(
+    connection: &mut SqliteConnection,
+    guild_id: i64,
+) -> Result<Vec<Course>, sqlx::Error> {
+    let courses = sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE guild_id = ? AND deleted_at IS NULL",
+    )
+    .bind(guild_id)
+    .fetch_all(connection)
+    .await?;
+
+    Ok(courses)
+}
+
+pub async fn get_term_courses(
+    pool: &SqlitePool,
+    guild_id: i64,
+    fk_term_id: i64,
+) -> Result<Vec<Course>, sqlx::Error> {
+    let courses = sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE guild_id = ? AND fk_term_id = ? AND deleted_at IS NULL",
+    )
+    .bind(guild_id)
+    .bind(fk_term_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(courses)
 }
 
-pub async fn get_course(pool: &SqlitePool, id: i32) -> Result<Option<Course>, sqlx::Error> {
+pub async fn get_course(
+    pool: &SqlitePool,
+    guild_id: i64,
+    id: i64,
+) -> Result<Option<Course>, sqlx::Error> {
     let course: Option<Course> = sqlx::query_as!(
         Course,
         r#"
         SELECT * FROM courses
-        WHERE id = ?
+        WHERE id = ? AND guild_id = ? AND deleted_at IS NULL
         "#,
-        id
+        id,
+        guild_id
     )
     .fetch_optional(pool)
     .await?;
@@ -56,68 +196,283 @@ pub async fn get_course(pool: &SqlitePool, id: i32) -> Result<Option<Course>, sq
     Ok(course)
 }
 
-pub async fn insert_course(pool: &SqlitePool, course: &Course) -> Result<u64, sqlx::Error> {
-    let rows_affected = sqlx::query!(
+pub async fn insert_course(
+    pool: &SqlitePool,
+    course: &Course,
+    actor: &Actor,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let result = sqlx::query!(
         r#"
-        INSERT INTO courses (name, code, semester, year, credit)
-        VALUES (?, ?, ?, ?, ?)
+        INSERT INTO courses (name, code, semester, year, credit, fk_term_id, guild_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
         "#,
         course.name,
         course.code,
         course.semester,
         course.year,
-        course.credit
+        course.credit,
+        course.fk_term_id,
+        actor.guild_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let inserted = Course {
+        id: result.last_insert_rowid(),
+        guild_id: actor.guild_id,
+        ..course.clone()
+    };
+    audit::record_insert(
+        &mut transaction,
+        actor,
+        audit::COURSE,
+        inserted.id,
+        &inserted,
     )
-    .execute(pool)
     .await?;
 
-    Ok(rows_affected.rows_affected())
+    transaction.commit().await?;
+
+    Ok(result.rows_affected())
 }
 
-pub async fn delete_course(pool: &SqlitePool, id: i32) -> Result<Option<Course>, sqlx::Error> {
-    // Delete all assessments associated with the course
-    sqlx::query!(
+/// Updates everything but the id, term and guild, returns the updated course or `None` if
+/// the actor's guild has no such course
+pub async fn update_course(
+    pool: &SqlitePool,
+    course: &Course,
+    actor: &Actor,
+) -> Result<Option<Course>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let before = match sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE id = ? AND guild_id = ? AND deleted_at IS NULL",
+    )
+    .bind(course.id)
+    .bind(actor.guild_id)
+    .fetch_optional(&mut *transaction)
+    .await?
+    {
+        Some(before) => before,
+        None => return Ok(None),
+    };
+
+    let updated = Course {
+        fk_term_id: before.fk_term_id,
+        deleted_at: None,
+        guild_id: before.guild_id,
+        ..course.clone()
+    };
+    update_course_row(&mut transaction, &updated).await?;
+    audit::record_update(
+        &mut transaction,
+        actor,
+        audit::COURSE,
+        updated.id,
+        &before,
+        &updated,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(updated))
+}
+
+pub async fn update_course_row(
+    connection: &mut SqliteConnection,
+    course: &Course,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
         r#"
-        DELETE FROM assessments
-        WHERE fk_course_id = ?
+        UPDATE courses SET name = ?, code = ?, semester = ?, year = ?, credit = ?, fk_term_id = ?
+        WHERE id = ?
         "#,
-        id
     )
-    .execute(pool)
+    .bind(&course.name)
+    .bind(&course.code)
+    .bind(course.semester)
+    .bind(course.year)
+    .bind(course.credit)
+    .bind(course.fk_term_id)
+    .bind(course.id)
+    .execute(connection)
     .await?;
 
-    let course: Option<Course> = sqlx::query_as!(
-        Course,
+    Ok(())
+}
+
+pub async fn delete_course(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Course>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    if !is_guild_course(&mut transaction, id, actor.guild_id).await? {
+        return Ok(None);
+    }
+    let snapshot = remove_course_rows(&mut transaction, id).await?;
+    if let Some(snapshot) = &snapshot {
+        audit::record_delete(&mut transaction, actor, audit::COURSE, id, snapshot).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(snapshot.map(|snapshot| snapshot.course))
+}
+
+/// Whether the course, live or in the trash, belongs to the guild
+async fn is_guild_course(
+    connection: &mut SqliteConnection,
+    id: i64,
+    guild_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let count =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM courses WHERE id = ? AND guild_id = ?")
+            .bind(id)
+            .bind(guild_id)
+            .fetch_one(connection)
+            .await?;
+
+    Ok(count > 0)
+}
+
+/// Moves the course and its assessments to the trash.
+/// Sessions and editor grants stay until the course is purged, the snapshot keeps them in case it already was.
+pub async fn remove_course_rows(
+    connection: &mut SqliteConnection,
+    id: i64,
+) -> Result<Option<CourseSnapshot>, sqlx::Error> {
+    let assessments = sqlx::query_as::<_, Assessment>(
         r#"
-        DELETE FROM courses
-        WHERE id = ?
+        UPDATE assessments SET deleted_at = CURRENT_TIMESTAMP
+        WHERE fk_course_id = ? AND deleted_at IS NULL
         RETURNING *
         "#,
-        id
     )
-    .fetch_optional(pool)
+    .bind(id)
+    .fetch_all(&mut *connection)
     .await?;
 
-    Ok(course)
+    let sessions = sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE fk_course_id = ?")
+        .bind(id)
+        .fetch_all(&mut *connection)
+        .await?;
+
+    let editors =
+        sqlx::query_as::<_, CourseEditor>("SELECT * FROM course_editors WHERE fk_course_id = ?")
+            .bind(id)
+            .fetch_all(&mut *connection)
+            .await?;
+
+    let course: Option<Course> = sqlx::query_as::<_, Course>(
+        r#"
+        UPDATE courses SET deleted_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND deleted_at IS NULL
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    Ok(course.map(|course| CourseSnapshot {
+        course,
+        assessments,
+        sessions,
+        editors,
+    }))
 }
 
-pub async fn get_all_assessments(pool: &SqlitePool) -> Result<Vec<Assessment>, sqlx::Error> {
-    let assessments = sqlx::query_as::<_, Assessment>("SELECT * FROM assessments")
-        .fetch_all(pool)
-        .await?;
+/// Takes a course back out of the trash, or inserts it with its original ids if it was purged
+pub async fn restore_course_rows(
+    connection: &mut SqliteConnection,
+    snapshot: &CourseSnapshot,
+) -> Result<(), sqlx::Error> {
+    let course = &snapshot.course;
+    sqlx::query!(
+        r#"
+        INSERT INTO courses (id, name, code, semester, year, credit, fk_term_id, guild_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET deleted_at = NULL
+        "#,
+        course.id,
+        course.name,
+        course.code,
+        course.semester,
+        course.year,
+        course.credit,
+        course.fk_term_id,
+        course.guild_id
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    for assessment in &snapshot.assessments {
+        restore_assessment_row(&mut *connection, assessment).await?;
+    }
+    for session in &snapshot.sessions {
+        restore_session_row(&mut *connection, session).await?;
+    }
+    for editor in &snapshot.editors {
+        restore_course_editor_row(&mut *connection, editor).await?;
+    }
+
+    Ok(())
+}
+
+/// Live assessments of the guild's live courses
+pub async fn get_all_assessments(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<Assessment>, sqlx::Error> {
+    let assessments = sqlx::query_as::<_, Assessment>(
+        r#"
+        SELECT assessments.* FROM assessments
+        JOIN courses ON courses.id = assessments.fk_course_id
+        WHERE courses.guild_id = ? AND assessments.deleted_at IS NULL AND courses.deleted_at IS NULL
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(assessments)
 }
 
+pub async fn get_assessment(
+    pool: &SqlitePool,
+    guild_id: i64,
+    id: i64,
+) -> Result<Option<Assessment>, sqlx::Error> {
+    let assessment = sqlx::query_as::<_, Assessment>(
+        r#"
+        SELECT assessments.* FROM assessments
+        JOIN courses ON courses.id = assessments.fk_course_id
+        WHERE assessments.id = ? AND courses.guild_id = ? AND assessments.deleted_at IS NULL
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(assessment)
+}
+
 pub async fn get_course_assessments(
     pool: &SqlitePool,
     fk_course_id: i64,
 ) -> Result<Vec<Assessment>, sqlx::Error> {
-    let assessments =
-        sqlx::query_as::<_, Assessment>("SELECT * FROM assessments WHERE fk_course_id = ?")
-            .bind(fk_course_id)
-            .fetch_all(pool)
-            .await?;
+    let assessments = sqlx::query_as::<_, Assessment>(
+        "SELECT * FROM assessments WHERE fk_course_id = ? AND deleted_at IS NULL",
+    )
+    .bind(fk_course_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(assessments)
 }
@@ -125,107 +480,1759 @@ pub async fn get_course_assessments(
 pub async fn insert_assessment(
     pool: &SqlitePool,
     assessment: &Assessment,
+    actor: &Actor,
 ) -> Result<u64, sqlx::Error> {
-    let rows_affected = sqlx::query!(
+    let mut transaction = pool.begin().await?;
+
+    // Nothing is inserted unless the course is a live course of the actor's guild
+    let result = sqlx::query!(
         r#"
         INSERT INTO assessments (name, weight, take1, retake1, retake2, fk_course_id)
-        VALUES (?, ?, ?, ?, ?, ?)
+        SELECT ?, ?, ?, ?, ?, id FROM courses
+        WHERE id = ? AND guild_id = ? AND deleted_at IS NULL
         "#,
         assessment.name,
         assessment.weight,
         assessment.take1,
         assessment.retake1,
         assessment.retake2,
-        assessment.fk_course_id
+        assessment.fk_course_id,
+        actor.guild_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(0);
+    }
+
+    let inserted = Assessment {
+        id: result.last_insert_rowid(),
+        ..assessment.clone()
+    };
+    audit::record_insert(
+        &mut transaction,
+        actor,
+        audit::ASSESSMENT,
+        inserted.id,
+        &inserted,
     )
-    .execute(pool)
     .await?;
 
-    Ok(rows_affected.rows_affected())
+    transaction.commit().await?;
+
+    Ok(result.rows_affected())
 }
 
 pub async fn delete_assessment(
     pool: &SqlitePool,
-    id: i32,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Assessment>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    if !is_guild_assessment(&mut transaction, id, actor.guild_id).await? {
+        return Ok(None);
+    }
+    let assessment = remove_assessment_row(&mut transaction, id).await?;
+    if let Some(assessment) = &assessment {
+        audit::record_delete(
+            &mut transaction,
+            actor,
+            audit::ASSESSMENT,
+            assessment.id,
+            assessment,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(assessment)
+}
+
+/// Whether the assessment, live or in the trash, belongs to a course of the guild
+async fn is_guild_assessment(
+    connection: &mut SqliteConnection,
+    id: i64,
+    guild_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let count = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM assessments
+        JOIN courses ON courses.id = assessments.fk_course_id
+        WHERE assessments.id = ? AND courses.guild_id = ?
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_one(connection)
+    .await?;
+
+    Ok(count > 0)
+}
+
+pub async fn remove_assessment_row(
+    connection: &mut SqliteConnection,
+    id: i64,
 ) -> Result<Option<Assessment>, sqlx::Error> {
-    let assessment: Option<Assessment> = sqlx::query_as!(
-        Assessment,
+    let assessment: Option<Assessment> = sqlx::query_as::<_, Assessment>(
+        r#"
+        UPDATE assessments SET deleted_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND deleted_at IS NULL
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(assessment)
+}
+
+pub async fn restore_assessment_row(
+    connection: &mut SqliteConnection,
+    assessment: &Assessment,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO assessments (id, name, weight, take1, retake1, retake2, fk_course_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET deleted_at = NULL
+        "#,
+        assessment.id,
+        assessment.name,
+        assessment.weight,
+        assessment.take1,
+        assessment.retake1,
+        assessment.retake2,
+        assessment.fk_course_id
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_all_sessions(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<Session>, sqlx::Error> {
+    // Sessions of courses in the trash are hidden along with the course
+    let sessions = sqlx::query_as::<_, Session>(
+        r#"
+        SELECT sessions.* FROM sessions
+        JOIN courses ON courses.id = sessions.fk_course_id
+        WHERE courses.guild_id = ? AND courses.deleted_at IS NULL
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+pub async fn get_course_sessions(
+    pool: &SqlitePool,
+    fk_course_id: i64,
+) -> Result<Vec<Session>, sqlx::Error> {
+    let sessions = sqlx::query_as::<_, Session>(
+        "SELECT * FROM sessions WHERE fk_course_id = ? ORDER BY weekday, start_time",
+    )
+    .bind(fk_course_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+pub async fn insert_session(
+    pool: &SqlitePool,
+    session: &Session,
+    actor: &Actor,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    // Nothing is inserted unless the course is live and belongs to the actor's guild
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO sessions (weekday, start_time, end_time, location, fk_course_id)
+        SELECT ?, ?, ?, ?, id FROM courses
+        WHERE id = ? AND guild_id = ? AND deleted_at IS NULL
+        "#,
+        session.weekday,
+        session.start_time,
+        session.end_time,
+        session.location,
+        session.fk_course_id,
+        actor.guild_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(0);
+    }
+
+    let inserted = Session {
+        id: result.last_insert_rowid(),
+        ..session.clone()
+    };
+    audit::record_insert(
+        &mut transaction,
+        actor,
+        audit::SESSION,
+        inserted.id,
+        &inserted,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_session(
+    pool: &SqlitePool,
+    guild_id: i64,
+    id: i64,
+) -> Result<Option<Session>, sqlx::Error> {
+    let session = sqlx::query_as::<_, Session>(
+        r#"
+        SELECT sessions.* FROM sessions
+        JOIN courses ON courses.id = sessions.fk_course_id
+        WHERE sessions.id = ? AND courses.guild_id = ?
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session)
+}
+
+pub async fn delete_session(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Session>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let session = sqlx::query_as::<_, Session>(
+        r#"
+        SELECT sessions.* FROM sessions
+        JOIN courses ON courses.id = sessions.fk_course_id
+        WHERE sessions.id = ? AND courses.guild_id = ?
+        "#,
+    )
+    .bind(id)
+    .bind(actor.guild_id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+    if session.is_none() {
+        return Ok(None);
+    }
+    let session = remove_session_row(&mut transaction, id).await?;
+    if let Some(session) = &session {
+        audit::record_delete(&mut transaction, actor, audit::SESSION, session.id, session).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(session)
+}
+
+pub async fn remove_session_row(
+    connection: &mut SqliteConnection,
+    id: i64,
+) -> Result<Option<Session>, sqlx::Error> {
+    let session: Option<Session> = sqlx::query_as!(
+        Session,
         r#"
-        DELETE FROM assessments
+        DELETE FROM sessions
         WHERE id = ?
         RETURNING *
         "#,
         id
     )
-    .fetch_optional(pool)
+    .fetch_optional(connection)
     .await?;
 
-    Ok(assessment)
+    Ok(session)
+}
+
+pub async fn restore_session_row(
+    connection: &mut SqliteConnection,
+    session: &Session,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO sessions (id, weekday, start_time, end_time, location, fk_course_id)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+        session.id,
+        session.weekday,
+        session.start_time,
+        session.end_time,
+        session.location,
+        session.fk_course_id
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// `new_id` is `None` when the target semester/year already has a course with the code
+pub struct ClonedCourse {
+    pub old_id: i64,
+    pub new_id: Option<i64>,
+    pub code: String,
+    pub assessments: usize,
+    pub sessions: usize,
 }
 
-/* UNUSED FUNCTIONS */
-
-// async fn create_table_courses(pool: &SqlitePool) -> Result<()> {
-//     sqlx::query(
-//         r#"
-//         CREATE TABLE IF NOT EXISTS courses (
-//             id INTEGER PRIMARY KEY AUTOINCREMENT,
-//             name TEXT NOT NULL,
-//             code TEXT NOT NULL,
-//             semester INTEGER NOT NULL,
-//             year INTEGER NOT NULL,
-//             credit REAL NOT NULL
-//         )
-//         "#,
-//     )
-//     .execute(pool)
-//     .await?;
-
-//     Ok(())
-// }
-
-// async fn create_table_assessment(pool: &SqlitePool) -> Result<()> {
-//     sqlx::query(
-//         r#"
-//         CREATE TABLE IF NOT EXISTS assessments (
-//             id INTEGER PRIMARY KEY AUTOINCREMENT,
-//             name TEXT NOT NULL,
-//             weight REAL NOT NULL,
-//             take1 TEXT NOT NULL,
-//             retake1 TEXT NOT NULL,
-//             retake2 TEXT NOT NULL,
-//             fk_course_id INTEGER NOT NULL,
-//             FOREIGN KEY (fk_course_id) REFERENCES courses(id)
-//         )
-//         "#,
-//     )
-//     .execute(pool)
-//     .await?;
-
-//     Ok(())
-// }
-
-// async fn insert_courses(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-//     let course1 = Course {
-//         id: 1,
-//         name: "Course 1".to_string(),
-//         code: "CSE101".to_string(),
-//         semester: 1,
-//         year: 2023,
-//         credit: 3.0,
-//     };
-
-//     let course2 = Course {
-//         id: 2,
-//         name: "Course 2".to_string(),
-//         code: "CSE102".to_string(),
-//         semester: 1,
-//         year: 2023,
-//         credit: 3.0,
-//     };
-
-//     insert_course(pool, &course1).await?;
-//     insert_course(pool, &course2).await?;
-
-//     Ok(())
-// }
+/// Copies all courses of the actor's guild in a semester/year together with their assessments
+/// and sessions, skipping those whose code is already taken in the target semester/year.
+/// Assessment dates are moved by `shift_days` or cleared when it is `None`.
+pub async fn clone_courses(
+    pool: &SqlitePool,
+    from: (i64, i64),
+    to: (i64, i64),
+    fk_term_id: Option<i64>,
+    shift_days: Option<i64>,
+    actor: &Actor,
+) -> Result<Vec<ClonedCourse>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let courses = sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE guild_id = ? AND semester = ? AND year = ? AND deleted_at IS NULL",
+    )
+    .bind(actor.guild_id)
+    .bind(from.0)
+    .bind(from.1)
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    let shift = |date: &str| match shift_days {
+        Some(days) => utils::shift_date(date, days),
+        None => String::new(),
+    };
+
+    let mut cloned = Vec::new();
+    for course in courses {
+        let exists = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM courses
+                WHERE guild_id = ? AND code = ? COLLATE NOCASE AND semester = ? AND year = ?
+                    AND deleted_at IS NULL
+            )
+            "#,
+        )
+        .bind(actor.guild_id)
+        .bind(&course.code)
+        .bind(to.0)
+        .bind(to.1)
+        .fetch_one(&mut *transaction)
+        .await?;
+        if exists {
+            cloned.push(ClonedCourse {
+                old_id: course.id,
+                new_id: None,
+                code: course.code,
+                assessments: 0,
+                sessions: 0,
+            });
+            continue;
+        }
+
+        let new_course = Course {
+            semester: to.0,
+            year: to.1,
+            fk_term_id,
+            ..course.clone()
+        };
+
+        let new_id = sqlx::query!(
+            r#"
+            INSERT INTO courses (name, code, semester, year, credit, fk_term_id, guild_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            new_course.name,
+            new_course.code,
+            new_course.semester,
+            new_course.year,
+            new_course.credit,
+            new_course.fk_term_id,
+            new_course.guild_id
+        )
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+
+        let new_course = Course {
+            id: new_id,
+            ..new_course
+        };
+        audit::record_insert(&mut transaction, actor, audit::COURSE, new_id, &new_course).await?;
+
+        let assessments = sqlx::query_as::<_, Assessment>(
+            "SELECT * FROM assessments WHERE fk_course_id = ? AND deleted_at IS NULL",
+        )
+        .bind(course.id)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        for assessment in &assessments {
+            let new_assessment = Assessment {
+                take1: shift(&assessment.take1),
+                retake1: shift(&assessment.retake1),
+                retake2: shift(&assessment.retake2),
+                fk_course_id: new_id,
+                ..assessment.clone()
+            };
+
+            let new_assessment_id = sqlx::query!(
+                r#"
+                INSERT INTO assessments (name, weight, take1, retake1, retake2, fk_course_id)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+                new_assessment.name,
+                new_assessment.weight,
+                new_assessment.take1,
+                new_assessment.retake1,
+                new_assessment.retake2,
+                new_assessment.fk_course_id
+            )
+            .execute(&mut *transaction)
+            .await?
+            .last_insert_rowid();
+
+            let new_assessment = Assessment {
+                id: new_assessment_id,
+                ..new_assessment
+            };
+            audit::record_insert(
+                &mut transaction,
+                actor,
+                audit::ASSESSMENT,
+                new_assessment_id,
+                &new_assessment,
+            )
+            .await?;
+        }
+
+        let sessions =
+            sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE fk_course_id = ?")
+                .bind(course.id)
+                .fetch_all(&mut *transaction)
+                .await?;
+
+        for session in &sessions {
+            let new_session = Session {
+                fk_course_id: new_id,
+                ..session.clone()
+            };
+
+            let new_session_id = sqlx::query!(
+                r#"
+                INSERT INTO sessions (weekday, start_time, end_time, location, fk_course_id)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                new_session.weekday,
+                new_session.start_time,
+                new_session.end_time,
+                new_session.location,
+                new_session.fk_course_id
+            )
+            .execute(&mut *transaction)
+            .await?
+            .last_insert_rowid();
+
+            let new_session = Session {
+                id: new_session_id,
+                ..new_session
+            };
+            audit::record_insert(
+                &mut transaction,
+                actor,
+                audit::SESSION,
+                new_session_id,
+                &new_session,
+            )
+            .await?;
+        }
+
+        cloned.push(ClonedCourse {
+            old_id: course.id,
+            new_id: Some(new_id),
+            code: course.code,
+            assessments: assessments.len(),
+            sessions: sessions.len(),
+        });
+    }
+
+    transaction.commit().await?;
+
+    Ok(cloned)
+}
+
+pub async fn get_deleted_courses(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<Course>, sqlx::Error> {
+    let courses = sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE guild_id = ? AND deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(courses)
+}
+
+/// Assessments deleted on their own, those deleted with their course are restored with it
+pub async fn get_deleted_assessments(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<Assessment>, sqlx::Error> {
+    let assessments = sqlx::query_as::<_, Assessment>(
+        r#"
+        SELECT assessments.* FROM assessments
+        JOIN courses ON courses.id = assessments.fk_course_id
+        WHERE courses.guild_id = ? AND assessments.deleted_at IS NOT NULL AND courses.deleted_at IS NULL
+        ORDER BY assessments.deleted_at DESC
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(assessments)
+}
+
+/// Restores the course together with the assessments that were deleted along with it
+pub async fn restore_deleted_course(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Course>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let course = sqlx::query_as::<_, Course>(
+        "SELECT * FROM courses WHERE id = ? AND guild_id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(id)
+    .bind(actor.guild_id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let course = match course {
+        Some(course) => course,
+        None => return Ok(None),
+    };
+
+    sqlx::query!(
+        "UPDATE assessments SET deleted_at = NULL WHERE fk_course_id = ? AND deleted_at = ?",
+        course.id,
+        course.deleted_at
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let restored = sqlx::query_as::<_, Course>(
+        "UPDATE courses SET deleted_at = NULL WHERE id = ? RETURNING *",
+    )
+    .bind(id)
+    .fetch_one(&mut *transaction)
+    .await?;
+    audit::record_insert(&mut transaction, actor, audit::COURSE, id, &restored).await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(restored))
+}
+
+pub async fn restore_deleted_assessment(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Assessment>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    if !is_guild_assessment(&mut transaction, id, actor.guild_id).await? {
+        return Ok(None);
+    }
+    let assessment = sqlx::query_as::<_, Assessment>(
+        r#"
+        UPDATE assessments SET deleted_at = NULL
+        WHERE id = ? AND deleted_at IS NOT NULL
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    if let Some(assessment) = &assessment {
+        audit::record_insert(&mut transaction, actor, audit::ASSESSMENT, id, assessment).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(assessment)
+}
+
+/// Permanently deletes everything that has been in the trash longer than the retention period
+pub async fn purge_trash(pool: &SqlitePool, retention_days: i64) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let cutoff = format!("-{} days", retention_days);
+
+    let purged_courses = "SELECT id FROM courses WHERE deleted_at < datetime('now', ?)";
+    // Cohort dates of assessments purged on their own or along with their course
+    sqlx::query(&format!(
+        r#"
+        DELETE FROM cohort_dates WHERE fk_assessment_id IN (
+            SELECT id FROM assessments
+            WHERE deleted_at < datetime('now', ?) OR fk_course_id IN ({})
+        )
+        "#,
+        purged_courses
+    ))
+    .bind(&cutoff)
+    .bind(&cutoff)
+    .execute(&mut *transaction)
+    .await?;
+    for table in [
+        "assessments",
+        "sessions",
+        "course_editors",
+        "course_subscriptions",
+        "course_roles",
+    ] {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE fk_course_id IN ({})",
+            table, purged_courses
+        ))
+        .bind(&cutoff)
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    let courses = sqlx::query("DELETE FROM courses WHERE deleted_at < datetime('now', ?)")
+        .bind(&cutoff)
+        .execute(&mut *transaction)
+        .await?;
+
+    let assessments = sqlx::query("DELETE FROM assessments WHERE deleted_at < datetime('now', ?)")
+        .bind(&cutoff)
+        .execute(&mut *transaction)
+        .await?;
+
+    // Reminders are only looked up for upcoming dates
+    sqlx::query("DELETE FROM sent_reminders WHERE date < date('now', ?)")
+        .bind(&cutoff)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(courses.rows_affected() + assessments.rows_affected())
+}
+
+pub async fn get_all_terms(pool: &SqlitePool, guild_id: i64) -> Result<Vec<Term>, sqlx::Error> {
+    let terms =
+        sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE guild_id = ? ORDER BY start_date")
+            .bind(guild_id)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(terms)
+}
+
+pub async fn get_term(
+    pool: &SqlitePool,
+    guild_id: i64,
+    id: i64,
+) -> Result<Option<Term>, sqlx::Error> {
+    let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE id = ? AND guild_id = ?")
+        .bind(id)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(term)
+}
+
+pub async fn get_active_term(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Option<Term>, sqlx::Error> {
+    let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE guild_id = ? AND active = 1")
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(term)
+}
+
+/// Inserting an active term deactivates the previous one of the actor's guild
+pub async fn insert_term(
+    pool: &SqlitePool,
+    term: &Term,
+    actor: &Actor,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    if term.active {
+        deactivate_terms(&mut transaction, actor).await?;
+    }
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO terms (name, start_date, end_date, active, archived, guild_id)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        term.name,
+        term.start_date,
+        term.end_date,
+        term.active,
+        term.archived,
+        actor.guild_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let inserted = Term {
+        id: result.last_insert_rowid(),
+        guild_id: actor.guild_id,
+        ..term.clone()
+    };
+    audit::record_insert(&mut transaction, actor, audit::TERM, inserted.id, &inserted).await?;
+
+    transaction.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn deactivate_terms(
+    connection: &mut SqliteConnection,
+    actor: &Actor,
+) -> Result<(), sqlx::Error> {
+    let active = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE guild_id = ? AND active = 1")
+        .bind(actor.guild_id)
+        .fetch_all(&mut *connection)
+        .await?;
+
+    for term in active {
+        let updated = Term {
+            active: false,
+            ..term.clone()
+        };
+        update_term_row(&mut *connection, &updated).await?;
+        audit::record_update(
+            &mut *connection,
+            actor,
+            audit::TERM,
+            term.id,
+            &term,
+            &updated,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Makes the term the only active one, unarchiving it if needed
+pub async fn activate_term(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Term>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE id = ? AND guild_id = ?")
+        .bind(id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+    let updated = match term {
+        Some(term) => {
+            deactivate_terms(&mut transaction, actor).await?;
+
+            let updated = Term {
+                active: true,
+                archived: false,
+                ..term.clone()
+            };
+            update_term_row(&mut transaction, &updated).await?;
+            audit::record_update(&mut transaction, actor, audit::TERM, id, &term, &updated).await?;
+            Some(updated)
+        }
+        None => None,
+    };
+
+    transaction.commit().await?;
+
+    Ok(updated)
+}
+
+pub async fn archive_term(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<Term>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE id = ? AND guild_id = ?")
+        .bind(id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+    let updated = match term {
+        Some(term) => {
+            let updated = Term {
+                active: false,
+                archived: true,
+                ..term.clone()
+            };
+            update_term_row(&mut transaction, &updated).await?;
+            audit::record_update(&mut transaction, actor, audit::TERM, id, &term, &updated).await?;
+            Some(updated)
+        }
+        None => None,
+    };
+
+    transaction.commit().await?;
+
+    Ok(updated)
+}
+
+pub async fn update_term_row(
+    connection: &mut SqliteConnection,
+    term: &Term,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE terms SET name = ?, start_date = ?, end_date = ?, active = ?, archived = ?
+        WHERE id = ?
+        "#,
+        term.name,
+        term.start_date,
+        term.end_date,
+        term.active,
+        term.archived,
+        term.id
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn restore_term_row(
+    connection: &mut SqliteConnection,
+    term: &Term,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO terms (id, name, start_date, end_date, active, archived, guild_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        term.id,
+        term.name,
+        term.start_date,
+        term.end_date,
+        term.active,
+        term.archived,
+        term.guild_id
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the term, its courses are left without a term
+pub async fn remove_term_row(
+    connection: &mut SqliteConnection,
+    id: i64,
+) -> Result<Option<Term>, sqlx::Error> {
+    sqlx::query!(
+        "UPDATE courses SET fk_term_id = NULL WHERE fk_term_id = ?",
+        id
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    let term = sqlx::query_as::<_, Term>("DELETE FROM terms WHERE id = ? RETURNING *")
+        .bind(id)
+        .fetch_optional(connection)
+        .await?;
+
+    Ok(term)
+}
+
+pub async fn get_course_editors(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<CourseEditor>, sqlx::Error> {
+    let editors = sqlx::query_as::<_, CourseEditor>(
+        "SELECT * FROM course_editors WHERE guild_id = ? ORDER BY fk_course_id",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(editors)
+}
+
+pub async fn insert_course_editor(
+    pool: &SqlitePool,
+    editor: &CourseEditor,
+    actor: &Actor,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO course_editors (guild_id, role_id, user_id, fk_course_id)
+        VALUES (?, ?, ?, ?)
+        "#,
+        editor.guild_id,
+        editor.role_id,
+        editor.user_id,
+        editor.fk_course_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let inserted = CourseEditor {
+        id: result.last_insert_rowid(),
+        ..editor.clone()
+    };
+    audit::record_insert(
+        &mut transaction,
+        actor,
+        audit::COURSE_EDITOR,
+        inserted.id,
+        &inserted,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn delete_course_editor(
+    pool: &SqlitePool,
+    id: i64,
+    actor: &Actor,
+) -> Result<Option<CourseEditor>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let editor = sqlx::query_as::<_, CourseEditor>(
+        "SELECT * FROM course_editors WHERE guild_id = ? AND id = ?",
+    )
+    .bind(actor.guild_id)
+    .bind(id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let editor = match editor {
+        Some(editor) => remove_course_editor_row(&mut transaction, editor.id).await?,
+        None => None,
+    };
+    if let Some(editor) = &editor {
+        audit::record_delete(
+            &mut transaction,
+            actor,
+            audit::COURSE_EDITOR,
+            editor.id,
+            editor,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(editor)
+}
+
+pub async fn remove_course_editor_row(
+    connection: &mut SqliteConnection,
+    id: i64,
+) -> Result<Option<CourseEditor>, sqlx::Error> {
+    let editor =
+        sqlx::query_as::<_, CourseEditor>("DELETE FROM course_editors WHERE id = ? RETURNING *")
+            .bind(id)
+            .fetch_optional(connection)
+            .await?;
+
+    Ok(editor)
+}
+
+pub async fn restore_course_editor_row(
+    connection: &mut SqliteConnection,
+    editor: &CourseEditor,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO course_editors (id, guild_id, role_id, user_id, fk_course_id)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+        editor.id,
+        editor.guild_id,
+        editor.role_id,
+        editor.user_id,
+        editor.fk_course_id
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// The user's own theme, otherwise the guild's
+pub async fn get_theme_preference(
+    pool: &SqlitePool,
+    guild_id: i64,
+    user_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let theme = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT theme FROM theme_preferences
+        WHERE (guild_id = 0 AND user_id = ?) OR (guild_id = ? AND user_id = 0)
+        ORDER BY user_id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(guild_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(theme)
+}
+
+/// `None` clears the preference
+pub async fn set_theme_preference(
+    pool: &SqlitePool,
+    guild_id: i64,
+    user_id: i64,
+    theme: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    match theme {
+        Some(theme) => {
+            sqlx::query(
+                r#"
+                INSERT INTO theme_preferences (guild_id, user_id, theme)
+                VALUES (?, ?, ?)
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET theme = excluded.theme
+                "#,
+            )
+            .bind(guild_id)
+            .bind(user_id)
+            .bind(theme)
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM theme_preferences WHERE guild_id = ? AND user_id = ?")
+                .bind(guild_id)
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The words of a search query, punctuation only separates them
+pub fn search_words(query: &str) -> Vec<&str> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Best matches first, code matches weigh more than name matches.
+/// The index is maintained by triggers on `courses` and `assessments`, see `migrations/sqlite/0008_search_index.sql`
+pub async fn search(
+    pool: &SqlitePool,
+    guild_id: i64,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let words = search_words(query);
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+    // Every word as a prefix, quoted so FTS5 operators are matched literally
+    let terms: Vec<String> = words.iter().map(|word| format!("\"{}\"*", word)).collect();
+
+    let hits = sqlx::query_as::<_, SearchHit>(
+        r#"
+        SELECT kind, entity_id, course_id, search_index.name, search_index.code
+        FROM search_index
+        JOIN courses ON courses.id = search_index.course_id
+        WHERE search_index MATCH ? AND courses.guild_id = ?
+        ORDER BY bm25(search_index, 0.0, 0.0, 0.0, 1.0, 5.0), kind DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(terms.join(" "))
+    .bind(guild_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(hits)
+}
+
+pub async fn is_subscribed(
+    pool: &SqlitePool,
+    user_id: i64,
+    fk_course_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let subscribed = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM course_subscriptions WHERE user_id = ? AND fk_course_id = ?",
+    )
+    .bind(user_id)
+    .bind(fk_course_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(subscribed > 0)
+}
+
+pub async fn get_subscribed_courses(
+    pool: &SqlitePool,
+    guild_id: i64,
+    user_id: i64,
+) -> Result<Vec<Course>, sqlx::Error> {
+    let courses = sqlx::query_as::<_, Course>(
+        r#"
+        SELECT courses.* FROM courses
+        JOIN course_subscriptions ON course_subscriptions.fk_course_id = courses.id
+        WHERE course_subscriptions.user_id = ? AND courses.guild_id = ? AND courses.deleted_at IS NULL
+        ORDER BY courses.id
+        "#,
+    )
+    .bind(user_id)
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(courses)
+}
+
+/// Enrolls or unenrolls the actor, only actual changes are recorded
+pub async fn set_subscribed(
+    pool: &SqlitePool,
+    fk_course_id: i64,
+    subscribed: bool,
+    actor: &Actor,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let subscription = Subscription {
+        user_id: actor.user_id,
+        fk_course_id,
+    };
+    match subscribed {
+        true => {
+            if restore_subscription_row(&mut transaction, &subscription).await? {
+                audit::record_insert(
+                    &mut transaction,
+                    actor,
+                    audit::SUBSCRIPTION,
+                    fk_course_id,
+                    &subscription,
+                )
+                .await?;
+            }
+        }
+        false => {
+            if remove_subscription_row(&mut transaction, actor.user_id, fk_course_id)
+                .await?
+                .is_some()
+            {
+                audit::record_delete(
+                    &mut transaction,
+                    actor,
+                    audit::SUBSCRIPTION,
+                    fk_course_id,
+                    &subscription,
+                )
+                .await?;
+            }
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub async fn remove_subscription_row(
+    connection: &mut SqliteConnection,
+    user_id: i64,
+    fk_course_id: i64,
+) -> Result<Option<Subscription>, sqlx::Error> {
+    let subscription = sqlx::query_as::<_, Subscription>(
+        "DELETE FROM course_subscriptions WHERE user_id = ? AND fk_course_id = ? RETURNING *",
+    )
+    .bind(user_id)
+    .bind(fk_course_id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(subscription)
+}
+
+/// Returns whether the user was not enrolled yet, purged courses are skipped
+pub async fn restore_subscription_row(
+    connection: &mut SqliteConnection,
+    subscription: &Subscription,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO course_subscriptions (user_id, fk_course_id)
+        SELECT ?, ? WHERE EXISTS (SELECT 1 FROM courses WHERE id = ?)
+        ON CONFLICT (user_id, fk_course_id) DO NOTHING
+        "#,
+    )
+    .bind(subscription.user_id)
+    .bind(subscription.fk_course_id)
+    .bind(subscription.fk_course_id)
+    .execute(connection)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_course_roles(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<CourseRole>, sqlx::Error> {
+    let roles = sqlx::query_as::<_, CourseRole>(
+        "SELECT * FROM course_roles WHERE guild_id = ? ORDER BY fk_course_id",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(roles)
+}
+
+/// Links of every guild that have a reminder channel
+pub async fn get_reminder_channels(pool: &SqlitePool) -> Result<Vec<CourseRole>, sqlx::Error> {
+    let roles = sqlx::query_as::<_, CourseRole>(
+        "SELECT * FROM course_roles WHERE channel_id IS NOT NULL ORDER BY guild_id, fk_course_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(roles)
+}
+
+/// Replaces the course's previous link in the actor's guild
+pub async fn set_course_role(
+    pool: &SqlitePool,
+    role: &CourseRole,
+    actor: &Actor,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let before = sqlx::query_as::<_, CourseRole>(
+        "SELECT * FROM course_roles WHERE guild_id = ? AND fk_course_id = ?",
+    )
+    .bind(actor.guild_id)
+    .bind(role.fk_course_id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let role = CourseRole {
+        guild_id: actor.guild_id,
+        ..role.clone()
+    };
+    restore_course_role_row(&mut transaction, &role).await?;
+    match before {
+        Some(before) => {
+            audit::record_update(
+                &mut transaction,
+                actor,
+                audit::COURSE_ROLE,
+                role.fk_course_id,
+                &before,
+                &role,
+            )
+            .await?
+        }
+        None => {
+            audit::record_insert(
+                &mut transaction,
+                actor,
+                audit::COURSE_ROLE,
+                role.fk_course_id,
+                &role,
+            )
+            .await?
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_course_role(
+    pool: &SqlitePool,
+    fk_course_id: i64,
+    actor: &Actor,
+) -> Result<Option<CourseRole>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let role = remove_course_role_row(&mut transaction, actor.guild_id, fk_course_id).await?;
+    if let Some(role) = &role {
+        audit::record_delete(
+            &mut transaction,
+            actor,
+            audit::COURSE_ROLE,
+            role.fk_course_id,
+            role,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(role)
+}
+
+pub async fn remove_course_role_row(
+    connection: &mut SqliteConnection,
+    guild_id: i64,
+    fk_course_id: i64,
+) -> Result<Option<CourseRole>, sqlx::Error> {
+    let role = sqlx::query_as::<_, CourseRole>(
+        "DELETE FROM course_roles WHERE guild_id = ? AND fk_course_id = ? RETURNING *",
+    )
+    .bind(guild_id)
+    .bind(fk_course_id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(role)
+}
+
+pub async fn restore_course_role_row(
+    connection: &mut SqliteConnection,
+    role: &CourseRole,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO course_roles (guild_id, fk_course_id, role_id, channel_id)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (guild_id, fk_course_id)
+        DO UPDATE SET role_id = excluded.role_id, channel_id = excluded.channel_id
+        "#,
+    )
+    .bind(role.guild_id)
+    .bind(role.fk_course_id)
+    .bind(role.role_id)
+    .bind(role.channel_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// (user id, course id) of everyone enrolled in a live course linked to a role of the guild
+pub async fn get_linked_subscriptions(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let subscriptions = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT course_subscriptions.user_id, course_subscriptions.fk_course_id
+        FROM course_subscriptions
+        JOIN course_roles ON course_roles.fk_course_id = course_subscriptions.fk_course_id
+        JOIN courses ON courses.id = course_subscriptions.fk_course_id
+        WHERE course_roles.guild_id = ? AND courses.deleted_at IS NULL
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subscriptions)
+}
+
+pub async fn is_reminder_sent(
+    pool: &SqlitePool,
+    guild_id: i64,
+    fk_assessment_id: i64,
+    date: &str,
+) -> Result<bool, sqlx::Error> {
+    let sent = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sent_reminders WHERE guild_id = ? AND fk_assessment_id = ? AND date = ?",
+    )
+    .bind(guild_id)
+    .bind(fk_assessment_id)
+    .bind(date)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(sent > 0)
+}
+
+pub async fn record_reminder(
+    pool: &SqlitePool,
+    guild_id: i64,
+    fk_assessment_id: i64,
+    date: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO sent_reminders (guild_id, fk_assessment_id, date) VALUES (?, ?, ?)",
+    )
+    .bind(guild_id)
+    .bind(fk_assessment_id)
+    .bind(date)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_cohorts(pool: &SqlitePool, guild_id: i64) -> Result<Vec<Cohort>, sqlx::Error> {
+    let cohorts =
+        sqlx::query_as::<_, Cohort>("SELECT * FROM cohorts WHERE guild_id = ? ORDER BY name")
+            .bind(guild_id)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(cohorts)
+}
+
+/// Names are matched ignoring case
+pub async fn find_cohort(
+    pool: &SqlitePool,
+    guild_id: i64,
+    name: &str,
+) -> Result<Option<Cohort>, sqlx::Error> {
+    let cohort = sqlx::query_as::<_, Cohort>(
+        "SELECT * FROM cohorts WHERE guild_id = ? AND name = ? COLLATE NOCASE",
+    )
+    .bind(guild_id)
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(cohort)
+}
+
+pub async fn insert_cohort(
+    pool: &SqlitePool,
+    name: &str,
+    actor: &Actor,
+) -> Result<Cohort, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let cohort = sqlx::query_as::<_, Cohort>(
+        "INSERT INTO cohorts (guild_id, name) VALUES (?, ?) RETURNING *",
+    )
+    .bind(actor.guild_id)
+    .bind(name)
+    .fetch_one(&mut *transaction)
+    .await?;
+    audit::record_insert(&mut transaction, actor, audit::COHORT, cohort.id, &cohort).await?;
+
+    transaction.commit().await?;
+
+    Ok(cohort)
+}
+
+/// Also removes the cohort's members and dates, only cohorts of the actor's guild are deleted
+pub async fn delete_cohort(pool: &SqlitePool, id: i64, actor: &Actor) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM cohorts WHERE id = ? AND guild_id = ?)",
+    )
+    .bind(id)
+    .bind(actor.guild_id)
+    .fetch_one(&mut *transaction)
+    .await?;
+    if !exists {
+        return Ok(());
+    }
+
+    if let Some(snapshot) = remove_cohort_rows(&mut transaction, id).await? {
+        audit::record_delete(&mut transaction, actor, audit::COHORT, id, &snapshot).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub async fn remove_cohort_rows(
+    connection: &mut SqliteConnection,
+    id: i64,
+) -> Result<Option<CohortSnapshot>, sqlx::Error> {
+    let dates = sqlx::query_as::<_, CohortDates>(
+        "DELETE FROM cohort_dates WHERE fk_cohort_id = ? RETURNING *",
+    )
+    .bind(id)
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let members = sqlx::query_as::<_, CohortMember>(
+        "DELETE FROM cohort_members WHERE fk_cohort_id = ? RETURNING *",
+    )
+    .bind(id)
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let cohort = sqlx::query_as::<_, Cohort>("DELETE FROM cohorts WHERE id = ? RETURNING *")
+        .bind(id)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    Ok(cohort.map(|cohort| CohortSnapshot {
+        cohort,
+        members,
+        dates,
+    }))
+}
+
+/// Inserts the cohort with its original id, members who joined another cohort since stay there
+pub async fn restore_cohort_rows(
+    connection: &mut SqliteConnection,
+    snapshot: &CohortSnapshot,
+) -> Result<(), sqlx::Error> {
+    let cohort = &snapshot.cohort;
+    sqlx::query(
+        "INSERT INTO cohorts (id, guild_id, name) VALUES (?, ?, ?) ON CONFLICT (id) DO NOTHING",
+    )
+    .bind(cohort.id)
+    .bind(cohort.guild_id)
+    .bind(&cohort.name)
+    .execute(&mut *connection)
+    .await?;
+
+    for member in &snapshot.members {
+        sqlx::query(
+            r#"
+            INSERT INTO cohort_members (guild_id, user_id, fk_cohort_id) VALUES (?, ?, ?)
+            ON CONFLICT (guild_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(member.guild_id)
+        .bind(member.user_id)
+        .bind(member.fk_cohort_id)
+        .execute(&mut *connection)
+        .await?;
+    }
+    for dates in &snapshot.dates {
+        restore_cohort_dates_row(&mut *connection, dates).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn get_member_cohort(
+    pool: &SqlitePool,
+    guild_id: i64,
+    user_id: i64,
+) -> Result<Option<Cohort>, sqlx::Error> {
+    let cohort = sqlx::query_as::<_, Cohort>(
+        r#"
+        SELECT cohorts.* FROM cohorts
+        JOIN cohort_members ON cohort_members.fk_cohort_id = cohorts.id
+        WHERE cohort_members.guild_id = ? AND cohort_members.user_id = ?
+        "#,
+    )
+    .bind(guild_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(cohort)
+}
+
+/// `None` leaves the member's cohort
+pub async fn set_member_cohort(
+    pool: &SqlitePool,
+    guild_id: i64,
+    user_id: i64,
+    fk_cohort_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    match fk_cohort_id {
+        Some(fk_cohort_id) => {
+            sqlx::query(
+                r#"
+                INSERT INTO cohort_members (guild_id, user_id, fk_cohort_id) VALUES (?, ?, ?)
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET fk_cohort_id = excluded.fk_cohort_id
+                "#,
+            )
+            .bind(guild_id)
+            .bind(user_id)
+            .bind(fk_cohort_id)
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM cohort_members WHERE guild_id = ? AND user_id = ?")
+                .bind(guild_id)
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Assessment assignments of the guild's cohorts
+pub async fn get_cohort_dates(
+    pool: &SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<CohortDates>, sqlx::Error> {
+    let dates = sqlx::query_as::<_, CohortDates>(
+        r#"
+        SELECT cohort_dates.* FROM cohort_dates
+        JOIN cohorts ON cohorts.id = cohort_dates.fk_cohort_id
+        WHERE cohorts.guild_id = ?
+        ORDER BY cohort_dates.fk_assessment_id, cohorts.name
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(dates)
+}
+
+/// Replaces the previous dates of the assessment for the cohort
+pub async fn set_cohort_dates(
+    pool: &SqlitePool,
+    dates: &CohortDates,
+    actor: &Actor,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let before = sqlx::query_as::<_, CohortDates>(
+        "SELECT * FROM cohort_dates WHERE fk_assessment_id = ? AND fk_cohort_id = ?",
+    )
+    .bind(dates.fk_assessment_id)
+    .bind(dates.fk_cohort_id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    restore_cohort_dates_row(&mut transaction, dates).await?;
+    match before {
+        Some(before) => {
+            audit::record_update(
+                &mut transaction,
+                actor,
+                audit::COHORT_DATES,
+                dates.fk_assessment_id,
+                &before,
+                dates,
+            )
+            .await?
+        }
+        None => {
+            audit::record_insert(
+                &mut transaction,
+                actor,
+                audit::COHORT_DATES,
+                dates.fk_assessment_id,
+                dates,
+            )
+            .await?
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Returns whether the assessment was assigned to the cohort
+pub async fn delete_cohort_dates(
+    pool: &SqlitePool,
+    fk_assessment_id: i64,
+    fk_cohort_id: i64,
+    actor: &Actor,
+) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let dates = remove_cohort_dates_row(&mut transaction, fk_assessment_id, fk_cohort_id).await?;
+    if let Some(dates) = &dates {
+        audit::record_delete(
+            &mut transaction,
+            actor,
+            audit::COHORT_DATES,
+            fk_assessment_id,
+            dates,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(dates.is_some())
+}
+
+pub async fn remove_cohort_dates_row(
+    connection: &mut SqliteConnection,
+    fk_assessment_id: i64,
+    fk_cohort_id: i64,
+) -> Result<Option<CohortDates>, sqlx::Error> {
+    let dates = sqlx::query_as::<_, CohortDates>(
+        "DELETE FROM cohort_dates WHERE fk_assessment_id = ? AND fk_cohort_id = ? RETURNING *",
+    )
+    .bind(fk_assessment_id)
+    .bind(fk_cohort_id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(dates)
+}
+
+/// Skipped once the assessment was purged from the trash
+pub async fn restore_cohort_dates_row(
+    connection: &mut SqliteConnection,
+    dates: &CohortDates,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO cohort_dates (fk_assessment_id, fk_cohort_id, take1, retake1, retake2)
+        SELECT ?, ?, ?, ?, ? WHERE EXISTS (SELECT 1 FROM assessments WHERE id = ?)
+        ON CONFLICT (fk_assessment_id, fk_cohort_id)
+        DO UPDATE SET take1 = excluded.take1, retake1 = excluded.retake1, retake2 = excluded.retake2
+        "#,
+    )
+    .bind(dates.fk_assessment_id)
+    .bind(dates.fk_cohort_id)
+    .bind(&dates.take1)
+    .bind(&dates.retake1)
+    .bind(&dates.retake2)
+    .bind(dates.fk_assessment_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use poise::serenity_prelude as serenity;
+
+/// Error type shared by commands, component handlers and database helpers
+#[derive(Debug)]
+pub enum Error {
+    Database(sqlx::Error),
+    /// Input that could not be parsed or does not make sense, shown to the user as is
+    Validation(String),
+    NotFound(String),
+    Permission(String),
+    Discord(Box<serenity::Error>),
+    /// Anything else, only the error id is shown to the user
+    Internal(String),
+}
+
+impl Error {
+    /// Message shown to the user, internal details stay in the log under `error_id`
+    pub fn user_message(&self, error_id: u64) -> String {
+        match self {
+            Error::Validation(message) => format!("Invalid input: {}", message),
+            Error::NotFound(message) => message.clone(),
+            Error::Permission(message) => message.clone(),
+            Error::Database(_) => format!(
+                "Could not reach the database, please try again later (error id: {})",
+                error_id
+            ),
+            Error::Discord(_) => format!(
+                "Discord did not accept the response, please try again (error id: {})",
+                error_id
+            ),
+            Error::Internal(_) => format!("Something went wrong (error id: {})", error_id),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Database(error) => write!(f, "Database error: {}", error),
+            Error::Validation(message) => write!(f, "Validation error: {}", message),
+            Error::NotFound(message) => write!(f, "Not found: {}", message),
+            Error::Permission(message) => write!(f, "Permission denied: {}", message),
+            Error::Discord(error) => write!(f, "Discord error: {}", error),
+            Error::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// The course code index is hit when a clone, restore or undo would bring back a code in use,
+// see `course_lookup::ensure_unique_code`. PostgreSQL names the index, SQLite only its columns
+fn is_course_code_violation(error: &sqlx::Error) -> bool {
+    is_unique_violation(error, "courses_code", "courses.code")
+}
+
+// Undoing a cohort's deletion brings its name back, which another cohort may have taken
+fn is_cohort_name_violation(error: &sqlx::Error) -> bool {
+    is_unique_violation(error, "cohorts_name", "cohorts.name")
+}
+
+fn is_unique_violation(error: &sqlx::Error, index: &str, column: &str) -> bool {
+    match error {
+        sqlx::Error::Database(error) => {
+            error.is_unique_violation()
+                && (error.constraint() == Some(index) || error.message().contains(column))
+        }
+        _ => false,
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        if is_course_code_violation(&error) {
+            return Error::Validation(String::from(
+                "A course with the same code already exists in that semester and year",
+            ));
+        }
+        if is_cohort_name_violation(&error) {
+            return Error::Validation(String::from("A cohort with the same name already exists"));
+        }
+
+        Error::Database(error)
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for Error {
+    fn from(error: sqlx::migrate::MigrateError) -> Self {
+        Error::Database(sqlx::Error::Migrate(Box::new(error)))
+    }
+}
+
+impl From<serenity::Error> for Error {
+    fn from(error: serenity::Error) -> Self {
+        Error::Discord(Box::new(error))
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(error: std::num::ParseIntError) -> Self {
+        Error::Validation(error.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for Error {
+    fn from(error: chrono::ParseError) -> Self {
+        Error::Validation(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Internal(message.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Internal(message)
+    }
+}
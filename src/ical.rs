@@ -0,0 +1,199 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::database_utils::{Assessment, Course, Session, Term};
+
+// RFC 5545 lines are folded after 75 octets
+const LINE_OCTETS: usize = 75;
+const UID_DOMAIN: &str = "discord-timetable";
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Continuation lines start with a space, which counts towards their octets
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > LINE_OCTETS {
+            folded += "\r\n ";
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+
+    folded + "\r\n"
+}
+
+fn date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn date_time(date_time: NaiveDateTime) -> String {
+    date_time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// DTSTAMP has to be in UTC
+fn utc_date_time(date_time: DateTime<Utc>) -> String {
+    date_time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// The first day on or after `from` that falls on the session's weekday, 1 = Monday
+fn first_occurrence(from: NaiveDate, weekday: i64) -> NaiveDate {
+    let offset = (weekday - 1 - from.weekday().num_days_from_monday() as i64).rem_euclid(7);
+    from + Duration::days(offset)
+}
+
+/// All-day events for every assessment attempt and weekly events for the sessions.
+/// Sessions repeat from the start of the term until its end, or from `today` on without a term.
+/// Times are floating, calendars show them in their own time zone, only the stamp is in UTC
+pub fn course_calendar(
+    course: &Course,
+    term: Option<&Term>,
+    assessments: &[Assessment],
+    sessions: &[Session],
+    today: NaiveDate,
+    stamp: DateTime<Utc>,
+) -> String {
+    let parse = |text: &str| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok();
+    let term_start = term.and_then(|term| parse(&term.start_date));
+    let term_end = term.and_then(|term| parse(&term.end_date));
+
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:-//{}//{}//EN", UID_DOMAIN, escape(&course.code)),
+        String::from("CALSCALE:GREGORIAN"),
+    ];
+
+    for assessment in assessments {
+        let attempts = [
+            ("Take 1", &assessment.take1),
+            ("Retake 1", &assessment.retake1),
+            ("Retake 2", &assessment.retake2),
+        ];
+        for (index, (attempt, day)) in attempts.iter().enumerate() {
+            let day = match parse(day) {
+                Some(day) => day,
+                None => continue,
+            };
+            lines.extend([
+                String::from("BEGIN:VEVENT"),
+                format!(
+                    "UID:assessment-{}-{}@{}",
+                    assessment.id,
+                    index + 1,
+                    UID_DOMAIN
+                ),
+                format!("DTSTAMP:{}", utc_date_time(stamp)),
+                format!("DTSTART;VALUE=DATE:{}", date(day)),
+                format!("DTEND;VALUE=DATE:{}", date(day + Duration::days(1))),
+                format!(
+                    "SUMMARY:{}",
+                    escape(&format!(
+                        "{} {} ({})",
+                        course.code, assessment.name, attempt
+                    ))
+                ),
+                String::from("END:VEVENT"),
+            ]);
+        }
+    }
+
+    for session in sessions {
+        let times = (
+            NaiveTime::parse_from_str(&session.start_time, "%H:%M"),
+            NaiveTime::parse_from_str(&session.end_time, "%H:%M"),
+        );
+        let (start, end) = match times {
+            (Ok(start), Ok(end)) if (1..=7).contains(&session.weekday) => (start, end),
+            _ => continue,
+        };
+        let first = first_occurrence(term_start.unwrap_or(today), session.weekday);
+        let rule = match term_end {
+            Some(end) => format!("RRULE:FREQ=WEEKLY;UNTIL={}T235959", date(end)),
+            None => String::from("RRULE:FREQ=WEEKLY"),
+        };
+
+        lines.extend([
+            String::from("BEGIN:VEVENT"),
+            format!("UID:session-{}@{}", session.id, UID_DOMAIN),
+            format!("DTSTAMP:{}", utc_date_time(stamp)),
+            format!("DTSTART:{}", date_time(first.and_time(start))),
+            format!("DTEND:{}", date_time(first.and_time(end))),
+            rule,
+            format!(
+                "SUMMARY:{}",
+                escape(&format!("{} {}", course.code, course.name))
+            ),
+            format!("LOCATION:{}", escape(&session.location)),
+            String::from("END:VEVENT"),
+        ]);
+    }
+
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    #[test]
+    fn course_calendar_has_attempts_and_weekly_sessions() {
+        let today = NaiveDate::from_ymd_opt(2024, 9, 10).unwrap();
+        let stamp = today.and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let course = Course {
+            id: 1,
+            ..course("MTH101", Some(1))
+        };
+        let assessments = [Assessment {
+            id: 2,
+            take1: String::from("2024-10-01"),
+            retake1: String::from("TBA"),
+            retake2: String::new(),
+            ..assessment("Midterm, part 1", 1)
+        }];
+        let sessions = [Session {
+            id: 3,
+            weekday: 3,
+            start_time: String::from("10:00"),
+            end_time: String::from("11:30"),
+            location: String::from("Room 101"),
+            fk_course_id: 1,
+        }];
+
+        let calendar = course_calendar(
+            &course,
+            Some(&term("Fall", true)),
+            &assessments,
+            &sessions,
+            today,
+            stamp,
+        );
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(calendar.contains("DTSTART;VALUE=DATE:20241001\r\nDTEND;VALUE=DATE:20241002\r\n"));
+        assert!(calendar.contains("SUMMARY:MTH101 Midterm\\, part 1 (Take 1)\r\n"));
+        assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 2);
+        assert_eq!(calendar.matches("DTSTAMP:20240910T120000Z\r\n").count(), 2);
+        // The term starts on Sunday 2024-09-01, the first Wednesday after is 2024-09-04
+        assert!(calendar.contains("DTSTART:20240904T100000\r\nDTEND:20240904T113000\r\n"));
+        assert!(calendar.contains("RRULE:FREQ=WEEKLY;UNTIL=20250131T235959\r\n"));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|part| part.len() <= LINE_OCTETS));
+        assert_eq!(folded.replace("\r\n ", ""), line + "\r\n");
+    }
+}
@@ -1,27 +1,57 @@
+use std::borrow::Cow;
+
+use ::serenity::model::application::component::InputTextStyle;
 use ::serenity::model::application::interaction::InteractionResponseType;
+use chrono::{NaiveDate, Utc};
 use poise::serenity_prelude as serenity;
 use serenity::model::application::interaction;
 use serenity::model::prelude::interaction::MessageFlags;
 
+use crate::access_control;
+use crate::audit::Actor;
+use crate::commands;
+use crate::config;
+use crate::course_lookup;
+use crate::course_roles;
+use crate::error::Error;
+use crate::list_view::{AssessmentView, CourseView};
+use crate::rendering;
+use crate::storage::Storage;
 use crate::{database_utils, utils};
 
-type Error = Box<dyn std::error::Error + Send + Sync>;
-
 pub fn parse_select_menu_course_id(select_menu_id: &str) -> Result<i64, Error> {
-    println!("Select menu id: {}", select_menu_id);
     let mut split_id = select_menu_id.split(";");
 
     let course_id = match split_id.next() {
-        Some("select_course") => match split_id.last() {
-            Some(id) => id.parse::<i64>().unwrap(),
-            None => return Err("No course id".into()),
+        Some("select_course") => match split_id.next() {
+            Some(id) => id.parse::<i64>()?,
+            None => return Err(Error::Validation(String::from("No course id"))),
         },
-        _ => return Err("Wrong value".into()),
+        _ => return Err(Error::Validation(String::from("Not a course select menu"))),
     };
 
     Ok(course_id)
 }
 
+// Whatever follows the course id, see `utils::create_courses_select_menu`
+pub fn parse_select_menu_view(select_menu_id: &str) -> &str {
+    select_menu_id.splitn(3, ';').nth(2).unwrap_or("")
+}
+
+pub fn parse_select_menu_month(select_menu_id: &str) -> Result<usize, Error> {
+    let mut split_id = select_menu_id.split(';');
+
+    let month = match split_id.next() {
+        Some("select_day") => match split_id.next() {
+            Some(month) => month.parse::<usize>()?,
+            None => return Err(Error::Validation(String::from("No month"))),
+        },
+        _ => return Err(Error::Validation(String::from("Not a day select menu"))),
+    };
+
+    Ok(month)
+}
+
 pub fn parse_button_page(button_id: &str) -> Result<usize, Error> {
     let mut split_id = button_id.split(";");
 
@@ -29,16 +59,16 @@ pub fn parse_button_page(button_id: &str) -> Result<usize, Error> {
         Some("next_page") => "next_page",
         Some("previous_page") => "previous_page",
         Some("refresh_page") => "refresh_page",
-        _ => return Err("nope".into()),
+        _ => return Err(Error::Validation(String::from("Not a page button"))),
     };
 
-    let current_page = match split_id.last() {
-        Some(page) => page.parse::<usize>().unwrap(),
-        None => return Err("No page number".into()),
+    let current_page = match split_id.next() {
+        Some(page) => page.parse::<usize>()?,
+        None => return Err(Error::Validation(String::from("No page number"))),
     };
 
     let new_page = match button_id {
-        "previous_page" => current_page - 1,
+        "previous_page" => current_page.saturating_sub(1).max(1),
         "next_page" => current_page + 1,
         _ => current_page,
     };
@@ -46,15 +76,58 @@ pub fn parse_button_page(button_id: &str) -> Result<usize, Error> {
     Ok(new_page)
 }
 
+// Search result buttons, see `utils::create_search_buttons`
+pub fn parse_course_button(button_id: &str) -> Result<i64, Error> {
+    match button_id.strip_prefix("open_course;") {
+        Some(id) => Ok(id.parse::<i64>()?),
+        None => Err(Error::Validation(String::from("Not a course button"))),
+    }
+}
+
+// Whatever follows the page number, see `utils::create_buttons`
+pub fn parse_button_view(button_id: &str) -> &str {
+    button_id.splitn(3, ';').nth(2).unwrap_or("")
+}
+
+/// Returns whether the action was confirmed, which action it was and the id it applies to
+pub fn parse_confirmation_button(button_id: &str) -> Result<(bool, &str, i64), Error> {
+    let mut split_id = button_id.split(';');
+
+    let confirmed = match split_id.next() {
+        Some("confirm") => true,
+        Some("cancel") => false,
+        _ => return Err(Error::Validation(String::from("Not a confirmation button"))),
+    };
+
+    let action = split_id
+        .next()
+        .ok_or(Error::Validation(String::from("No action")))?;
+    let id = match split_id.next() {
+        Some(id) => id.parse::<i64>()?,
+        None => return Err(Error::Validation(String::from("No id"))),
+    };
+
+    Ok((confirmed, action, id))
+}
+
 pub async fn check_interaction_caller(
     ctx: &serenity::Context,
     interaction: interaction::Interaction,
 ) -> Result<bool, Error> {
-    let msg_component = interaction.as_message_component().unwrap();
-    let author = (msg_component.message.interaction).clone().unwrap().user;
-    let caller = (msg_component.user).clone();
+    let msg_component = interaction
+        .as_message_component()
+        .ok_or(Error::Validation(String::from("Not a message component")))?;
+    // Only messages sent in response to a slash command have an author to compare against
+    let author = match &msg_component.message.interaction {
+        Some(message_interaction) => message_interaction.user.id,
+        None => {
+            return Err(Error::Validation(String::from(
+                "This message does not belong to a command, its buttons can not be used",
+            )))
+        }
+    };
 
-    if author.id != caller.id {
+    if author != msg_component.user.id {
         msg_component
             .create_interaction_response(&ctx, |r| {
                 r.kind(InteractionResponseType::ChannelMessageWithSource)
@@ -71,45 +144,112 @@ pub async fn check_interaction_caller(
     Ok(true)
 }
 
+/// Component counterpart of the framework `on_error`, the component interaction id serves as error id.
+/// The error itself is logged by the event handler
+pub async fn report_error(
+    ctx: &serenity::Context,
+    component: &interaction::message_component::MessageComponentInteraction,
+    error: &Error,
+) {
+    let error_id = component.id.0;
+
+    let response = error.user_message(error_id);
+    let reported = component
+        .create_interaction_response(&ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(response).flags(MessageFlags::EPHEMERAL))
+        })
+        .await;
+    if let Err(e) = reported {
+        tracing::warn!(error_id, error = %e, "failed to report error");
+    }
+}
+
+/// `report_error` for modal submissions
+pub async fn report_modal_error(
+    ctx: &serenity::Context,
+    modal: &interaction::modal::ModalSubmitInteraction,
+    error: &Error,
+) {
+    let error_id = modal.id.0;
+
+    let response = error.user_message(error_id);
+    let reported = modal
+        .create_interaction_response(&ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(response).flags(MessageFlags::EPHEMERAL))
+        })
+        .await;
+    if let Err(e) = reported {
+        tracing::warn!(error_id, error = %e, "failed to report error");
+    }
+}
+
+fn component_actor(
+    component: &interaction::message_component::MessageComponentInteraction,
+) -> Actor {
+    Actor {
+        guild_id: component.guild_id.map_or(0, |guild_id| guild_id.0 as i64),
+        user_id: component.user.id.0 as i64,
+    }
+}
+
 pub async fn list_courses_handler(
     ctx: &serenity::Context,
     interaction: &serenity::model::application::interaction::Interaction,
+    storage: &dyn Storage,
 ) -> Result<(), Error> {
-    //println!("Interaction: {:?}", interaction);
-
     let msg_component = match interaction {
         serenity::model::application::interaction::Interaction::MessageComponent(component) => {
             component.clone()
         }
-        _ => return Err("Not a message component".into()),
+        _ => return Err(Error::Validation(String::from("Not a message component"))),
     };
 
-    if check_interaction_caller(&ctx, interaction.clone()).await? == false {
+    if !check_interaction_caller(ctx, interaction.clone()).await? {
         return Ok(());
     }
 
     let button_id = match msg_component.data.component_type {
         serenity::model::application::component::ComponentType::Button => {
-            msg_component.data.custom_id
+            msg_component.data.custom_id.clone()
         }
-        _ => return Err("Not a button".into()),
+        _ => return Err(Error::Validation(String::from("Not a button"))),
     };
 
-    let connection = database_utils::establish_connection().await?;
-    let courses = database_utils::get_all_courses(&connection).await?;
+    let view = parse_button_view(&button_id);
+    let course_view = CourseView::decode(view)?;
 
+    // Wraps around to the first page if the courses no longer fill the requested one
     let mut new_page = parse_button_page(&button_id)?;
-    if new_page > (courses.len() / utils::COURSES_PER_PAGE) + 1 {
-        new_page = 1;
-    }
-
-    let content = utils::format_course_response(&courses, new_page)?;
-
+    let actor = component_actor(&msg_component);
+    let options = commands::render_options(storage, &actor).await?;
+    let listing =
+        match commands::course_list_page(storage, actor.guild_id, new_page, &course_view, &options)
+            .await?
+        {
+            Some(listing) => listing,
+            None => {
+                new_page = 1;
+                commands::course_list_page(
+                    storage,
+                    actor.guild_id,
+                    new_page,
+                    &course_view,
+                    &options,
+                )
+                .await?
+                .ok_or(Error::NotFound(String::from("No courses found")))?
+            }
+        };
+    let content = listing.content;
+
+    let last_page = rendering::page_count(listing.total, config::get().pages.courses);
     let (previous_button, next_button, refresh_button) =
-        utils::create_buttons(new_page, courses.len() / utils::COURSES_PER_PAGE);
+        utils::create_buttons(new_page, last_page - 1, view);
 
-    if courses.is_empty() {
-        interaction.as_message_component().unwrap().create_interaction_response(&ctx, |r| {
+    if listing.total == 0 {
+        msg_component.create_interaction_response(&ctx, |r| {
             r.kind(serenity::model::application::interaction::InteractionResponseType::UpdateMessage)
                 .interaction_response_data(|d| {
                     d.content("No courses found").components(|c| {
@@ -125,9 +265,7 @@ pub async fn list_courses_handler(
         return Ok(());
     }
 
-    interaction
-        .as_message_component()
-        .unwrap()
+    msg_component
         .create_interaction_response(&ctx, |r| {
             r.kind(
                 serenity::model::application::interaction::InteractionResponseType::UpdateMessage,
@@ -150,71 +288,107 @@ pub async fn list_courses_handler(
 pub async fn list_assessments_handler(
     ctx: &serenity::Context,
     interaction: &serenity::model::application::interaction::Interaction,
+    storage: &dyn Storage,
 ) -> Result<(), Error> {
     let msg_component = match interaction {
         serenity::model::application::interaction::Interaction::MessageComponent(component) => {
             component.clone()
         }
-        _ => return Err("Not a message component".into()),
+        _ => return Err(Error::Validation(String::from("Not a message component"))),
     };
 
-    if check_interaction_caller(&ctx, interaction.clone()).await? == false {
+    if !check_interaction_caller(ctx, interaction.clone()).await? {
         return Ok(());
     }
 
     let course_id;
     let mut new_page = 1;
+    let view;
 
     match msg_component.data.component_type {
         serenity::model::application::component::ComponentType::Button => {
             let button_id = msg_component.data.custom_id.clone();
-            new_page = parse_button_page(&button_id)?;
 
-            let select_menu = msg_component.message.components.clone()[1].clone();
-            let select_menu_id = match select_menu.components[0].clone() {
-                serenity::model::application::component::ActionRowComponent::SelectMenu(menu) => {
-                    menu.custom_id.unwrap_or("".to_string())
-                }
-                _ => return Err("Not a select menu".into()),
-            };
-            course_id = match parse_select_menu_course_id(&select_menu_id) {
-                Ok(id) => id,
-                Err(e) => {
-                    println!("Error parsing select menu id: {}", e);
-                    return Ok(());
-                }
-            };
+            if let Ok(id) = parse_course_button(&button_id) {
+                // Opened from `/search`, the message has no course select menu yet
+                course_id = id;
+                view = String::new();
+            } else {
+                new_page = parse_button_page(&button_id)?;
+                view = parse_button_view(&button_id).to_string();
+
+                // The course select menu is kept on the message below the page buttons
+                let select_menu_id = msg_component
+                    .message
+                    .components
+                    .iter()
+                    .flat_map(|row| row.components.iter())
+                    .find_map(|component| match component {
+                        serenity::model::application::component::ActionRowComponent::SelectMenu(
+                            menu,
+                        ) => menu.custom_id.clone(),
+                        _ => None,
+                    })
+                    .ok_or(Error::Validation(String::from("No course select menu")))?;
+                course_id = parse_select_menu_course_id(&select_menu_id)?;
+            }
         }
         serenity::model::application::component::ComponentType::SelectMenu => {
-            course_id = msg_component.data.values[0].clone().parse::<i64>().unwrap();
+            view = parse_select_menu_view(&msg_component.data.custom_id).to_string();
+            course_id = match msg_component.data.values.first() {
+                Some(value) => value.parse::<i64>()?,
+                None => return Err(Error::Validation(String::from("No course selected"))),
+            };
         }
-        _ => return Err("Wrong component type".into()),
+        _ => return Err(Error::Validation(String::from("Wrong component type"))),
     };
 
-    let connection = database_utils::establish_connection().await?;
-    let courses = database_utils::get_all_courses(&connection).await?;
-    let assessments = database_utils::get_course_assessments(&connection, course_id).await?;
-
-    if new_page > (assessments.len() / utils::ASSESSMENTS_PER_PAGE) + 1 {
-        return Ok(());
-    }
-
-    let course_name = match courses.iter().find(|course| course.id == course_id) {
-        Some(course) => course.name.clone(),
-        None => "No course selected".to_string(),
+    let assessment_view = AssessmentView::decode(&view)?;
+
+    let actor = component_actor(&msg_component);
+    let options = commands::render_options(storage, &actor).await?;
+    // Goes back to the first page if the assessments no longer fill the requested one
+    let listing = match commands::assessment_list_page(
+        storage,
+        &actor,
+        course_id,
+        new_page,
+        &assessment_view,
+        &options,
+    )
+    .await?
+    {
+        Some(listing) => listing,
+        None => {
+            new_page = 1;
+            commands::assessment_list_page(
+                storage,
+                &actor,
+                course_id,
+                new_page,
+                &assessment_view,
+                &options,
+            )
+            .await?
+            .ok_or(Error::NotFound(String::from("No assessments found")))?
+        }
     };
 
-    let mut content = utils::format_assessment_response(&assessments, new_page, &course_name)?;
-    let select_menu = match utils::create_courses_select_menu(&courses, course_id) {
+    let mut content = listing.content;
+    let select_menu = match utils::create_courses_select_menu(
+        &listing.courses,
+        course_id,
+        &assessment_view.encode(),
+    ) {
         Ok(menu) => menu,
         Err(e) => {
-            content = format!("Error creating select menu: Course not found. Please select a course from the list below.");
+            content = "Error creating select menu: Course not found. Please select a course from the list below.".to_string();
             e
         }
     };
 
-    if assessments.len() <= utils::ASSESSMENTS_PER_PAGE {
-        interaction.as_message_component().unwrap().create_interaction_response(&ctx, |r| {
+    if listing.total <= config::get().pages.assessments {
+        msg_component.create_interaction_response(&ctx, |r| {
             r.kind(serenity::model::application::interaction::InteractionResponseType::UpdateMessage)
                 .interaction_response_data(|d| {
                     d.content(content)
@@ -224,12 +398,11 @@ pub async fn list_assessments_handler(
         return Ok(());
     }
 
-    let (previous_button, next_button, refresh_button) =
-        utils::create_buttons(new_page, assessments.len() / utils::ASSESSMENTS_PER_PAGE);
+    let last_page = rendering::page_count(listing.total, config::get().pages.assessments);
+    let (previous_button, next_button, _refresh_button) =
+        utils::create_buttons(new_page, last_page - 1, &view);
 
-    interaction
-        .as_message_component()
-        .unwrap()
+    msg_component
         .create_interaction_response(&ctx, |r| {
             r.kind(
                 serenity::model::application::interaction::InteractionResponseType::UpdateMessage,
@@ -248,3 +421,474 @@ pub async fn list_assessments_handler(
 
     Ok(())
 }
+
+pub async fn calendar_handler(
+    ctx: &serenity::Context,
+    interaction: &serenity::model::application::interaction::Interaction,
+    storage: &dyn Storage,
+) -> Result<(), Error> {
+    let msg_component = match interaction {
+        serenity::model::application::interaction::Interaction::MessageComponent(component) => {
+            component.clone()
+        }
+        _ => return Err(Error::Validation(String::from("Not a message component"))),
+    };
+
+    if !check_interaction_caller(ctx, interaction.clone()).await? {
+        return Ok(());
+    }
+
+    let month;
+    let mut selected_day = None;
+
+    match msg_component.data.component_type {
+        serenity::model::application::component::ComponentType::Button => {
+            month = parse_button_page(&msg_component.data.custom_id)?;
+        }
+        serenity::model::application::component::ComponentType::SelectMenu => {
+            month = parse_select_menu_month(&msg_component.data.custom_id)?;
+            selected_day = match msg_component.data.values.first() {
+                Some(day) => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok(),
+                None => None,
+            };
+        }
+        _ => return Err(Error::Validation(String::from("Wrong component type"))),
+    };
+
+    let first_day = match utils::month_from_index(month) {
+        Some(date) => date,
+        None => return Err(Error::Validation(String::from("Invalid month"))),
+    };
+
+    let actor = component_actor(&msg_component);
+    let options = commands::render_options(storage, &actor).await?;
+    let assessments = storage.all_assessments(actor.guild_id).await?;
+
+    let content = utils::format_calendar_response(&assessments, first_day, selected_day, &options);
+    let select_menu = utils::create_calendar_day_select_menu(&assessments, first_day);
+    let (previous_button, next_button) = utils::create_calendar_buttons(month);
+
+    msg_component
+        .create_interaction_response(&ctx, |r| {
+            r.kind(
+                serenity::model::application::interaction::InteractionResponseType::UpdateMessage,
+            )
+            .interaction_response_data(|d| {
+                d.content(content).components(|c| {
+                    c.create_action_row(|row| {
+                        row.add_button(previous_button.clone())
+                            .add_button(next_button.clone())
+                    });
+                    if let Some(select_menu) = select_menu {
+                        c.add_action_row(select_menu);
+                    }
+                    c
+                })
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn confirmation_handler(
+    ctx: &serenity::Context,
+    interaction: &serenity::model::application::interaction::Interaction,
+    storage: &dyn Storage,
+) -> Result<(), Error> {
+    let msg_component = match interaction {
+        serenity::model::application::interaction::Interaction::MessageComponent(component) => {
+            component.clone()
+        }
+        _ => return Err(Error::Validation(String::from("Not a message component"))),
+    };
+
+    if !check_interaction_caller(ctx, interaction.clone()).await? {
+        return Ok(());
+    }
+
+    let (confirmed, action, id) = parse_confirmation_button(&msg_component.data.custom_id)?;
+
+    let sent_at = msg_component.message.timestamp.unix_timestamp();
+    let expired =
+        Utc::now().timestamp() - sent_at > config::get().scheduler.confirmation_timeout_seconds;
+
+    let content = if expired {
+        String::from("Confirmation expired, nothing was removed")
+    } else if !confirmed {
+        String::from("Cancelled, nothing was removed")
+    } else {
+        match action {
+            "remove_course" => {
+                match storage
+                    .delete_course(id, &component_actor(&msg_component))
+                    .await?
+                {
+                    Some(course) => {
+                        format!("Moved course to trash: {}, ID: {}", course.name, course.id)
+                    }
+                    None => format!("Course not found with id: {}", id),
+                }
+            }
+            _ => {
+                return Err(Error::Validation(String::from(
+                    "Unknown confirmation action",
+                )))
+            }
+        }
+    };
+
+    msg_component
+        .create_interaction_response(&ctx, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(content).components(|c| c))
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Course menus of `/enroll` and `/unenroll`, see `commands::enrollment_menu`
+pub async fn enrollment_handler(
+    ctx: &serenity::Context,
+    interaction: &serenity::model::application::interaction::Interaction,
+    storage: &dyn Storage,
+) -> Result<(), Error> {
+    let msg_component = match interaction {
+        serenity::model::application::interaction::Interaction::MessageComponent(component) => {
+            component.clone()
+        }
+        _ => return Err(Error::Validation(String::from("Not a message component"))),
+    };
+
+    if !check_interaction_caller(ctx, interaction.clone()).await? {
+        return Ok(());
+    }
+
+    let view = parse_select_menu_view(&msg_component.data.custom_id);
+    let (action, view) = view.split_once(';').unwrap_or((view, ""));
+    let enroll = match action {
+        "enroll" => true,
+        "unenroll" => false,
+        _ => return Err(Error::Validation(String::from("Not an enrollment menu"))),
+    };
+    let course_view = CourseView::decode(view)?;
+    let actor = component_actor(&msg_component);
+
+    let mut courses: Vec<database_utils::Course> = Vec::new();
+    for value in &msg_component.data.values {
+        // Courses removed since the menu was sent are skipped
+        if let Some(course) = storage
+            .course(actor.guild_id, value.parse::<i64>()?)
+            .await?
+        {
+            storage.set_subscribed(course.id, enroll, &actor).await?;
+            courses.push(course);
+        }
+    }
+
+    let course_ids: Vec<i64> = courses.iter().map(|course| course.id).collect();
+    let failed_roles = course_roles::sync_member(
+        &ctx.http,
+        storage,
+        msg_component.guild_id,
+        msg_component.member.as_ref(),
+        &course_ids,
+    )
+    .await?;
+
+    let codes: Vec<&str> = courses.iter().map(|course| course.code.as_str()).collect();
+    let mut content = match (enroll, codes.is_empty()) {
+        (_, true) => String::from("The selected course no longer exists"),
+        (true, false) => format!("Enrolled in {}", codes.join(", ")),
+        (false, false) => format!("Left {}", codes.join(", ")),
+    };
+    if !failed_roles.is_empty() {
+        let roles: Vec<String> = failed_roles
+            .iter()
+            .map(|role_id| format!("<@&{}>", role_id))
+            .collect();
+        content += &format!(
+            "\nCould not update {}, ask an administrator to check the bot's Manage Roles permission",
+            roles.join(", ")
+        );
+    }
+
+    // The rest of the choices stay on offer until none are left
+    let remaining = commands::enrollment_choices(storage, &actor, enroll, &course_view).await?;
+    let select_menu = match remaining.is_empty() {
+        true => None,
+        false => {
+            let (prompt, select_menu) = commands::enrollment_menu(&remaining, action, &course_view);
+            content += &format!("\n{}", prompt);
+            Some(select_menu)
+        }
+    };
+    msg_component
+        .create_interaction_response(&ctx, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    d.content(content).components(|c| {
+                        if let Some(select_menu) = select_menu {
+                            c.add_action_row(select_menu);
+                        }
+                        c
+                    })
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Buttons of `/course` and its edit modal, see `utils::create_course_buttons`
+pub fn parse_course_action(custom_id: &str) -> Result<(&str, i64), Error> {
+    let mut split_id = custom_id.split(';');
+
+    let action = match split_id.next() {
+        Some(action @ ("subscribe" | "unsubscribe" | "export_course" | "edit_course")) => action,
+        _ => return Err(Error::Validation(String::from("Not a course button"))),
+    };
+
+    let course_id = match split_id.next() {
+        Some(id) => id.parse::<i64>()?,
+        None => return Err(Error::Validation(String::from("No course id"))),
+    };
+
+    Ok((action, course_id))
+}
+
+/// The dashboard and its buttons as the member sees them
+async fn course_message(
+    storage: &dyn Storage,
+    course_id: i64,
+    actor: &Actor,
+    guild_id: Option<serenity::GuildId>,
+    member: Option<&serenity::Member>,
+) -> Result<(String, serenity::CreateActionRow), Error> {
+    let options = commands::render_options(storage, actor).await?;
+    let content = commands::course_dashboard(storage, actor.guild_id, course_id, &options)
+        .await?
+        .ok_or(Error::NotFound(format!(
+            "Course not found with id: {}",
+            course_id
+        )))?;
+
+    let subscribed = storage.is_subscribed(actor.user_id, course_id).await?;
+    let can_edit =
+        access_control::member_can_edit_course(storage, guild_id, member, course_id).await?;
+
+    Ok((
+        content,
+        utils::create_course_buttons(course_id, subscribed, can_edit),
+    ))
+}
+
+pub async fn course_handler(
+    ctx: &serenity::Context,
+    interaction: &serenity::model::application::interaction::Interaction,
+    storage: &dyn Storage,
+) -> Result<(), Error> {
+    let msg_component = match interaction {
+        serenity::model::application::interaction::Interaction::MessageComponent(component) => {
+            component.clone()
+        }
+        _ => return Err(Error::Validation(String::from("Not a message component"))),
+    };
+
+    if !check_interaction_caller(ctx, interaction.clone()).await? {
+        return Ok(());
+    }
+
+    let (action, course_id) = parse_course_action(&msg_component.data.custom_id)?;
+    let actor = component_actor(&msg_component);
+
+    match action {
+        "subscribe" | "unsubscribe" => {
+            storage
+                .set_subscribed(course_id, action == "subscribe", &actor)
+                .await?;
+            // Failures are logged, the dashboard has no room to report them
+            course_roles::sync_member(
+                &ctx.http,
+                storage,
+                msg_component.guild_id,
+                msg_component.member.as_ref(),
+                &[course_id],
+            )
+            .await?;
+
+            let (content, buttons) = course_message(
+                storage,
+                course_id,
+                &actor,
+                msg_component.guild_id,
+                msg_component.member.as_ref(),
+            )
+            .await?;
+            msg_component
+                .create_interaction_response(&ctx, |r| {
+                    r.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.content(content).components(|c| c.add_action_row(buttons))
+                        })
+                })
+                .await?;
+        }
+        "export_course" => {
+            let (course, calendar) = commands::course_calendar(storage, actor.guild_id, course_id)
+                .await?
+                .ok_or(Error::NotFound(format!(
+                    "Course not found with id: {}",
+                    course_id
+                )))?;
+
+            msg_component
+                .create_interaction_response(&ctx, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.content(format!(
+                                "Calendar of {}, import it into your calendar app",
+                                course.code
+                            ))
+                            .add_file(serenity::AttachmentType::Bytes {
+                                data: Cow::Owned(calendar.into_bytes()),
+                                filename: format!("{}.ics", course.code),
+                            })
+                            .flags(MessageFlags::EPHEMERAL)
+                        })
+                })
+                .await?;
+        }
+        _ => {
+            let member = msg_component.member.as_ref();
+            if !access_control::member_can_edit_course(
+                storage,
+                msg_component.guild_id,
+                member,
+                course_id,
+            )
+            .await?
+            {
+                return Err(Error::Permission(format!(
+                    "You are not allowed to edit course with id: {}",
+                    course_id
+                )));
+            }
+
+            let course =
+                storage
+                    .course(actor.guild_id, course_id)
+                    .await?
+                    .ok_or(Error::NotFound(format!(
+                        "Course not found with id: {}",
+                        course_id
+                    )))?;
+            let inputs = [
+                ("name", "Name", course.name.clone(), 3, 80),
+                ("code", "Code", course.code.clone(), 3, 10),
+                ("semester", "Semester", course.semester.to_string(), 1, 2),
+                ("year", "Year", course.year.to_string(), 4, 4),
+                ("credit", "Credit", course.credit.to_string(), 1, 10),
+            ];
+
+            msg_component
+                .create_interaction_response(&ctx, |r| {
+                    r.kind(InteractionResponseType::Modal)
+                        .interaction_response_data(|d| {
+                            d.custom_id(format!("edit_course;{}", course_id))
+                                .title(format!("Edit {}", course.code))
+                                .components(|c| {
+                                    for (id, label, value, min, max) in inputs {
+                                        c.create_action_row(|row| {
+                                            row.create_input_text(|input| {
+                                                input
+                                                    .custom_id(id)
+                                                    .label(label)
+                                                    .style(InputTextStyle::Short)
+                                                    .value(value)
+                                                    .min_length(min)
+                                                    .max_length(max)
+                                                    .required(true)
+                                            })
+                                        });
+                                    }
+                                    c
+                                })
+                        })
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Submitted edit modal of `/course`, the dashboard it was opened from is updated in place
+pub async fn edit_course_modal_handler(
+    ctx: &serenity::Context,
+    modal: &interaction::modal::ModalSubmitInteraction,
+    storage: &dyn Storage,
+) -> Result<(), Error> {
+    let (_, course_id) = parse_course_action(&modal.data.custom_id)?;
+    let actor = Actor {
+        guild_id: modal.guild_id.map_or(0, |guild_id| guild_id.0 as i64),
+        user_id: modal.user.id.0 as i64,
+    };
+    if !access_control::member_can_edit_course(
+        storage,
+        modal.guild_id,
+        modal.member.as_ref(),
+        course_id,
+    )
+    .await?
+    {
+        return Err(Error::Permission(format!(
+            "You are not allowed to edit course with id: {}",
+            course_id
+        )));
+    }
+
+    let course = storage
+        .course(actor.guild_id, course_id)
+        .await?
+        .ok_or(Error::NotFound(format!(
+            "Course not found with id: {}",
+            course_id
+        )))?;
+    let fields: Vec<(String, String)> = modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .filter_map(|component| match component {
+            serenity::model::application::component::ActionRowComponent::InputText(input) => {
+                Some((input.custom_id.clone(), input.value.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    let edited = commands::edited_course(&course, &fields)?;
+    course_lookup::ensure_unique_code(storage, actor.guild_id, &edited).await?;
+
+    storage.update_course(&edited, &actor).await?;
+
+    let (content, buttons) = course_message(
+        storage,
+        course_id,
+        &actor,
+        modal.guild_id,
+        modal.member.as_ref(),
+    )
+    .await?;
+    modal
+        .create_interaction_response(&ctx, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    d.content(content).components(|c| c.add_action_row(buttons))
+                })
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,354 @@
+use chrono::NaiveDate;
+
+use crate::database_utils::{Assessment, Course};
+use crate::error::Error;
+use crate::utils;
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CourseSort {
+    #[default]
+    #[name = "id"]
+    Id,
+    #[name = "name"]
+    Name,
+    #[name = "code"]
+    Code,
+    #[name = "semester"]
+    Semester,
+    #[name = "year"]
+    Year,
+    #[name = "credit"]
+    Credit,
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AssessmentSort {
+    #[default]
+    #[name = "id"]
+    Id,
+    #[name = "next attempt"]
+    NextAttempt,
+    #[name = "name"]
+    Name,
+    #[name = "weight"]
+    Weight,
+}
+
+/// Sort order and filters of `list_courses`, kept in the page button ids
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct CourseView {
+    pub term_id: Option<i64>,
+    pub sort: CourseSort,
+    pub semester: Option<i64>,
+    pub year: Option<i64>,
+    pub min_credit: Option<f64>,
+    pub max_credit: Option<f64>,
+    /// Case-insensitive part of the name or code
+    pub query: Option<String>,
+}
+
+/// Term, sort order and filter of `list_assessments`, kept in the page button
+/// and course select menu ids
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct AssessmentView {
+    /// Term whose courses the select menu offers, the course's own term if not given
+    pub term_id: Option<i64>,
+    pub sort: AssessmentSort,
+    /// Case-insensitive part of the name
+    pub query: Option<String>,
+}
+
+fn contains(text: &str, query: &Option<String>) -> bool {
+    query
+        .as_ref()
+        .is_none_or(|query| text.to_lowercase().contains(&query.to_lowercase()))
+}
+
+/// `key=value` pairs separated by commas, the query goes last so it may contain anything
+fn encode(pairs: Vec<(&str, String)>, query: &Option<String>) -> String {
+    let mut parts: Vec<String> = pairs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    if let Some(query) = query {
+        parts.push(format!("q={}", query));
+    }
+
+    parts.join(",")
+}
+
+fn decode(view: &str) -> (Vec<(&str, &str)>, Option<String>) {
+    let (pairs, query) = match view.strip_prefix("q=") {
+        Some(query) => ("", Some(query.to_string())),
+        None => match view.find(",q=") {
+            Some(index) => (&view[..index], Some(view[index + 3..].to_string())),
+            None => (view, None),
+        },
+    };
+
+    let pairs = pairs
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+
+    (pairs, query)
+}
+
+fn invalid(key: &str, value: &str) -> Error {
+    Error::Validation(format!("Invalid list view {}: {}", key, value))
+}
+
+impl CourseView {
+    pub fn encode(&self) -> String {
+        let mut pairs = Vec::new();
+        if let Some(term_id) = self.term_id {
+            pairs.push(("t", term_id.to_string()));
+        }
+        if self.sort != CourseSort::Id {
+            pairs.push(("s", self.sort.name().to_string()));
+        }
+        if let Some(semester) = self.semester {
+            pairs.push(("se", semester.to_string()));
+        }
+        if let Some(year) = self.year {
+            pairs.push(("y", year.to_string()));
+        }
+        if self.min_credit.is_some() || self.max_credit.is_some() {
+            let bound = |credit: Option<f64>| credit.map(|c| c.to_string()).unwrap_or_default();
+            pairs.push((
+                "c",
+                format!("{}-{}", bound(self.min_credit), bound(self.max_credit)),
+            ));
+        }
+
+        encode(pairs, &self.query)
+    }
+
+    /// Also accepts a bare term id, which older messages have in their buttons
+    pub fn decode(view: &str) -> Result<Self, Error> {
+        if let Ok(term_id) = view.parse::<i64>() {
+            return Ok(CourseView {
+                term_id: Some(term_id),
+                ..CourseView::default()
+            });
+        }
+
+        let (pairs, query) = decode(view);
+        let mut course_view = CourseView {
+            query,
+            ..CourseView::default()
+        };
+
+        for (key, value) in pairs {
+            match key {
+                "t" => course_view.term_id = Some(value.parse()?),
+                "s" => course_view.sort = value.parse().map_err(|_| invalid(key, value))?,
+                "se" => course_view.semester = Some(value.parse()?),
+                "y" => course_view.year = Some(value.parse()?),
+                "c" => {
+                    let (min, max) = value.split_once('-').ok_or(invalid(key, value))?;
+                    let bound = |credit: &str| match credit {
+                        "" => Ok(None),
+                        credit => credit.parse().map(Some).map_err(|_| invalid(key, value)),
+                    };
+                    course_view.min_credit = bound(min)?;
+                    course_view.max_credit = bound(max)?;
+                }
+                _ => return Err(invalid(key, value)),
+            }
+        }
+
+        Ok(course_view)
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.semester.is_some()
+            || self.year.is_some()
+            || self.min_credit.is_some()
+            || self.max_credit.is_some()
+            || self.query.is_some()
+    }
+
+    pub fn matches(&self, course: &Course) -> bool {
+        self.semester
+            .is_none_or(|semester| course.semester == semester)
+            && self.year.is_none_or(|year| course.year == year)
+            && self.min_credit.is_none_or(|min| course.credit >= min)
+            && self.max_credit.is_none_or(|max| course.credit <= max)
+            && (contains(&course.name, &self.query) || contains(&course.code, &self.query))
+    }
+
+    pub fn apply(&self, courses: &mut Vec<Course>) {
+        courses.retain(|course| self.matches(course));
+
+        match self.sort {
+            CourseSort::Id => courses.sort_by_key(|course| course.id),
+            CourseSort::Name => courses.sort_by_key(|course| course.name.to_lowercase()),
+            CourseSort::Code => courses.sort_by_key(|course| course.code.to_lowercase()),
+            CourseSort::Semester => courses.sort_by_key(|course| course.semester),
+            CourseSort::Year => courses.sort_by_key(|course| course.year),
+            CourseSort::Credit => courses.sort_by(|a, b| a.credit.total_cmp(&b.credit)),
+        }
+    }
+}
+
+impl AssessmentView {
+    pub fn encode(&self) -> String {
+        let mut pairs = Vec::new();
+        if let Some(term_id) = self.term_id {
+            pairs.push(("t", term_id.to_string()));
+        }
+        if self.sort != AssessmentSort::Id {
+            pairs.push(("s", self.sort.name().to_string()));
+        }
+
+        encode(pairs, &self.query)
+    }
+
+    pub fn decode(view: &str) -> Result<Self, Error> {
+        let (pairs, query) = decode(view);
+        let mut assessment_view = AssessmentView {
+            query,
+            ..AssessmentView::default()
+        };
+
+        for (key, value) in pairs {
+            match key {
+                "t" => assessment_view.term_id = Some(value.parse()?),
+                "s" => assessment_view.sort = value.parse().map_err(|_| invalid(key, value))?,
+                _ => return Err(invalid(key, value)),
+            }
+        }
+
+        Ok(assessment_view)
+    }
+
+    /// By next attempt lists upcoming ones first, then those with only past dates, most recent first.
+    /// By weight lists the heaviest first
+    pub fn apply(&self, assessments: &mut Vec<Assessment>, today: NaiveDate) {
+        assessments.retain(|assessment| contains(&assessment.name, &self.query));
+
+        match self.sort {
+            AssessmentSort::Id => assessments.sort_by_key(|assessment| assessment.id),
+            AssessmentSort::NextAttempt => {
+                assessments.sort_by_key(|assessment| match utils::next_attempt(assessment, today) {
+                    Some(date) if date >= today => (0, (date - today).num_days()),
+                    Some(date) => (1, (today - date).num_days()),
+                    None => (2, 0),
+                })
+            }
+            AssessmentSort::Name => {
+                assessments.sort_by_key(|assessment| assessment.name.to_lowercase())
+            }
+            AssessmentSort::Weight => assessments.sort_by(|a, b| b.weight.total_cmp(&a.weight)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    fn numbered(code: &str, id: i64, semester: i64, credit: f64) -> Course {
+        Course {
+            id,
+            semester,
+            credit,
+            ..course(code, None)
+        }
+    }
+
+    #[test]
+    fn course_views_survive_the_button_id() {
+        let view = CourseView {
+            term_id: Some(3),
+            sort: CourseSort::Credit,
+            semester: Some(2),
+            year: Some(2024),
+            min_credit: Some(1.5),
+            max_credit: None,
+            query: Some(String::from("a,q=b")),
+        };
+
+        let encoded = view.encode();
+        assert_eq!(encoded, "t=3,s=credit,se=2,y=2024,c=1.5-,q=a,q=b");
+        assert_eq!(CourseView::decode(&encoded).unwrap(), view);
+
+        assert_eq!(CourseView::decode("").unwrap(), CourseView::default());
+        assert_eq!(CourseView::decode("7").unwrap().term_id, Some(7));
+        assert!(CourseView::decode("s=colour").is_err());
+    }
+
+    #[test]
+    fn assessment_views_survive_the_component_ids() {
+        let view = AssessmentView {
+            term_id: Some(3),
+            sort: AssessmentSort::NextAttempt,
+            query: Some(String::from("exam")),
+        };
+
+        assert_eq!(AssessmentView::decode(&view.encode()).unwrap(), view);
+        assert_eq!(
+            AssessmentView::decode("").unwrap(),
+            AssessmentView::default()
+        );
+    }
+
+    #[test]
+    fn course_filters_and_sort_apply_together() {
+        let mut courses = vec![
+            numbered("MTH", 1, 1, 6.0),
+            numbered("PHY", 2, 2, 3.0),
+            numbered("MATHS", 3, 2, 9.0),
+            numbered("CHEM", 4, 2, 4.5),
+        ];
+        let view = CourseView {
+            sort: CourseSort::Credit,
+            semester: Some(2),
+            max_credit: Some(6.0),
+            ..CourseView::default()
+        };
+
+        view.apply(&mut courses);
+        let codes: Vec<&str> = courses.iter().map(|course| course.code.as_str()).collect();
+        assert_eq!(codes, vec!["PHY", "CHEM"]);
+
+        let mut courses = vec![numbered("MTH", 1, 1, 6.0), numbered("PHY", 2, 2, 3.0)];
+        let view = CourseView {
+            query: Some(String::from("mt")),
+            ..CourseView::default()
+        };
+        view.apply(&mut courses);
+        assert_eq!(courses.len(), 1);
+        assert!(view.is_filtered());
+    }
+
+    #[test]
+    fn assessments_sort_by_next_attempt_then_recent_past() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let dated = |id: i64, take1: &str, retake1: &str| Assessment {
+            id,
+            take1: take1.to_string(),
+            retake1: retake1.to_string(),
+            retake2: String::new(),
+            ..assessment("Exam", 1)
+        };
+        let mut assessments = vec![
+            dated(1, "TBA", ""),
+            dated(2, "2024-01-10", "2024-02-10"),
+            dated(3, "2024-03-01", "2024-05-01"),
+            dated(4, "2024-03-16", "2024-04-16"),
+            dated(5, "2023-12-01", ""),
+        ];
+
+        let view = AssessmentView {
+            sort: AssessmentSort::NextAttempt,
+            ..AssessmentView::default()
+        };
+        view.apply(&mut assessments, today);
+
+        let ids: Vec<i64> = assessments.iter().map(|assessment| assessment.id).collect();
+        assert_eq!(ids, vec![4, 3, 2, 5, 1]);
+    }
+}
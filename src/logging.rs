@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+use tracing::Span;
+use tracing_subscriber::EnvFilter;
+
+use crate::commands::Data;
+use crate::error::Error;
+
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Level filter from `RUST_LOG` (default `info`), JSON lines when `LOG_FORMAT=json`
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = std::env::var("LOG_FORMAT").is_ok_and(|format| format == "json");
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if json {
+        builder.json().with_current_span(true).init();
+    } else {
+        builder.init();
+    }
+}
+
+/// Kept in the invocation data so every hook of a command logs under the same span
+struct CommandTrace {
+    span: Span,
+    started: Instant,
+}
+
+pub fn command_span(ctx: Context<'_>) -> Span {
+    tracing::info_span!(
+        "command",
+        command = %ctx.command().qualified_name,
+        guild = ctx.guild_id().map_or(0, |guild_id| guild_id.0),
+        user = ctx.author().id.0,
+        invocation = ctx.id(),
+    )
+}
+
+pub async fn pre_command(ctx: Context<'_>) {
+    let span = command_span(ctx);
+    span.in_scope(|| tracing::debug!(invocation = %ctx.invocation_string(), "command started"));
+
+    ctx.set_invocation_data(CommandTrace {
+        span,
+        started: Instant::now(),
+    })
+    .await;
+}
+
+/// Logs the outcome of a command, called for both successful and failed invocations
+pub async fn finish_command(ctx: Context<'_>, error: Option<&Error>) {
+    let (span, latency_ms) = match ctx.invocation_data::<CommandTrace>().await {
+        Some(trace) => (
+            trace.span.clone(),
+            trace.started.elapsed().as_millis() as u64,
+        ),
+        None => (command_span(ctx), 0),
+    };
+
+    span.in_scope(|| match error {
+        None => tracing::info!(latency_ms, outcome = "ok", "command finished"),
+        Some(error) => tracing::error!(
+            latency_ms,
+            outcome = "error",
+            error_id = ctx.id(),
+            %error,
+            "command failed"
+        ),
+    });
+}
+
+pub async fn post_command(ctx: Context<'_>) {
+    finish_command(ctx, None).await;
+}
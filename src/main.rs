@@ -3,26 +3,71 @@ use poise::Event;
 
 use dotenv::dotenv;
 use std::env;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
 
+mod access_control;
+mod audit;
+mod clash_detection;
+mod cohorts;
 mod commands;
+mod config;
+mod course_lookup;
+mod course_roles;
 mod database_utils;
+mod error;
+mod ical;
 mod interaction_handlers;
+mod list_view;
+mod logging;
+#[cfg(feature = "postgres")]
+mod postgres_utils;
+mod reminders;
+mod rendering;
+mod storage;
+mod timetable_image;
 mod utils;
 
 use crate::commands::Data;
 use crate::commands::{
-    insert_assessment, insert_course, list_assessments, list_courses, remove_assessment,
-    remove_course,
+    activate_term, archive_term, assign_cohort, audit, calendar, clashes, clone_courses, cohort,
+    course_role, enroll, insert_assessment, insert_course, insert_session, insert_term,
+    list_assessments, list_courses, list_terms, my_cohort, my_deadlines, my_timetable, permissions,
+    register, remove_assessment, remove_course, remove_session, restore_assessment, restore_course,
+    search, show_course, theme, timetable_image, trash, unassign_cohort, undo, unenroll,
 };
-
-type Error = Box<dyn std::error::Error + Send + Sync>;
+use crate::config::RegistrationScope;
+use crate::error::Error;
+use crate::storage::Storage;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     // env::set_var("RUST_BACKTRACE", "1");
+    logging::init();
+
+    match config::Config::load() {
+        Ok(config) => config::init(config),
+        Err(error) => {
+            tracing::error!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    let storage = match storage::connect().await {
+        Ok(storage) => storage,
+        Err(error) => {
+            tracing::error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    tokio::spawn(purge_trash_task(storage.clone()));
 
     let options = poise::FrameworkOptions {
+        on_error: |error| Box::pin(on_error(error)),
+        pre_command: |ctx| Box::pin(logging::pre_command(ctx)),
+        post_command: |ctx| Box::pin(logging::post_command(ctx)),
         event_handler: |ctx, event, framework, data| {
             Box::pin(event_handler(ctx, event, framework, data))
         },
@@ -31,8 +76,37 @@ async fn main() {
             remove_course(),
             list_courses(),
             list_assessments(),
+            search(),
+            show_course(),
             insert_assessment(),
             remove_assessment(),
+            insert_session(),
+            remove_session(),
+            timetable_image(),
+            my_timetable(),
+            my_deadlines(),
+            enroll(),
+            unenroll(),
+            calendar(),
+            clashes(),
+            insert_term(),
+            activate_term(),
+            archive_term(),
+            list_terms(),
+            clone_courses(),
+            permissions(),
+            course_role(),
+            cohort(),
+            assign_cohort(),
+            unassign_cohort(),
+            my_cohort(),
+            audit(),
+            undo(),
+            trash(),
+            restore_course(),
+            restore_assessment(),
+            theme(),
+            register(),
         ],
         ..Default::default()
     };
@@ -41,48 +115,201 @@ async fn main() {
         .options(options)
         .token(env::var("DISCORD_TOKEN").expect("Expected a token in the environment"))
         .intents(serenity::GatewayIntents::non_privileged())
-        .setup(|ctx, _ready, framework| {
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data {})
+                let commands = &framework.options().commands;
+                let registration = &config::get().registration;
+                match registration.scope {
+                    RegistrationScope::Global => {
+                        poise::builtins::register_globally(ctx, commands).await?;
+                        tracing::info!(commands = commands.len(), "registered commands globally");
+                    }
+                    // Development mode, guild commands show up instantly
+                    RegistrationScope::Guilds => {
+                        for guild_id in &registration.guild_ids {
+                            poise::builtins::register_in_guild(
+                                ctx,
+                                commands,
+                                serenity::GuildId(*guild_id),
+                            )
+                            .await?;
+                            tracing::info!(
+                                commands = commands.len(),
+                                guild = guild_id,
+                                "registered commands in guild"
+                            );
+                        }
+                    }
+                }
+                // Reminders need the HTTP client, so they start once the bot is connected
+                tokio::spawn(reminder_task(ctx.http.clone(), storage.clone()));
+
+                Ok(Data { storage })
             })
         });
     framework.run().await.unwrap();
 }
 
+/// Replies ephemerally to failed commands, the error id is the interaction id and is logged with the details
+async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    match error {
+        poise::FrameworkError::Command { error, ctx }
+        | poise::FrameworkError::CommandCheckFailed {
+            error: Some(error),
+            ctx,
+        } => {
+            logging::finish_command(ctx, Some(&error)).await;
+
+            let error_id = ctx.id();
+            let response = error.user_message(error_id);
+            if let Err(e) = ctx.send(|m| m.content(response).ephemeral(true)).await {
+                tracing::warn!(error_id, error = %e, "failed to report error");
+            }
+        }
+        error => {
+            if let Err(e) = poise::builtins::on_error(error).await {
+                tracing::error!(error = %e, "error while handling error");
+            }
+        }
+    }
+}
+
+async fn purge_trash_task(storage: Arc<dyn Storage>) {
+    let minutes = config::get().scheduler.trash_purge_interval_minutes;
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(minutes * 60));
+    loop {
+        interval.tick().await;
+
+        let purged = storage
+            .purge_trash(config::get().scheduler.trash_retention_days)
+            .await;
+        match purged {
+            Ok(0) => {}
+            Ok(rows) => tracing::info!(rows, "purged trash"),
+            Err(error) => tracing::error!(%error, "failed to purge trash"),
+        }
+    }
+}
+
+async fn reminder_task(http: Arc<serenity::Http>, storage: Arc<dyn Storage>) {
+    let minutes = config::get().scheduler.reminder_interval_minutes;
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(minutes * 60));
+    loop {
+        interval.tick().await;
+
+        match reminders::send_reminders(&http, storage.as_ref()).await {
+            Ok(0) => {}
+            Ok(messages) => tracing::info!(messages, "sent reminders"),
+            Err(error) => tracing::error!(%error, "failed to send reminders"),
+        }
+    }
+}
+
 async fn event_handler(
     ctx: &serenity::Context,
     event: &Event<'_>,
     _framework: poise::FrameworkContext<'_, Data, Error>,
-    _data: &Data,
+    data: &Data,
 ) -> Result<(), Error> {
-    match event {
-        Event::InteractionCreate { interaction, .. } => {
-            if let serenity::model::application::interaction::Interaction::MessageComponent(
-                component,
-            ) = interaction
-            {
-                let msg = component.message.clone();
-
-                let command = match msg.interaction {
-                    Some(interaction) => interaction.name,
-                    None => "".to_string(),
-                };
-
-                println!("Command: {}", command);
-
-                match command.as_str() {
-                    "list_courses" => {
-                        interaction_handlers::list_courses_handler(ctx, interaction).await?;
-                    }
-                    "list_assessments" => {
-                        interaction_handlers::list_assessments_handler(ctx, interaction).await?;
-                    }
-                    _ => {}
-                }
+    if let Event::InteractionCreate { interaction, .. } = event {
+        if let serenity::model::application::interaction::Interaction::MessageComponent(component) =
+            interaction
+        {
+            let command = match &component.message.interaction {
+                Some(interaction) => interaction.name.clone(),
+                None => "".to_string(),
+            };
+
+            let span = tracing::info_span!(
+                "interaction",
+                command = %command,
+                custom_id = %component.data.custom_id,
+                guild = component.guild_id.map_or(0, |guild_id| guild_id.0),
+                user = component.user.id.0,
+                interaction = component.id.0,
+            );
+            let started = Instant::now();
+
+            let result = dispatch_component(ctx, interaction, &command, data.storage.as_ref())
+                .instrument(span.clone())
+                .await;
+
+            let latency_ms = started.elapsed().as_millis() as u64;
+            match &result {
+                Ok(()) => span.in_scope(|| {
+                    tracing::info!(latency_ms, outcome = "ok", "interaction finished")
+                }),
+                Err(error) => span.in_scope(|| {
+                    tracing::error!(
+                        latency_ms,
+                        outcome = "error",
+                        error_id = component.id.0,
+                        %error,
+                        "interaction failed"
+                    )
+                }),
+            }
+
+            if let Err(error) = result {
+                interaction_handlers::report_error(ctx, component, &error)
+                    .instrument(span)
+                    .await;
+            }
+        }
+
+        // The only modal is the course edit form of `/course`
+        if let serenity::model::application::interaction::Interaction::ModalSubmit(modal) =
+            interaction
+        {
+            let span = tracing::info_span!(
+                "modal",
+                custom_id = %modal.data.custom_id,
+                guild = modal.guild_id.map_or(0, |guild_id| guild_id.0),
+                user = modal.user.id.0,
+                interaction = modal.id.0,
+            );
+
+            let result =
+                interaction_handlers::edit_course_modal_handler(ctx, modal, data.storage.as_ref())
+                    .instrument(span.clone())
+                    .await;
+
+            if let Err(error) = result {
+                span.in_scope(
+                    || tracing::error!(error_id = modal.id.0, %error, "modal submission failed"),
+                );
+                interaction_handlers::report_modal_error(ctx, modal, &error)
+                    .instrument(span)
+                    .await;
             }
         }
-        _ => {}
     }
     Ok(())
 }
+
+// Components are routed by the slash command that sent the message they are attached to
+async fn dispatch_component(
+    ctx: &serenity::Context,
+    interaction: &serenity::model::application::interaction::Interaction,
+    command: &str,
+    storage: &dyn Storage,
+) -> Result<(), Error> {
+    match command {
+        "list_courses" => {
+            interaction_handlers::list_courses_handler(ctx, interaction, storage).await
+        }
+        // Search results open a course's assessments, which then page like `list_assessments`
+        "list_assessments" | "search" => {
+            interaction_handlers::list_assessments_handler(ctx, interaction, storage).await
+        }
+        "calendar" => interaction_handlers::calendar_handler(ctx, interaction, storage).await,
+        "course" => interaction_handlers::course_handler(ctx, interaction, storage).await,
+        "enroll" | "unenroll" => {
+            interaction_handlers::enrollment_handler(ctx, interaction, storage).await
+        }
+        "remove_course" => {
+            interaction_handlers::confirmation_handler(ctx, interaction, storage).await
+        }
+        _ => Ok(()),
+    }
+}
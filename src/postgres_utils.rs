@@ -0,0 +1,2340 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+
+use crate::audit::{self, Actor, AuditEntry, UndoRows};
+use crate::database_utils::{
+    self, Assessment, ClonedCourse, Cohort, CohortDates, CohortMember, CohortSnapshot, Course,
+    CourseEditor, CourseRole, CourseSnapshot, SearchHit, Session, Subscription, Term,
+};
+use crate::error::Error;
+use crate::storage::Storage;
+use crate::utils;
+
+// Matches SQLite's CURRENT_TIMESTAMP so trash dates compare and display the same
+const NOW: &str = "to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')";
+
+/// Connects and brings the schema up to date with `migrations/postgres`
+pub async fn establish_connection(url: &str) -> Result<PgPool, Error> {
+    let pool = PgPool::connect(url).await?;
+    sqlx::migrate!("migrations/postgres").run(&pool).await?;
+    Ok(pool)
+}
+
+async fn record<T: Serialize>(
+    connection: &mut PgConnection,
+    actor: &Actor,
+    action: &str,
+    entity: &str,
+    entity_id: i64,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), Error> {
+    let to_json = |value: Option<&T>| value.and_then(|value| serde_json::to_string(value).ok());
+
+    record_json(
+        connection,
+        actor,
+        action,
+        entity,
+        entity_id,
+        to_json(before),
+        to_json(after),
+    )
+    .await
+}
+
+async fn record_json(
+    connection: &mut PgConnection,
+    actor: &Actor,
+    action: &str,
+    entity: &str,
+    entity_id: i64,
+    before: Option<String>,
+    after: Option<String>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (guild_id, actor_id, action, entity, entity_id, before, after)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(actor.guild_id)
+    .bind(actor.user_id)
+    .bind(action)
+    .bind(entity)
+    .bind(entity_id)
+    .bind(before)
+    .bind(after)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn update_course_row(connection: &mut PgConnection, course: &Course) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE courses SET name = $1, code = $2, semester = $3, year = $4, credit = $5, fk_term_id = $6
+        WHERE id = $7
+        "#,
+    )
+    .bind(&course.name)
+    .bind(&course.code)
+    .bind(course.semester)
+    .bind(course.year)
+    .bind(course.credit)
+    .bind(course.fk_term_id)
+    .bind(course.id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves the course and its assessments to the trash, see `database_utils::remove_course_rows`
+async fn remove_course_rows(
+    connection: &mut PgConnection,
+    id: i64,
+) -> Result<Option<CourseSnapshot>, Error> {
+    let course = sqlx::query_as::<_, Course>(&format!(
+        r#"
+        UPDATE courses SET deleted_at = {}
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING *
+        "#,
+        NOW
+    ))
+    .bind(id)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    let course = match course {
+        Some(course) => course,
+        None => return Ok(None),
+    };
+
+    // Assessments share the course's deleted_at so restoring the course brings them back
+    let assessments = sqlx::query_as::<_, Assessment>(
+        r#"
+        UPDATE assessments SET deleted_at = $2
+        WHERE fk_course_id = $1 AND deleted_at IS NULL
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&course.deleted_at)
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let sessions = sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE fk_course_id = $1")
+        .bind(id)
+        .fetch_all(&mut *connection)
+        .await?;
+
+    let editors =
+        sqlx::query_as::<_, CourseEditor>("SELECT * FROM course_editors WHERE fk_course_id = $1")
+            .bind(id)
+            .fetch_all(&mut *connection)
+            .await?;
+
+    Ok(Some(CourseSnapshot {
+        course,
+        assessments,
+        sessions,
+        editors,
+    }))
+}
+
+async fn restore_course_rows(
+    connection: &mut PgConnection,
+    snapshot: &CourseSnapshot,
+) -> Result<(), Error> {
+    let course = &snapshot.course;
+    sqlx::query(
+        r#"
+        INSERT INTO courses (id, name, code, semester, year, credit, fk_term_id, guild_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (id) DO UPDATE SET deleted_at = NULL
+        "#,
+    )
+    .bind(course.id)
+    .bind(&course.name)
+    .bind(&course.code)
+    .bind(course.semester)
+    .bind(course.year)
+    .bind(course.credit)
+    .bind(course.fk_term_id)
+    .bind(course.guild_id)
+    .execute(&mut *connection)
+    .await?;
+
+    for assessment in &snapshot.assessments {
+        restore_assessment_row(&mut *connection, assessment).await?;
+    }
+    for session in &snapshot.sessions {
+        restore_session_row(&mut *connection, session).await?;
+    }
+    for editor in &snapshot.editors {
+        restore_course_editor_row(&mut *connection, editor).await?;
+    }
+
+    Ok(())
+}
+
+async fn remove_assessment_row(
+    connection: &mut PgConnection,
+    id: i64,
+) -> Result<Option<Assessment>, Error> {
+    let assessment = sqlx::query_as::<_, Assessment>(&format!(
+        r#"
+        UPDATE assessments SET deleted_at = {}
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING *
+        "#,
+        NOW
+    ))
+    .bind(id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(assessment)
+}
+
+async fn restore_assessment_row(
+    connection: &mut PgConnection,
+    assessment: &Assessment,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO assessments (id, name, weight, take1, retake1, retake2, fk_course_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (id) DO UPDATE SET deleted_at = NULL
+        "#,
+    )
+    .bind(assessment.id)
+    .bind(&assessment.name)
+    .bind(assessment.weight)
+    .bind(&assessment.take1)
+    .bind(&assessment.retake1)
+    .bind(&assessment.retake2)
+    .bind(assessment.fk_course_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn remove_session_row(
+    connection: &mut PgConnection,
+    id: i64,
+) -> Result<Option<Session>, Error> {
+    let session = sqlx::query_as::<_, Session>("DELETE FROM sessions WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_optional(connection)
+        .await?;
+
+    Ok(session)
+}
+
+async fn restore_session_row(
+    connection: &mut PgConnection,
+    session: &Session,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (id, weekday, start_time, end_time, location, fk_course_id)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(session.id)
+    .bind(session.weekday)
+    .bind(&session.start_time)
+    .bind(&session.end_time)
+    .bind(&session.location)
+    .bind(session.fk_course_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn remove_course_editor_row(
+    connection: &mut PgConnection,
+    id: i64,
+) -> Result<Option<CourseEditor>, Error> {
+    let editor =
+        sqlx::query_as::<_, CourseEditor>("DELETE FROM course_editors WHERE id = $1 RETURNING *")
+            .bind(id)
+            .fetch_optional(connection)
+            .await?;
+
+    Ok(editor)
+}
+
+async fn restore_course_editor_row(
+    connection: &mut PgConnection,
+    editor: &CourseEditor,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO course_editors (id, guild_id, role_id, user_id, fk_course_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(editor.id)
+    .bind(editor.guild_id)
+    .bind(editor.role_id)
+    .bind(editor.user_id)
+    .bind(editor.fk_course_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn remove_course_role_row(
+    connection: &mut PgConnection,
+    guild_id: i64,
+    fk_course_id: i64,
+) -> Result<Option<CourseRole>, Error> {
+    let role = sqlx::query_as::<_, CourseRole>(
+        "DELETE FROM course_roles WHERE guild_id = $1 AND fk_course_id = $2 RETURNING *",
+    )
+    .bind(guild_id)
+    .bind(fk_course_id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(role)
+}
+
+async fn restore_course_role_row(
+    connection: &mut PgConnection,
+    role: &CourseRole,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO course_roles (guild_id, fk_course_id, role_id, channel_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id, fk_course_id)
+        DO UPDATE SET role_id = excluded.role_id, channel_id = excluded.channel_id
+        "#,
+    )
+    .bind(role.guild_id)
+    .bind(role.fk_course_id)
+    .bind(role.role_id)
+    .bind(role.channel_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn remove_subscription_row(
+    connection: &mut PgConnection,
+    user_id: i64,
+    fk_course_id: i64,
+) -> Result<Option<Subscription>, Error> {
+    let subscription = sqlx::query_as::<_, Subscription>(
+        "DELETE FROM course_subscriptions WHERE user_id = $1 AND fk_course_id = $2 RETURNING *",
+    )
+    .bind(user_id)
+    .bind(fk_course_id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(subscription)
+}
+
+/// Returns whether the user was not enrolled yet
+async fn restore_subscription_row(
+    connection: &mut PgConnection,
+    subscription: &Subscription,
+) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO course_subscriptions (user_id, fk_course_id)
+        SELECT $1, $2 WHERE EXISTS (SELECT 1 FROM courses WHERE id = $2)
+        ON CONFLICT (user_id, fk_course_id) DO NOTHING
+        "#,
+    )
+    .bind(subscription.user_id)
+    .bind(subscription.fk_course_id)
+    .execute(connection)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn remove_cohort_rows(
+    connection: &mut PgConnection,
+    id: i64,
+) -> Result<Option<CohortSnapshot>, Error> {
+    let dates = sqlx::query_as::<_, CohortDates>(
+        "DELETE FROM cohort_dates WHERE fk_cohort_id = $1 RETURNING *",
+    )
+    .bind(id)
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let members = sqlx::query_as::<_, CohortMember>(
+        "DELETE FROM cohort_members WHERE fk_cohort_id = $1 RETURNING *",
+    )
+    .bind(id)
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let cohort = sqlx::query_as::<_, Cohort>("DELETE FROM cohorts WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    Ok(cohort.map(|cohort| CohortSnapshot {
+        cohort,
+        members,
+        dates,
+    }))
+}
+
+async fn restore_cohort_rows(
+    connection: &mut PgConnection,
+    snapshot: &CohortSnapshot,
+) -> Result<(), Error> {
+    let cohort = &snapshot.cohort;
+    sqlx::query(
+        "INSERT INTO cohorts (id, guild_id, name) VALUES ($1, $2, $3) ON CONFLICT (id) DO NOTHING",
+    )
+    .bind(cohort.id)
+    .bind(cohort.guild_id)
+    .bind(&cohort.name)
+    .execute(&mut *connection)
+    .await?;
+
+    for member in &snapshot.members {
+        sqlx::query(
+            r#"
+            INSERT INTO cohort_members (guild_id, user_id, fk_cohort_id) VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(member.guild_id)
+        .bind(member.user_id)
+        .bind(member.fk_cohort_id)
+        .execute(&mut *connection)
+        .await?;
+    }
+    for dates in &snapshot.dates {
+        restore_cohort_dates_row(&mut *connection, dates).await?;
+    }
+
+    Ok(())
+}
+
+async fn remove_cohort_dates_row(
+    connection: &mut PgConnection,
+    fk_assessment_id: i64,
+    fk_cohort_id: i64,
+) -> Result<Option<CohortDates>, Error> {
+    let dates = sqlx::query_as::<_, CohortDates>(
+        "DELETE FROM cohort_dates WHERE fk_assessment_id = $1 AND fk_cohort_id = $2 RETURNING *",
+    )
+    .bind(fk_assessment_id)
+    .bind(fk_cohort_id)
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(dates)
+}
+
+async fn restore_cohort_dates_row(
+    connection: &mut PgConnection,
+    dates: &CohortDates,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO cohort_dates (fk_assessment_id, fk_cohort_id, take1, retake1, retake2)
+        SELECT $1, $2, $3, $4, $5 WHERE EXISTS (SELECT 1 FROM assessments WHERE id = $1)
+        ON CONFLICT (fk_assessment_id, fk_cohort_id)
+        DO UPDATE SET take1 = excluded.take1, retake1 = excluded.retake1, retake2 = excluded.retake2
+        "#,
+    )
+    .bind(dates.fk_assessment_id)
+    .bind(dates.fk_cohort_id)
+    .bind(&dates.take1)
+    .bind(&dates.retake1)
+    .bind(&dates.retake2)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn update_term_row(connection: &mut PgConnection, term: &Term) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE terms SET name = $1, start_date = $2, end_date = $3, active = $4, archived = $5
+        WHERE id = $6
+        "#,
+    )
+    .bind(&term.name)
+    .bind(&term.start_date)
+    .bind(&term.end_date)
+    .bind(term.active)
+    .bind(term.archived)
+    .bind(term.id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn restore_term_row(connection: &mut PgConnection, term: &Term) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO terms (id, name, start_date, end_date, active, archived, guild_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(term.id)
+    .bind(&term.name)
+    .bind(&term.start_date)
+    .bind(&term.end_date)
+    .bind(term.active)
+    .bind(term.archived)
+    .bind(term.guild_id)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the term, its courses are left without a term
+async fn remove_term_row(connection: &mut PgConnection, id: i64) -> Result<Option<Term>, Error> {
+    sqlx::query("UPDATE courses SET fk_term_id = NULL WHERE fk_term_id = $1")
+        .bind(id)
+        .execute(&mut *connection)
+        .await?;
+
+    let term = sqlx::query_as::<_, Term>("DELETE FROM terms WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_optional(connection)
+        .await?;
+
+    Ok(term)
+}
+
+async fn deactivate_terms(connection: &mut PgConnection, actor: &Actor) -> Result<(), Error> {
+    let active =
+        sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE guild_id = $1 AND active FOR UPDATE")
+            .bind(actor.guild_id)
+            .fetch_all(&mut *connection)
+            .await?;
+
+    for term in active {
+        let updated = Term {
+            active: false,
+            ..term.clone()
+        };
+        update_term_row(&mut *connection, &updated).await?;
+        record(
+            &mut *connection,
+            actor,
+            audit::UPDATE,
+            audit::TERM,
+            term.id,
+            Some(&term),
+            Some(&updated),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Sets the term's flags, `None` if the actor's guild has no such term
+async fn set_term_state(
+    pool: &PgPool,
+    id: i64,
+    active: bool,
+    actor: &Actor,
+) -> Result<Option<Term>, Error> {
+    let mut transaction = pool.begin().await?;
+
+    let term = match sqlx::query_as::<_, Term>(
+        "SELECT * FROM terms WHERE id = $1 AND guild_id = $2 FOR UPDATE",
+    )
+    .bind(id)
+    .bind(actor.guild_id)
+    .fetch_optional(&mut *transaction)
+    .await?
+    {
+        Some(term) => term,
+        None => return Ok(None),
+    };
+
+    if active {
+        deactivate_terms(&mut transaction, actor).await?;
+    }
+    let updated = Term {
+        active,
+        archived: !active,
+        ..term.clone()
+    };
+    update_term_row(&mut transaction, &updated).await?;
+    record(
+        &mut transaction,
+        actor,
+        audit::UPDATE,
+        audit::TERM,
+        id,
+        Some(&term),
+        Some(&updated),
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(updated))
+}
+
+/// Whether the course, live or in the trash, belongs to the guild
+async fn is_guild_course(
+    connection: &mut PgConnection,
+    id: i64,
+    guild_id: i64,
+) -> Result<bool, Error> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM courses WHERE id = $1 AND guild_id = $2)",
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_one(connection)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Whether the assessment, live or in the trash, belongs to a course of the guild
+async fn is_guild_assessment(
+    connection: &mut PgConnection,
+    id: i64,
+    guild_id: i64,
+) -> Result<bool, Error> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM assessments
+            JOIN courses ON courses.id = assessments.fk_course_id
+            WHERE assessments.id = $1 AND courses.guild_id = $2
+        )
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_one(connection)
+    .await?;
+
+    Ok(exists)
+}
+
+#[async_trait]
+impl UndoRows for PgConnection {
+    async fn record(
+        &mut self,
+        actor: &Actor,
+        action: &str,
+        entity: &str,
+        entity_id: i64,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> Result<(), Error> {
+        record_json(self, actor, action, entity, entity_id, before, after).await
+    }
+
+    async fn previous_entry(
+        &mut self,
+        guild_id: i64,
+        actor_id: i64,
+        id: i64,
+    ) -> Result<Option<AuditEntry>, Error> {
+        let entry = sqlx::query_as::<_, AuditEntry>(
+            r#"
+            SELECT * FROM audit_log
+            WHERE guild_id = $1 AND actor_id = $2 AND id < $3
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(guild_id)
+        .bind(actor_id)
+        .bind(id)
+        .fetch_optional(self)
+        .await?;
+
+        Ok(entry)
+    }
+
+    async fn live_courses(&mut self, guild_id: i64) -> Result<Vec<Course>, Error> {
+        let courses = sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE guild_id = $1 AND deleted_at IS NULL",
+        )
+        .bind(guild_id)
+        .fetch_all(self)
+        .await?;
+
+        Ok(courses)
+    }
+
+    async fn remove_course(&mut self, id: i64) -> Result<Option<CourseSnapshot>, Error> {
+        remove_course_rows(self, id).await
+    }
+
+    async fn update_course(&mut self, course: &Course) -> Result<(), Error> {
+        update_course_row(self, course).await
+    }
+
+    async fn restore_course(&mut self, snapshot: &CourseSnapshot) -> Result<(), Error> {
+        restore_course_rows(self, snapshot).await
+    }
+
+    async fn remove_assessment(&mut self, id: i64) -> Result<Option<Assessment>, Error> {
+        remove_assessment_row(self, id).await
+    }
+
+    async fn restore_assessment(&mut self, assessment: &Assessment) -> Result<(), Error> {
+        restore_assessment_row(self, assessment).await
+    }
+
+    async fn remove_session(&mut self, id: i64) -> Result<Option<Session>, Error> {
+        remove_session_row(self, id).await
+    }
+
+    async fn restore_session(&mut self, session: &Session) -> Result<(), Error> {
+        restore_session_row(self, session).await
+    }
+
+    async fn remove_course_editor(&mut self, id: i64) -> Result<Option<CourseEditor>, Error> {
+        remove_course_editor_row(self, id).await
+    }
+
+    async fn restore_course_editor(&mut self, editor: &CourseEditor) -> Result<(), Error> {
+        restore_course_editor_row(self, editor).await
+    }
+
+    async fn remove_course_role(
+        &mut self,
+        guild_id: i64,
+        fk_course_id: i64,
+    ) -> Result<Option<CourseRole>, Error> {
+        remove_course_role_row(self, guild_id, fk_course_id).await
+    }
+
+    async fn restore_course_role(&mut self, role: &CourseRole) -> Result<(), Error> {
+        restore_course_role_row(self, role).await
+    }
+
+    async fn remove_subscription(
+        &mut self,
+        user_id: i64,
+        fk_course_id: i64,
+    ) -> Result<Option<Subscription>, Error> {
+        remove_subscription_row(self, user_id, fk_course_id).await
+    }
+
+    async fn restore_subscription(&mut self, subscription: &Subscription) -> Result<(), Error> {
+        restore_subscription_row(self, subscription).await?;
+        Ok(())
+    }
+
+    async fn remove_cohort(&mut self, id: i64) -> Result<Option<CohortSnapshot>, Error> {
+        remove_cohort_rows(self, id).await
+    }
+
+    async fn restore_cohort(&mut self, snapshot: &CohortSnapshot) -> Result<(), Error> {
+        restore_cohort_rows(self, snapshot).await
+    }
+
+    async fn remove_cohort_dates(
+        &mut self,
+        fk_assessment_id: i64,
+        fk_cohort_id: i64,
+    ) -> Result<Option<CohortDates>, Error> {
+        remove_cohort_dates_row(self, fk_assessment_id, fk_cohort_id).await
+    }
+
+    async fn restore_cohort_dates(&mut self, dates: &CohortDates) -> Result<(), Error> {
+        restore_cohort_dates_row(self, dates).await
+    }
+
+    async fn remove_term(&mut self, id: i64) -> Result<Option<Term>, Error> {
+        remove_term_row(self, id).await
+    }
+
+    async fn update_term(&mut self, term: &Term) -> Result<(), Error> {
+        update_term_row(self, term).await
+    }
+
+    async fn restore_term(&mut self, term: &Term) -> Result<(), Error> {
+        restore_term_row(self, term).await
+    }
+}
+
+/// Same semantics as the SQLite functions in `database_utils`, including the audit entries
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresStorage { pool }
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn all_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error> {
+        let courses = sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE guild_id = $1 AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(courses)
+    }
+
+    async fn term_courses(&self, guild_id: i64, fk_term_id: i64) -> Result<Vec<Course>, Error> {
+        let courses = sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE guild_id = $1 AND fk_term_id = $2 AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(guild_id)
+        .bind(fk_term_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(courses)
+    }
+
+    async fn course(&self, guild_id: i64, id: i64) -> Result<Option<Course>, Error> {
+        let course = sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE id = $1 AND guild_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(guild_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(course)
+    }
+
+    async fn insert_course(&self, course: &Course, actor: &Actor) -> Result<u64, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let inserted = sqlx::query_as::<_, Course>(
+            r#"
+            INSERT INTO courses (name, code, semester, year, credit, fk_term_id, guild_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(&course.name)
+        .bind(&course.code)
+        .bind(course.semester)
+        .bind(course.year)
+        .bind(course.credit)
+        .bind(course.fk_term_id)
+        .bind(actor.guild_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::COURSE,
+            inserted.id,
+            None,
+            Some(&inserted),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(1)
+    }
+
+    async fn update_course(&self, course: &Course, actor: &Actor) -> Result<Option<Course>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let before = match sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE id = $1 AND guild_id = $2 AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(course.id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        {
+            Some(before) => before,
+            None => return Ok(None),
+        };
+
+        let updated = sqlx::query_as::<_, Course>(
+            r#"
+            UPDATE courses SET name = $1, code = $2, semester = $3, year = $4, credit = $5
+            WHERE id = $6
+            RETURNING *
+            "#,
+        )
+        .bind(&course.name)
+        .bind(&course.code)
+        .bind(course.semester)
+        .bind(course.year)
+        .bind(course.credit)
+        .bind(course.id)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        record(
+            &mut transaction,
+            actor,
+            audit::UPDATE,
+            audit::COURSE,
+            updated.id,
+            Some(&before),
+            Some(&updated),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(updated))
+    }
+
+    async fn delete_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        if !is_guild_course(&mut transaction, id, actor.guild_id).await? {
+            return Ok(None);
+        }
+        let snapshot = match remove_course_rows(&mut transaction, id).await? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+        record(
+            &mut transaction,
+            actor,
+            audit::DELETE,
+            audit::COURSE,
+            id,
+            Some(&snapshot),
+            None,
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(snapshot.course))
+    }
+
+    async fn clone_courses(
+        &self,
+        from: (i64, i64),
+        to: (i64, i64),
+        fk_term_id: Option<i64>,
+        shift_days: Option<i64>,
+        actor: &Actor,
+    ) -> Result<Vec<ClonedCourse>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let courses = sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE guild_id = $1 AND semester = $2 AND year = $3 AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(actor.guild_id)
+        .bind(from.0)
+        .bind(from.1)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        let shift = |date: &str| match shift_days {
+            Some(days) => utils::shift_date(date, days),
+            None => String::new(),
+        };
+
+        let mut cloned = Vec::new();
+        for course in courses {
+            let exists = sqlx::query_scalar::<_, bool>(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM courses
+                    WHERE guild_id = $1 AND lower(code) = lower($2) AND semester = $3 AND year = $4
+                        AND deleted_at IS NULL
+                )
+                "#,
+            )
+            .bind(actor.guild_id)
+            .bind(&course.code)
+            .bind(to.0)
+            .bind(to.1)
+            .fetch_one(&mut *transaction)
+            .await?;
+            if exists {
+                cloned.push(ClonedCourse {
+                    old_id: course.id,
+                    new_id: None,
+                    code: course.code,
+                    assessments: 0,
+                    sessions: 0,
+                });
+                continue;
+            }
+
+            let new_course = sqlx::query_as::<_, Course>(
+                r#"
+                INSERT INTO courses (name, code, semester, year, credit, fk_term_id, guild_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING *
+                "#,
+            )
+            .bind(&course.name)
+            .bind(&course.code)
+            .bind(to.0)
+            .bind(to.1)
+            .bind(course.credit)
+            .bind(fk_term_id)
+            .bind(course.guild_id)
+            .fetch_one(&mut *transaction)
+            .await?;
+            record(
+                &mut transaction,
+                actor,
+                audit::INSERT,
+                audit::COURSE,
+                new_course.id,
+                None,
+                Some(&new_course),
+            )
+            .await?;
+
+            let assessments = sqlx::query_as::<_, Assessment>(
+                "SELECT * FROM assessments WHERE fk_course_id = $1 AND deleted_at IS NULL ORDER BY id",
+            )
+            .bind(course.id)
+            .fetch_all(&mut *transaction)
+            .await?;
+
+            for assessment in &assessments {
+                let new_assessment = sqlx::query_as::<_, Assessment>(
+                    r#"
+                    INSERT INTO assessments (name, weight, take1, retake1, retake2, fk_course_id)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    RETURNING *
+                    "#,
+                )
+                .bind(&assessment.name)
+                .bind(assessment.weight)
+                .bind(shift(&assessment.take1))
+                .bind(shift(&assessment.retake1))
+                .bind(shift(&assessment.retake2))
+                .bind(new_course.id)
+                .fetch_one(&mut *transaction)
+                .await?;
+                record(
+                    &mut transaction,
+                    actor,
+                    audit::INSERT,
+                    audit::ASSESSMENT,
+                    new_assessment.id,
+                    None,
+                    Some(&new_assessment),
+                )
+                .await?;
+            }
+
+            let sessions = sqlx::query_as::<_, Session>(
+                "SELECT * FROM sessions WHERE fk_course_id = $1 ORDER BY id",
+            )
+            .bind(course.id)
+            .fetch_all(&mut *transaction)
+            .await?;
+
+            for session in &sessions {
+                let new_session = sqlx::query_as::<_, Session>(
+                    r#"
+                    INSERT INTO sessions (weekday, start_time, end_time, location, fk_course_id)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING *
+                    "#,
+                )
+                .bind(session.weekday)
+                .bind(&session.start_time)
+                .bind(&session.end_time)
+                .bind(&session.location)
+                .bind(new_course.id)
+                .fetch_one(&mut *transaction)
+                .await?;
+                record(
+                    &mut transaction,
+                    actor,
+                    audit::INSERT,
+                    audit::SESSION,
+                    new_session.id,
+                    None,
+                    Some(&new_session),
+                )
+                .await?;
+            }
+
+            cloned.push(ClonedCourse {
+                old_id: course.id,
+                new_id: Some(new_course.id),
+                code: course.code,
+                assessments: assessments.len(),
+                sessions: sessions.len(),
+            });
+        }
+
+        transaction.commit().await?;
+
+        Ok(cloned)
+    }
+
+    async fn all_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error> {
+        let assessments = sqlx::query_as::<_, Assessment>(
+            r#"
+            SELECT assessments.* FROM assessments
+            JOIN courses ON courses.id = assessments.fk_course_id
+            WHERE courses.guild_id = $1 AND assessments.deleted_at IS NULL
+                AND courses.deleted_at IS NULL
+            ORDER BY assessments.id
+            "#,
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(assessments)
+    }
+
+    async fn assessment(&self, guild_id: i64, id: i64) -> Result<Option<Assessment>, Error> {
+        let assessment = sqlx::query_as::<_, Assessment>(
+            r#"
+            SELECT assessments.* FROM assessments
+            JOIN courses ON courses.id = assessments.fk_course_id
+            WHERE assessments.id = $1 AND courses.guild_id = $2 AND assessments.deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(guild_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(assessment)
+    }
+
+    async fn course_assessments(&self, fk_course_id: i64) -> Result<Vec<Assessment>, Error> {
+        let assessments = sqlx::query_as::<_, Assessment>(
+            "SELECT * FROM assessments WHERE fk_course_id = $1 AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(fk_course_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(assessments)
+    }
+
+    async fn insert_assessment(
+        &self,
+        assessment: &Assessment,
+        actor: &Actor,
+    ) -> Result<u64, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Nothing is inserted unless the course is a live course of the actor's guild
+        let inserted = match sqlx::query_as::<_, Assessment>(
+            r#"
+            INSERT INTO assessments (name, weight, take1, retake1, retake2, fk_course_id)
+            SELECT $1, $2, $3, $4, $5, id FROM courses
+            WHERE id = $6 AND guild_id = $7 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(&assessment.name)
+        .bind(assessment.weight)
+        .bind(&assessment.take1)
+        .bind(&assessment.retake1)
+        .bind(&assessment.retake2)
+        .bind(assessment.fk_course_id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        {
+            Some(inserted) => inserted,
+            None => return Ok(0),
+        };
+
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::ASSESSMENT,
+            inserted.id,
+            None,
+            Some(&inserted),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(1)
+    }
+
+    async fn delete_assessment(&self, id: i64, actor: &Actor) -> Result<Option<Assessment>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        if !is_guild_assessment(&mut transaction, id, actor.guild_id).await? {
+            return Ok(None);
+        }
+        let assessment = remove_assessment_row(&mut transaction, id).await?;
+        if let Some(assessment) = &assessment {
+            record(
+                &mut transaction,
+                actor,
+                audit::DELETE,
+                audit::ASSESSMENT,
+                id,
+                Some(assessment),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(assessment)
+    }
+
+    async fn all_sessions(&self, guild_id: i64) -> Result<Vec<Session>, Error> {
+        // Sessions of courses in the trash are hidden along with the course
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT sessions.* FROM sessions
+            JOIN courses ON courses.id = sessions.fk_course_id
+            WHERE courses.guild_id = $1 AND courses.deleted_at IS NULL
+            ORDER BY sessions.id
+            "#,
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn session(&self, guild_id: i64, id: i64) -> Result<Option<Session>, Error> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT sessions.* FROM sessions
+            JOIN courses ON courses.id = sessions.fk_course_id
+            WHERE sessions.id = $1 AND courses.guild_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(guild_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn course_sessions(&self, fk_course_id: i64) -> Result<Vec<Session>, Error> {
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions WHERE fk_course_id = $1 ORDER BY weekday, start_time",
+        )
+        .bind(fk_course_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn insert_session(&self, session: &Session, actor: &Actor) -> Result<u64, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Nothing is inserted unless the course is live and belongs to the actor's guild
+        let inserted = match sqlx::query_as::<_, Session>(
+            r#"
+            INSERT INTO sessions (weekday, start_time, end_time, location, fk_course_id)
+            SELECT $1, $2, $3, $4, id FROM courses
+            WHERE id = $5 AND guild_id = $6 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(session.weekday)
+        .bind(&session.start_time)
+        .bind(&session.end_time)
+        .bind(&session.location)
+        .bind(session.fk_course_id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        {
+            Some(inserted) => inserted,
+            None => return Ok(0),
+        };
+
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::SESSION,
+            inserted.id,
+            None,
+            Some(&inserted),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(1)
+    }
+
+    async fn delete_session(&self, id: i64, actor: &Actor) -> Result<Option<Session>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let own = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM sessions
+                JOIN courses ON courses.id = sessions.fk_course_id
+                WHERE sessions.id = $1 AND courses.guild_id = $2
+            )
+            "#,
+        )
+        .bind(id)
+        .bind(actor.guild_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+        if !own {
+            return Ok(None);
+        }
+        let session = remove_session_row(&mut transaction, id).await?;
+        if let Some(session) = &session {
+            record(
+                &mut transaction,
+                actor,
+                audit::DELETE,
+                audit::SESSION,
+                id,
+                Some(session),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(session)
+    }
+
+    async fn deleted_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error> {
+        let courses = sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE guild_id = $1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(courses)
+    }
+
+    async fn deleted_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error> {
+        let assessments = sqlx::query_as::<_, Assessment>(
+            r#"
+            SELECT assessments.* FROM assessments
+            JOIN courses ON courses.id = assessments.fk_course_id
+            WHERE courses.guild_id = $1 AND assessments.deleted_at IS NOT NULL
+                AND courses.deleted_at IS NULL
+            ORDER BY assessments.deleted_at DESC
+            "#,
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(assessments)
+    }
+
+    async fn restore_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let course = match sqlx::query_as::<_, Course>(
+            "SELECT * FROM courses WHERE id = $1 AND guild_id = $2 AND deleted_at IS NOT NULL FOR UPDATE",
+        )
+        .bind(id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        {
+            Some(course) => course,
+            None => return Ok(None),
+        };
+
+        sqlx::query(
+            "UPDATE assessments SET deleted_at = NULL WHERE fk_course_id = $1 AND deleted_at = $2",
+        )
+        .bind(course.id)
+        .bind(&course.deleted_at)
+        .execute(&mut *transaction)
+        .await?;
+
+        let restored = sqlx::query_as::<_, Course>(
+            "UPDATE courses SET deleted_at = NULL WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .fetch_one(&mut *transaction)
+        .await?;
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::COURSE,
+            id,
+            None,
+            Some(&restored),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(restored))
+    }
+
+    async fn restore_assessment(
+        &self,
+        id: i64,
+        actor: &Actor,
+    ) -> Result<Option<Assessment>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        if !is_guild_assessment(&mut transaction, id, actor.guild_id).await? {
+            return Ok(None);
+        }
+        let assessment = sqlx::query_as::<_, Assessment>(
+            r#"
+            UPDATE assessments SET deleted_at = NULL
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        if let Some(assessment) = &assessment {
+            record(
+                &mut transaction,
+                actor,
+                audit::INSERT,
+                audit::ASSESSMENT,
+                id,
+                None,
+                Some(assessment),
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(assessment)
+    }
+
+    async fn purge_trash(&self, retention_days: i64) -> Result<u64, Error> {
+        let mut transaction = self.pool.begin().await?;
+        let cutoff = "to_char(now() AT TIME ZONE 'UTC' - make_interval(days => $1::int), 'YYYY-MM-DD HH24:MI:SS')";
+        let purged_courses = format!("SELECT id FROM courses WHERE deleted_at < {}", cutoff);
+
+        // Cohort dates of assessments purged on their own or along with their course
+        sqlx::query(&format!(
+            r#"
+            DELETE FROM cohort_dates WHERE fk_assessment_id IN (
+                SELECT id FROM assessments
+                WHERE deleted_at < {} OR fk_course_id IN ({})
+            )
+            "#,
+            cutoff, purged_courses
+        ))
+        .bind(retention_days)
+        .execute(&mut *transaction)
+        .await?;
+        for table in [
+            "assessments",
+            "sessions",
+            "course_editors",
+            "course_subscriptions",
+            "course_roles",
+        ] {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE fk_course_id IN ({})",
+                table, purged_courses
+            ))
+            .bind(retention_days)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        let courses = sqlx::query(&format!(
+            "DELETE FROM courses WHERE deleted_at < {}",
+            cutoff
+        ))
+        .bind(retention_days)
+        .execute(&mut *transaction)
+        .await?;
+
+        let assessments = sqlx::query(&format!(
+            "DELETE FROM assessments WHERE deleted_at < {}",
+            cutoff
+        ))
+        .bind(retention_days)
+        .execute(&mut *transaction)
+        .await?;
+
+        // Reminders are only looked up for upcoming dates
+        sqlx::query(
+            "DELETE FROM sent_reminders WHERE date < to_char(current_date - $1::int, 'YYYY-MM-DD')",
+        )
+        .bind(retention_days)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(courses.rows_affected() + assessments.rows_affected())
+    }
+
+    async fn is_subscribed(&self, user_id: i64, fk_course_id: i64) -> Result<bool, Error> {
+        let subscribed = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM course_subscriptions WHERE user_id = $1 AND fk_course_id = $2)",
+        )
+        .bind(user_id)
+        .bind(fk_course_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(subscribed)
+    }
+
+    async fn subscribed_courses(&self, guild_id: i64, user_id: i64) -> Result<Vec<Course>, Error> {
+        let courses = sqlx::query_as::<_, Course>(
+            r#"
+            SELECT courses.* FROM courses
+            JOIN course_subscriptions ON course_subscriptions.fk_course_id = courses.id
+            WHERE course_subscriptions.user_id = $1 AND courses.guild_id = $2
+                AND courses.deleted_at IS NULL
+            ORDER BY courses.id
+            "#,
+        )
+        .bind(user_id)
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(courses)
+    }
+
+    async fn set_subscribed(
+        &self,
+        fk_course_id: i64,
+        subscribed: bool,
+        actor: &Actor,
+    ) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let subscription = Subscription {
+            user_id: actor.user_id,
+            fk_course_id,
+        };
+        let (changed, action, before, after) = match subscribed {
+            true => (
+                restore_subscription_row(&mut transaction, &subscription).await?,
+                audit::INSERT,
+                None,
+                Some(&subscription),
+            ),
+            false => (
+                remove_subscription_row(&mut transaction, actor.user_id, fk_course_id)
+                    .await?
+                    .is_some(),
+                audit::DELETE,
+                Some(&subscription),
+                None,
+            ),
+        };
+        if changed {
+            record(
+                &mut transaction,
+                actor,
+                action,
+                audit::SUBSCRIPTION,
+                fk_course_id,
+                before,
+                after,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn all_terms(&self, guild_id: i64) -> Result<Vec<Term>, Error> {
+        let terms = sqlx::query_as::<_, Term>(
+            "SELECT * FROM terms WHERE guild_id = $1 ORDER BY start_date",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(terms)
+    }
+
+    async fn term(&self, guild_id: i64, id: i64) -> Result<Option<Term>, Error> {
+        let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE id = $1 AND guild_id = $2")
+            .bind(id)
+            .bind(guild_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(term)
+    }
+
+    async fn active_term(&self, guild_id: i64) -> Result<Option<Term>, Error> {
+        let term = sqlx::query_as::<_, Term>("SELECT * FROM terms WHERE guild_id = $1 AND active")
+            .bind(guild_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(term)
+    }
+
+    async fn insert_term(&self, term: &Term, actor: &Actor) -> Result<u64, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        if term.active {
+            deactivate_terms(&mut transaction, actor).await?;
+        }
+
+        let inserted = sqlx::query_as::<_, Term>(
+            r#"
+            INSERT INTO terms (name, start_date, end_date, active, archived, guild_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(&term.name)
+        .bind(&term.start_date)
+        .bind(&term.end_date)
+        .bind(term.active)
+        .bind(term.archived)
+        .bind(actor.guild_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::TERM,
+            inserted.id,
+            None,
+            Some(&inserted),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(1)
+    }
+
+    async fn activate_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error> {
+        set_term_state(&self.pool, id, true, actor).await
+    }
+
+    async fn archive_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error> {
+        set_term_state(&self.pool, id, false, actor).await
+    }
+
+    async fn course_editors(&self, guild_id: i64) -> Result<Vec<CourseEditor>, Error> {
+        let editors = sqlx::query_as::<_, CourseEditor>(
+            "SELECT * FROM course_editors WHERE guild_id = $1 ORDER BY fk_course_id, id",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(editors)
+    }
+
+    async fn insert_course_editor(
+        &self,
+        editor: &CourseEditor,
+        actor: &Actor,
+    ) -> Result<u64, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let inserted = sqlx::query_as::<_, CourseEditor>(
+            r#"
+            INSERT INTO course_editors (guild_id, role_id, user_id, fk_course_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(editor.guild_id)
+        .bind(editor.role_id)
+        .bind(editor.user_id)
+        .bind(editor.fk_course_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::COURSE_EDITOR,
+            inserted.id,
+            None,
+            Some(&inserted),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(1)
+    }
+
+    async fn delete_course_editor(
+        &self,
+        id: i64,
+        actor: &Actor,
+    ) -> Result<Option<CourseEditor>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let editor = sqlx::query_as::<_, CourseEditor>(
+            "DELETE FROM course_editors WHERE guild_id = $1 AND id = $2 RETURNING *",
+        )
+        .bind(actor.guild_id)
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+        if let Some(editor) = &editor {
+            record(
+                &mut transaction,
+                actor,
+                audit::DELETE,
+                audit::COURSE_EDITOR,
+                id,
+                Some(editor),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(editor)
+    }
+
+    async fn audit_entries(
+        &self,
+        guild_id: i64,
+        entity: Option<&str>,
+        entity_id: Option<i64>,
+        actor_id: Option<i64>,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        let entries = sqlx::query_as::<_, AuditEntry>(
+            r#"
+            SELECT * FROM audit_log
+            WHERE guild_id = $1
+                AND ($2::text IS NULL OR entity = $2)
+                AND ($3::bigint IS NULL OR entity_id = $3)
+                AND ($4::bigint IS NULL OR actor_id = $4)
+            ORDER BY id DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(guild_id)
+        .bind(entity)
+        .bind(entity_id)
+        .bind(actor_id)
+        .bind(audit::AUDIT_ENTRIES_SHOWN)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    async fn undo(&self, id: i64, actor: &Actor) -> Result<AuditEntry, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let entry = sqlx::query_as::<_, AuditEntry>(
+            "SELECT * FROM audit_log WHERE id = $1 AND guild_id = $2 FOR UPDATE",
+        )
+        .bind(id)
+        .bind(actor.guild_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+        let entry = audit::check_undoable(entry, id)?;
+
+        let connection: &mut PgConnection = &mut transaction;
+        audit::revert(connection, &entry, actor).await?;
+
+        sqlx::query("UPDATE audit_log SET reverted = TRUE WHERE id = $1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(entry)
+    }
+
+    async fn course_roles(&self, guild_id: i64) -> Result<Vec<CourseRole>, Error> {
+        let roles = sqlx::query_as::<_, CourseRole>(
+            "SELECT * FROM course_roles WHERE guild_id = $1 ORDER BY fk_course_id",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    async fn reminder_channels(&self) -> Result<Vec<CourseRole>, Error> {
+        let roles = sqlx::query_as::<_, CourseRole>(
+            "SELECT * FROM course_roles WHERE channel_id IS NOT NULL ORDER BY guild_id, fk_course_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    async fn set_course_role(&self, role: &CourseRole, actor: &Actor) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let before = sqlx::query_as::<_, CourseRole>(
+            "SELECT * FROM course_roles WHERE guild_id = $1 AND fk_course_id = $2 FOR UPDATE",
+        )
+        .bind(actor.guild_id)
+        .bind(role.fk_course_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        let role = CourseRole {
+            guild_id: actor.guild_id,
+            ..role.clone()
+        };
+        restore_course_role_row(&mut transaction, &role).await?;
+        let action = match before {
+            Some(_) => audit::UPDATE,
+            None => audit::INSERT,
+        };
+        record(
+            &mut transaction,
+            actor,
+            action,
+            audit::COURSE_ROLE,
+            role.fk_course_id,
+            before.as_ref(),
+            Some(&role),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_course_role(
+        &self,
+        fk_course_id: i64,
+        actor: &Actor,
+    ) -> Result<Option<CourseRole>, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let role = remove_course_role_row(&mut transaction, actor.guild_id, fk_course_id).await?;
+        if let Some(role) = &role {
+            record(
+                &mut transaction,
+                actor,
+                audit::DELETE,
+                audit::COURSE_ROLE,
+                fk_course_id,
+                Some(role),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(role)
+    }
+
+    async fn linked_subscriptions(&self, guild_id: i64) -> Result<Vec<(i64, i64)>, Error> {
+        let subscriptions = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT course_subscriptions.user_id, course_subscriptions.fk_course_id
+            FROM course_subscriptions
+            JOIN course_roles ON course_roles.fk_course_id = course_subscriptions.fk_course_id
+            JOIN courses ON courses.id = course_subscriptions.fk_course_id
+            WHERE course_roles.guild_id = $1 AND courses.deleted_at IS NULL
+            "#,
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    async fn is_reminder_sent(
+        &self,
+        guild_id: i64,
+        fk_assessment_id: i64,
+        date: &str,
+    ) -> Result<bool, Error> {
+        let sent = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM sent_reminders
+                WHERE guild_id = $1 AND fk_assessment_id = $2 AND date = $3
+            )
+            "#,
+        )
+        .bind(guild_id)
+        .bind(fk_assessment_id)
+        .bind(date)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(sent)
+    }
+
+    async fn record_reminder(
+        &self,
+        guild_id: i64,
+        fk_assessment_id: i64,
+        date: &str,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO sent_reminders (guild_id, fk_assessment_id, date) VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(guild_id)
+        .bind(fk_assessment_id)
+        .bind(date)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn cohorts(&self, guild_id: i64) -> Result<Vec<Cohort>, Error> {
+        let cohorts =
+            sqlx::query_as::<_, Cohort>("SELECT * FROM cohorts WHERE guild_id = $1 ORDER BY name")
+                .bind(guild_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(cohorts)
+    }
+
+    async fn find_cohort(&self, guild_id: i64, name: &str) -> Result<Option<Cohort>, Error> {
+        let cohort = sqlx::query_as::<_, Cohort>(
+            "SELECT * FROM cohorts WHERE guild_id = $1 AND lower(name) = lower($2)",
+        )
+        .bind(guild_id)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(cohort)
+    }
+
+    async fn insert_cohort(&self, name: &str, actor: &Actor) -> Result<Cohort, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let cohort = sqlx::query_as::<_, Cohort>(
+            "INSERT INTO cohorts (guild_id, name) VALUES ($1, $2) RETURNING *",
+        )
+        .bind(actor.guild_id)
+        .bind(name)
+        .fetch_one(&mut *transaction)
+        .await?;
+        record(
+            &mut transaction,
+            actor,
+            audit::INSERT,
+            audit::COHORT,
+            cohort.id,
+            None,
+            Some(&cohort),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(cohort)
+    }
+
+    async fn delete_cohort(&self, id: i64, actor: &Actor) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM cohorts WHERE id = $1 AND guild_id = $2)",
+        )
+        .bind(id)
+        .bind(actor.guild_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+        if !exists {
+            return Ok(());
+        }
+
+        if let Some(snapshot) = remove_cohort_rows(&mut transaction, id).await? {
+            record(
+                &mut transaction,
+                actor,
+                audit::DELETE,
+                audit::COHORT,
+                id,
+                Some(&snapshot),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn member_cohort(&self, guild_id: i64, user_id: i64) -> Result<Option<Cohort>, Error> {
+        let cohort = sqlx::query_as::<_, Cohort>(
+            r#"
+            SELECT cohorts.* FROM cohorts
+            JOIN cohort_members ON cohort_members.fk_cohort_id = cohorts.id
+            WHERE cohort_members.guild_id = $1 AND cohort_members.user_id = $2
+            "#,
+        )
+        .bind(guild_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(cohort)
+    }
+
+    async fn set_member_cohort(
+        &self,
+        guild_id: i64,
+        user_id: i64,
+        fk_cohort_id: Option<i64>,
+    ) -> Result<(), Error> {
+        match fk_cohort_id {
+            Some(fk_cohort_id) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO cohort_members (guild_id, user_id, fk_cohort_id) VALUES ($1, $2, $3)
+                    ON CONFLICT (guild_id, user_id) DO UPDATE SET fk_cohort_id = excluded.fk_cohort_id
+                    "#,
+                )
+                .bind(guild_id)
+                .bind(user_id)
+                .bind(fk_cohort_id)
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM cohort_members WHERE guild_id = $1 AND user_id = $2")
+                    .bind(guild_id)
+                    .bind(user_id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn cohort_dates(&self, guild_id: i64) -> Result<Vec<CohortDates>, Error> {
+        let dates = sqlx::query_as::<_, CohortDates>(
+            r#"
+            SELECT cohort_dates.* FROM cohort_dates
+            JOIN cohorts ON cohorts.id = cohort_dates.fk_cohort_id
+            WHERE cohorts.guild_id = $1
+            ORDER BY cohort_dates.fk_assessment_id, cohorts.name
+            "#,
+        )
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(dates)
+    }
+
+    async fn set_cohort_dates(&self, dates: &CohortDates, actor: &Actor) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let before = sqlx::query_as::<_, CohortDates>(
+            "SELECT * FROM cohort_dates WHERE fk_assessment_id = $1 AND fk_cohort_id = $2 FOR UPDATE",
+        )
+        .bind(dates.fk_assessment_id)
+        .bind(dates.fk_cohort_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        restore_cohort_dates_row(&mut transaction, dates).await?;
+        let action = match before {
+            Some(_) => audit::UPDATE,
+            None => audit::INSERT,
+        };
+        record(
+            &mut transaction,
+            actor,
+            action,
+            audit::COHORT_DATES,
+            dates.fk_assessment_id,
+            before.as_ref(),
+            Some(dates),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_cohort_dates(
+        &self,
+        fk_assessment_id: i64,
+        fk_cohort_id: i64,
+        actor: &Actor,
+    ) -> Result<bool, Error> {
+        let mut transaction = self.pool.begin().await?;
+
+        let dates =
+            remove_cohort_dates_row(&mut transaction, fk_assessment_id, fk_cohort_id).await?;
+        if let Some(dates) = &dates {
+            record(
+                &mut transaction,
+                actor,
+                audit::DELETE,
+                audit::COHORT_DATES,
+                fk_assessment_id,
+                Some(dates),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(dates.is_some())
+    }
+
+    async fn theme_preference(&self, guild_id: i64, user_id: i64) -> Result<Option<String>, Error> {
+        let theme = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT theme FROM theme_preferences
+            WHERE (guild_id = 0 AND user_id = $1) OR (guild_id = $2 AND user_id = 0)
+            ORDER BY user_id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .bind(guild_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(theme)
+    }
+
+    async fn set_theme_preference(
+        &self,
+        guild_id: i64,
+        user_id: i64,
+        theme: Option<&str>,
+    ) -> Result<(), Error> {
+        match theme {
+            Some(theme) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO theme_preferences (guild_id, user_id, theme)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (guild_id, user_id) DO UPDATE SET theme = excluded.theme
+                    "#,
+                )
+                .bind(guild_id)
+                .bind(user_id)
+                .bind(theme)
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM theme_preferences WHERE guild_id = $1 AND user_id = $2")
+                    .bind(guild_id)
+                    .bind(user_id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        guild_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let words = database_utils::search_words(query);
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let terms: Vec<String> = words.iter().map(|word| format!("{}:*", word)).collect();
+
+        // Codes are weighted above names, like the SQLite index does
+        let hits = sqlx::query_as::<_, SearchHit>(
+            r#"
+            SELECT 'course' AS kind, id AS entity_id, id AS course_id, name, code,
+                ts_rank(setweight(to_tsvector('simple', code), 'A')
+                    || setweight(to_tsvector('simple', name), 'D'), query) AS rank
+            FROM courses, to_tsquery('simple', $1) query
+            WHERE guild_id = $3 AND deleted_at IS NULL
+                AND to_tsvector('simple', name || ' ' || code) @@ query
+            UNION ALL
+            SELECT 'assessment', a.id, a.fk_course_id, a.name, c.code,
+                ts_rank(setweight(to_tsvector('simple', c.code), 'A')
+                    || setweight(to_tsvector('simple', a.name), 'D'), query)
+            FROM assessments a JOIN courses c ON c.id = a.fk_course_id, to_tsquery('simple', $1) query
+            WHERE c.guild_id = $3 AND a.deleted_at IS NULL AND c.deleted_at IS NULL
+                AND to_tsvector('simple', a.name || ' ' || c.code) @@ query
+            ORDER BY rank DESC, kind DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(terms.join(" & "))
+        .bind(limit)
+        .bind(guild_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(hits)
+    }
+}
+
+/// Run against a throwaway database, the schema is created by the migrations:
+/// `TEST_POSTGRES_URL=postgres://... cargo test --features postgres`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    async fn storage() -> Option<PostgresStorage> {
+        let url = std::env::var("TEST_POSTGRES_URL").ok()?;
+        let pool = establish_connection(&url).await.unwrap();
+        sqlx::query("TRUNCATE cohort_dates, cohort_members, cohorts, sent_reminders, course_roles, course_subscriptions, theme_preferences, audit_log, course_editors, sessions, assessments, courses, terms RESTART IDENTITY")
+            .execute(&pool)
+            .await
+            .unwrap();
+        Some(PostgresStorage::new(pool))
+    }
+
+    #[tokio::test]
+    async fn delete_course_returns_it_and_trashes_its_assessments() {
+        let Some(storage) = storage().await else {
+            return;
+        };
+
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        storage
+            .insert_assessment(&assessment("Exam", course_id), &ACTOR)
+            .await
+            .unwrap();
+
+        let deleted = storage
+            .delete_course(course_id, &ACTOR)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(deleted.code, "ABC");
+        assert!(deleted.deleted_at.is_some());
+
+        assert!(storage
+            .course(ACTOR.guild_id, course_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .course_assessments(course_id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .delete_course(course_id, &ACTOR)
+            .await
+            .unwrap()
+            .is_none());
+
+        let (audit_entries,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM audit_log")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(audit_entries, 3);
+    }
+
+    #[tokio::test]
+    async fn delete_assessment_returns_the_deleted_row() {
+        let Some(storage) = storage().await else {
+            return;
+        };
+
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        storage
+            .insert_assessment(&assessment("Exam", course_id), &ACTOR)
+            .await
+            .unwrap();
+        let assessment_id = storage.all_assessments(ACTOR.guild_id).await.unwrap()[0].id;
+
+        let deleted = storage
+            .delete_assessment(assessment_id, &ACTOR)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(deleted.name, "Exam");
+        assert!(storage
+            .assessment(ACTOR.guild_id, assessment_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .delete_assessment(assessment_id, &ACTOR)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
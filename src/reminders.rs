@@ -0,0 +1,205 @@
+use chrono::NaiveDate;
+use poise::serenity_prelude as serenity;
+
+use crate::cohorts;
+use crate::config;
+use crate::database_utils::{Assessment, Course};
+use crate::error::Error;
+use crate::rendering;
+use crate::storage::Storage;
+use crate::utils;
+
+#[derive(Debug, PartialEq)]
+pub struct DueAttempt {
+    pub assessment_id: i64,
+    pub name: String,
+    pub attempt: &'static str,
+    pub date: NaiveDate,
+    /// Empty if the attempt is everyone's
+    pub cohorts: Vec<String>,
+}
+
+/// Attempts from today until `days_before` days ahead, soonest first.
+/// Cohorts sharing the date of an attempt get one entry, see `cohorts::cohort_versions`
+pub fn due_attempts(
+    versions: &[(Assessment, Option<String>)],
+    today: NaiveDate,
+    days_before: i64,
+) -> Vec<DueAttempt> {
+    let mut due: Vec<DueAttempt> = Vec::new();
+    for (assessment, cohort) in versions {
+        for (attempt, date) in utils::assessment_attempts(assessment) {
+            if !(0..=days_before).contains(&(date - today).num_days()) {
+                continue;
+            }
+
+            match due.iter_mut().find(|due| {
+                due.assessment_id == assessment.id && due.attempt == attempt && due.date == date
+            }) {
+                Some(due) => due.cohorts.extend(cohort.clone()),
+                None => due.push(DueAttempt {
+                    assessment_id: assessment.id,
+                    name: assessment.name.clone(),
+                    attempt,
+                    date,
+                    cohorts: cohort.iter().cloned().collect(),
+                }),
+            }
+        }
+    }
+    due.sort_by_key(|due| due.date);
+
+    due
+}
+
+pub fn reminder_message(
+    course: &Course,
+    role_id: i64,
+    due: &[DueAttempt],
+    today: NaiveDate,
+) -> String {
+    let mut content = format!(
+        "<@&{}> **{} {}** coming up:",
+        role_id, course.code, course.name
+    );
+    for due in due {
+        content += &format!(
+            "\n- {} ({}) on {}, {}",
+            due.name,
+            due.attempt,
+            due.date.format("%Y-%m-%d"),
+            rendering::countdown(due.date, today)
+        );
+        if !due.cohorts.is_empty() {
+            content += &format!(" for {}", due.cohorts.join(", "));
+        }
+    }
+
+    content
+}
+
+/// Posts attempts due soon in the channels linked to their courses, pinging the course role.
+/// Attempts of cohorts name the cohorts they are for. Each date is posted once per guild,
+/// returns the number of messages sent
+pub async fn send_reminders(http: &serenity::Http, storage: &dyn Storage) -> Result<usize, Error> {
+    let today = config::today();
+    let days_before = config::get().scheduler.reminder_days_before;
+    let mut sent = 0;
+
+    for link in storage.reminder_channels().await? {
+        let channel_id = match link.channel_id {
+            Some(channel_id) => serenity::ChannelId(channel_id as u64),
+            None => continue,
+        };
+        let course = match storage.course(link.guild_id, link.fk_course_id).await? {
+            Some(course) => course,
+            None => continue,
+        };
+        let assessments = storage.course_assessments(course.id).await?;
+        let versions = cohorts::cohort_versions(
+            &assessments,
+            &storage.cohorts(link.guild_id).await?,
+            &storage.cohort_dates(link.guild_id).await?,
+        );
+
+        let mut due = Vec::new();
+        for attempt in due_attempts(&versions, today, days_before) {
+            let day = attempt.date.format("%Y-%m-%d").to_string();
+            if !storage
+                .is_reminder_sent(link.guild_id, attempt.assessment_id, &day)
+                .await?
+            {
+                due.push(attempt);
+            }
+        }
+        if due.is_empty() {
+            continue;
+        }
+
+        let content = reminder_message(&course, link.role_id, &due, today);
+        let role_id = serenity::RoleId(link.role_id as u64);
+        let posted = channel_id
+            .send_message(http, |m| {
+                m.content(content)
+                    .allowed_mentions(|a| a.empty_parse().roles(vec![role_id]))
+            })
+            .await;
+        if let Err(error) = posted {
+            tracing::warn!(guild = link.guild_id, course = course.id, %error, "failed to post reminder");
+            continue;
+        }
+
+        for attempt in &due {
+            let day = attempt.date.format("%Y-%m-%d").to_string();
+            storage
+                .record_reminder(link.guild_id, attempt.assessment_id, &day)
+                .await?;
+        }
+        sent += 1;
+    }
+
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    #[test]
+    fn reminders_list_attempts_due_within_the_window() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let course = Course {
+            id: 1,
+            ..course("MTH101", None)
+        };
+        let assessments = [
+            Assessment {
+                take1: String::from("2024-03-14"),
+                retake1: String::from("2024-03-16"),
+                ..assessment("Midterm", 1)
+            },
+            Assessment {
+                take1: String::from("2024-03-15"),
+                retake1: String::from("2024-03-18"),
+                ..assessment("Quiz", 1)
+            },
+        ];
+
+        let everyone: Vec<(Assessment, Option<String>)> = assessments
+            .iter()
+            .map(|assessment| (assessment.clone(), None))
+            .collect();
+
+        let due = due_attempts(&everyone, today, 1);
+        assert_eq!(
+            reminder_message(&course, 42, &due, today),
+            format!(
+                "<@&42> **MTH101 {}** coming up:\n- Quiz (Take 1) on 2024-03-15, today\n- Midterm (Retake 1) on 2024-03-16, tomorrow",
+                course.name
+            )
+        );
+        assert!(due_attempts(&everyone, today, -1).is_empty());
+    }
+
+    #[test]
+    fn cohorts_sharing_a_date_are_reminded_together() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let lab = |take1: &str| Assessment {
+            id: 7,
+            take1: take1.to_string(),
+            ..assessment("Lab", 1)
+        };
+        let versions = [
+            (lab("2024-03-16"), Some(String::from("Group A"))),
+            (lab("2024-03-16"), Some(String::from("Group B"))),
+            (lab("2024-03-22"), Some(String::from("Group C"))),
+        ];
+
+        let due = due_attempts(&versions, today, 1);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].cohorts, ["Group A", "Group B"]);
+        let message = reminder_message(&course("PHY101", None), 42, &due, today);
+        assert!(message.ends_with("- Lab (Take 1) on 2024-03-16, tomorrow for Group A, Group B"));
+    }
+}
@@ -0,0 +1,1138 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{ContentArrangement, Table};
+
+use crate::config::{self, ColorsConfig};
+use crate::database_utils::{Assessment, Course, SearchHit, Session, Term};
+use crate::utils;
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+// Discord renders code blocks narrower than this without wrapping on most screens
+const TABLE_WIDTH: u16 = 100;
+// Discord API limits to 2000 characters per message
+pub const MESSAGE_LIMIT: usize = 2000;
+// `/course` shows at most this many assessments and sessions, `/list_assessments` has the rest
+const DASHBOARD_ROWS: usize = 8;
+const ANSI_RESET: &str = "\u{001b}[0m";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DateUrgency {
+    Past,
+    ThisWeek,
+    NextWeek,
+    Later,
+}
+
+/// Days until a due date counts as this week or next week, see `ColorsConfig`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Thresholds {
+    pub this_week_days: i64,
+    pub next_week_days: i64,
+}
+
+impl From<&ColorsConfig> for Thresholds {
+    fn from(colors: &ColorsConfig) -> Self {
+        Thresholds {
+            this_week_days: colors.this_week_days,
+            next_week_days: colors.next_week_days,
+        }
+    }
+}
+
+pub fn date_urgency(date: NaiveDate, today: NaiveDate, thresholds: Thresholds) -> DateUrgency {
+    if date < today {
+        DateUrgency::Past
+    } else if date <= today + chrono::Duration::days(thresholds.this_week_days) {
+        DateUrgency::ThisWeek
+    } else if date <= today + chrono::Duration::days(thresholds.next_week_days) {
+        DateUrgency::NextWeek
+    } else {
+        DateUrgency::Later
+    }
+}
+
+/// Textual urgency shown next to due dates, so the meaning does not depend on color alone
+pub fn urgency_marker(urgency: DateUrgency, days_left: i64) -> Option<String> {
+    match urgency {
+        DateUrgency::Past => Some(String::from("(past)")),
+        DateUrgency::ThisWeek if days_left == 0 => Some(String::from("⚠ today")),
+        DateUrgency::ThisWeek => Some(format!("⚠ {}d", days_left)),
+        DateUrgency::NextWeek => Some(format!("{}d", days_left)),
+        DateUrgency::Later => None,
+    }
+}
+
+/// Themes users and guilds can pick, stored by name
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ThemeName {
+    #[default]
+    #[name = "default"]
+    Default,
+    #[name = "high-contrast"]
+    HighContrast,
+    #[name = "colour-blind"]
+    ColorBlind,
+    #[name = "no-colour"]
+    NoColor,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Default => Theme::DEFAULT,
+            ThemeName::HighContrast => Theme::HIGH_CONTRAST,
+            ThemeName::ColorBlind => Theme::COLOR_BLIND,
+            ThemeName::NoColor => Theme::NO_COLOR,
+        }
+    }
+}
+
+/// ANSI escape codes used for each urgency, empty codes leave the text as is
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub past: &'static str,
+    pub this_week: &'static str,
+    pub next_week: &'static str,
+    pub later: &'static str,
+}
+
+impl Theme {
+    // Past dates in gray, black would vanish on the dark theme
+    pub const DEFAULT: Theme = Theme {
+        past: "\u{001b}[90m",
+        this_week: "\u{001b}[31m",
+        next_week: "\u{001b}[33m",
+        later: "\u{001b}[32m",
+    };
+
+    // Bold colors and white instead of the gray that is hard to read on the dark theme
+    pub const HIGH_CONTRAST: Theme = Theme {
+        past: "\u{001b}[37m",
+        this_week: "\u{001b}[1;31m",
+        next_week: "\u{001b}[1;33m",
+        later: "\u{001b}[1;36m",
+    };
+
+    // No red next to green, magenta and yellow against blue stay apart for all common types
+    pub const COLOR_BLIND: Theme = Theme {
+        past: "\u{001b}[37m",
+        this_week: "\u{001b}[1;35m",
+        next_week: "\u{001b}[33m",
+        later: "\u{001b}[34m",
+    };
+
+    pub const NO_COLOR: Theme = Theme {
+        past: "",
+        this_week: "",
+        next_week: "",
+        later: "",
+    };
+
+    pub fn color(&self, urgency: DateUrgency) -> &'static str {
+        match urgency {
+            DateUrgency::Past => self.past,
+            DateUrgency::ThisWeek => self.this_week,
+            DateUrgency::NextWeek => self.next_week,
+            DateUrgency::Later => self.later,
+        }
+    }
+
+    pub fn paint(&self, text: &str, urgency: DateUrgency) -> String {
+        match self.color(urgency) {
+            "" => text.to_string(),
+            color => format!("{}{}{}", color, text, ANSI_RESET),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DEFAULT
+    }
+}
+
+/// Everything the output depends on besides the listed rows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RenderOptions {
+    pub today: NaiveDate,
+    pub thresholds: Thresholds,
+    pub theme: Theme,
+}
+
+impl RenderOptions {
+    /// Today in the configured timezone with the configured thresholds
+    pub fn from_config(theme: Theme) -> Self {
+        RenderOptions {
+            today: config::today(),
+            thresholds: Thresholds::from(&config::get().colors),
+            theme,
+        }
+    }
+
+    pub fn urgency(&self, date: NaiveDate) -> DateUrgency {
+        date_urgency(date, self.today, self.thresholds)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cell {
+    pub text: String,
+    /// Set for due dates, decides how the theme colors the cell
+    pub urgency: Option<DateUrgency>,
+    /// Shown after the text, see `urgency_marker`
+    pub marker: Option<String>,
+}
+
+impl Cell {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Cell {
+            text: text.into(),
+            urgency: None,
+            marker: None,
+        }
+    }
+
+    /// Dates that can't be parsed are shown as they are
+    pub fn date(date: &str, options: &RenderOptions) -> Self {
+        match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => {
+                let urgency = options.urgency(date);
+                Cell {
+                    text: date.format("%Y-%m-%d").to_string(),
+                    urgency: Some(urgency),
+                    marker: urgency_marker(urgency, (date - options.today).num_days()),
+                }
+            }
+            Err(_) => Cell::plain(date),
+        }
+    }
+
+    /// Relative to today, the text already says how close the date is so there is no marker
+    pub fn countdown(date: Option<NaiveDate>, options: &RenderOptions) -> Self {
+        match date {
+            Some(date) => Cell {
+                text: countdown(date, options.today),
+                urgency: Some(options.urgency(date)),
+                marker: None,
+            },
+            None => Cell::plain("-"),
+        }
+    }
+
+    pub fn render(&self, theme: &Theme) -> String {
+        let text = match &self.marker {
+            Some(marker) => format!("{} {}", self.text, marker),
+            None => self.text.clone(),
+        };
+
+        match self.urgency {
+            Some(urgency) => theme.paint(&text, urgency),
+            None => text,
+        }
+    }
+}
+
+/// "today", "tomorrow", "in 3 days", "2 weeks ago" and so on
+pub fn countdown(date: NaiveDate, today: NaiveDate) -> String {
+    let days = (date - today).num_days();
+
+    let amount = |days: i64| match days {
+        0..=13 => format!("{} days", days),
+        14..=59 => format!("{} weeks", days / 7),
+        _ if days < 730 => format!("{} months", days / 30),
+        _ => format!("{} years", days / 365),
+    };
+
+    match days {
+        0 => String::from("today"),
+        1 => String::from("tomorrow"),
+        -1 => String::from("yesterday"),
+        _ if days > 0 => format!("in {}", amount(days)),
+        _ => format!("{} ago", amount(-days)),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TableView {
+    pub header: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl TableView {
+    /// An `ansi` code block for Discord
+    pub fn render(&self, theme: &Theme) -> String {
+        let mut table = Table::new();
+        table
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(TABLE_WIDTH)
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_header(self.header.clone());
+
+        for row in &self.rows {
+            table.add_row(row.iter().map(|cell| cell.render(theme)));
+        }
+
+        String::from("```ansi\n") + &table.to_string() + "```"
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PageView {
+    pub title: String,
+    /// 1-based
+    pub page: usize,
+    pub page_count: usize,
+    pub table: TableView,
+}
+
+impl PageView {
+    /// Rows that do not fit in one message are counted instead
+    pub fn render(&self, theme: &Theme) -> String {
+        fit_rows(self.table.clone(), |table, hidden| {
+            let content = match self.page_count {
+                0 | 1 => format!("# {}\n{}", self.title, table.render(theme)),
+                _ => format!(
+                    "# {} (Page {}/{})\n{}",
+                    self.title,
+                    self.page,
+                    self.page_count,
+                    table.render(theme)
+                ),
+            };
+
+            content + &not_shown(hidden)
+        })
+    }
+}
+
+/// Drops rows from the end of `table` until what `render` makes of it fits in one message,
+/// `render` is also given the number of dropped rows
+fn fit_rows(table: TableView, render: impl Fn(&TableView, usize) -> String) -> String {
+    let total = table.rows.len();
+    let render_first = |count: usize| {
+        let mut shown = table.clone();
+        shown.rows.truncate(count);
+        render(&shown, total - count)
+    };
+    let fits = |content: &str| content.chars().count() <= MESSAGE_LIMIT;
+
+    let content = render_first(total);
+    if fits(&content) {
+        return content;
+    }
+
+    // Fewer rows never make the message longer, so the most rows that fit can be bisected
+    let (mut low, mut high) = (0, total);
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+        match fits(&render_first(middle)) {
+            true => low = middle,
+            false => high = middle,
+        }
+    }
+
+    render_first(low)
+}
+
+fn not_shown(hidden: usize) -> String {
+    match hidden {
+        0 => String::new(),
+        _ => format!("\n...and {} more not shown", hidden),
+    }
+}
+
+/// An empty list still has one (empty) page
+pub fn page_count(total: usize, per_page: usize) -> usize {
+    total.div_ceil(per_page.max(1)).max(1)
+}
+
+fn page_rows<T>(items: &[T], page: usize, per_page: usize) -> &[T] {
+    let start = page
+        .saturating_sub(1)
+        .saturating_mul(per_page)
+        .min(items.len());
+    let end = start.saturating_add(per_page).min(items.len());
+
+    &items[start..end]
+}
+
+/// One example date per urgency, shown when a theme is picked
+pub fn legend(options: &RenderOptions) -> String {
+    let examples = [
+        ("Past", -3),
+        ("Due this week", options.thresholds.this_week_days.min(3)),
+        ("Due next week", options.thresholds.next_week_days),
+        ("Later", options.thresholds.next_week_days + 1),
+    ];
+
+    let lines: Vec<String> = examples
+        .iter()
+        .map(|(label, days)| {
+            let date = options.today + chrono::Duration::days(*days);
+            let cell = Cell::date(&date.format("%Y-%m-%d").to_string(), options);
+            format!("{:<14}{}", label, cell.render(&options.theme))
+        })
+        .collect();
+
+    String::from("```ansi\n") + &lines.join("\n") + "\n```"
+}
+
+pub fn courses_table(courses: &[Course]) -> TableView {
+    TableView {
+        header: vec!["ID", "Name", "Code", "Semester", "Year", "Credit"],
+        rows: courses
+            .iter()
+            .map(|course| {
+                vec![
+                    Cell::plain(course.id.to_string()),
+                    Cell::plain(&course.name),
+                    Cell::plain(&course.code),
+                    Cell::plain(course.semester.to_string()),
+                    Cell::plain(course.year.to_string()),
+                    Cell::plain(course.credit.to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+pub fn assessments_table(assessments: &[Assessment], options: &RenderOptions) -> TableView {
+    TableView {
+        header: vec![
+            "ID",
+            "Name",
+            "Take 1",
+            "Retake 1",
+            "Retake 2",
+            "Next attempt",
+            "Weight",
+            "Course ID",
+        ],
+        rows: assessments
+            .iter()
+            .map(|assessment| {
+                vec![
+                    Cell::plain(assessment.id.to_string()),
+                    Cell::plain(&assessment.name),
+                    Cell::date(&assessment.take1, options),
+                    Cell::date(&assessment.retake1, options),
+                    Cell::date(&assessment.retake2, options),
+                    Cell::countdown(utils::next_attempt(assessment, options.today), options),
+                    Cell::plain(assessment.weight.to_string()),
+                    Cell::plain(assessment.fk_course_id.to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+pub fn terms_table(terms: &[Term]) -> TableView {
+    TableView {
+        header: vec!["ID", "Name", "Start", "End", "Status"],
+        rows: terms
+            .iter()
+            .map(|term| {
+                let status = if term.active {
+                    "Active"
+                } else if term.archived {
+                    "Archived"
+                } else {
+                    ""
+                };
+
+                vec![
+                    Cell::plain(term.id.to_string()),
+                    Cell::plain(&term.name),
+                    Cell::plain(&term.start_date),
+                    Cell::plain(&term.end_date),
+                    Cell::plain(status),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Narrower than `assessments_table` so a whole course fits in one message,
+/// the last row adds up the weights and flags totals other than 100
+pub fn course_assessments_table(assessments: &[Assessment], options: &RenderOptions) -> TableView {
+    let mut rows: Vec<Vec<Cell>> = assessments
+        .iter()
+        .map(|assessment| {
+            vec![
+                Cell::plain(assessment.id.to_string()),
+                Cell::plain(&assessment.name),
+                Cell::countdown(utils::next_attempt(assessment, options.today), options),
+                Cell::plain(assessment.weight.to_string()),
+            ]
+        })
+        .collect();
+
+    let total = (assessments.iter().map(|a| a.weight).sum::<f64>() * 100.0).round() / 100.0;
+    let total = match total == 100.0 {
+        true => total.to_string(),
+        false => format!("{} ⚠ not 100", total),
+    };
+    rows.push(vec![
+        Cell::plain(""),
+        Cell::plain("Total"),
+        Cell::plain(""),
+        Cell::plain(total),
+    ]);
+
+    TableView {
+        header: vec!["ID", "Name", "Next attempt", "Weight"],
+        rows,
+    }
+}
+
+pub fn sessions_table(sessions: &[Session]) -> TableView {
+    TableView {
+        header: vec!["ID", "Day", "Time", "Location"],
+        rows: sessions
+            .iter()
+            .map(|session| {
+                let day = match session.weekday {
+                    1..=7 => WEEKDAYS[session.weekday as usize - 1].to_string(),
+                    weekday => weekday.to_string(),
+                };
+
+                vec![
+                    Cell::plain(session.id.to_string()),
+                    Cell::plain(day),
+                    Cell::plain(format!("{}-{}", session.start_time, session.end_time)),
+                    Cell::plain(&session.location),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Everything about one course for `/course`, sessions are left out if there are none.
+/// Sections are cut to `DASHBOARD_ROWS` rows and further until the dashboard fits in one message
+pub fn course_dashboard(
+    course: &Course,
+    term: Option<&Term>,
+    assessments: &[Assessment],
+    sessions: &[Session],
+    options: &RenderOptions,
+) -> String {
+    let term = match term {
+        Some(term) => format!(" · {}", term.name),
+        None => String::new(),
+    };
+    let mut content = format!(
+        "# {} {}\nID {} · semester {} · {} · {} credits{}\n",
+        course.code, course.name, course.id, course.semester, course.year, course.credit, term
+    );
+
+    let next_deadline = assessments
+        .iter()
+        .flat_map(|assessment| {
+            utils::assessment_dates(assessment)
+                .into_iter()
+                .map(move |date| (date, assessment))
+        })
+        .filter(|(date, _)| *date >= options.today)
+        .min_by_key(|(date, _)| *date);
+    content += &match next_deadline {
+        Some((date, assessment)) => format!(
+            "**Next deadline:** {} on {} ({})\n",
+            assessment.name,
+            date.format("%Y-%m-%d"),
+            countdown(date, options.today)
+        ),
+        None => String::from("**Next deadline:** none\n"),
+    };
+
+    // The longer section gives up rows until the dashboard fits in one message
+    let mut shown_assessments = assessments.len().min(DASHBOARD_ROWS);
+    let mut shown_sessions = sessions.len().min(DASHBOARD_ROWS);
+    loop {
+        let mut dashboard = content.clone();
+
+        // The total row stays, it adds up all of the course's weights
+        let mut table = course_assessments_table(assessments, options);
+        table.rows.drain(shown_assessments..assessments.len());
+        dashboard += &table.render(&options.theme);
+        if shown_assessments < assessments.len() {
+            dashboard += &format!(
+                "\n...and {} more, see `/list_assessments`",
+                assessments.len() - shown_assessments
+            );
+        }
+
+        if !sessions.is_empty() {
+            dashboard += "\n## Weekly sessions\n";
+            dashboard += &sessions_table(&sessions[..shown_sessions]).render(&options.theme);
+            dashboard += &not_shown(sessions.len() - shown_sessions);
+        }
+
+        if dashboard.chars().count() <= MESSAGE_LIMIT {
+            return dashboard;
+        }
+        match shown_assessments >= shown_sessions {
+            true if shown_assessments > 0 => shown_assessments -= 1,
+            _ if shown_sessions > 0 => shown_sessions -= 1,
+            _ => return dashboard,
+        }
+    }
+}
+
+/// Attempts from today on, soonest first. Assessments of other courses are left out
+pub fn deadlines_table(
+    courses: &[Course],
+    assessments: &[Assessment],
+    options: &RenderOptions,
+) -> TableView {
+    let mut deadlines: Vec<(NaiveDate, &str, &Course, &Assessment)> = Vec::new();
+    for assessment in assessments {
+        let course = match courses
+            .iter()
+            .find(|course| course.id == assessment.fk_course_id)
+        {
+            Some(course) => course,
+            None => continue,
+        };
+        for (attempt, date) in utils::assessment_attempts(assessment) {
+            if date >= options.today {
+                deadlines.push((date, attempt, course, assessment));
+            }
+        }
+    }
+    deadlines.sort_by_key(|(date, ..)| *date);
+
+    TableView {
+        header: vec!["Date", "Due", "Course", "Assessment", "Attempt", "Weight"],
+        rows: deadlines
+            .iter()
+            .map(|(date, attempt, course, assessment)| {
+                vec![
+                    Cell::date(&date.format("%Y-%m-%d").to_string(), options),
+                    Cell::countdown(Some(*date), options),
+                    Cell::plain(&course.code),
+                    Cell::plain(&assessment.name),
+                    Cell::plain(*attempt),
+                    Cell::plain(assessment.weight.to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Later deadlines that do not fit in one message are counted instead
+pub fn deadlines_page(
+    courses: &[Course],
+    assessments: &[Assessment],
+    options: &RenderOptions,
+) -> PageView {
+    PageView {
+        title: String::from("My deadlines"),
+        page: 1,
+        page_count: 1,
+        table: deadlines_table(courses, assessments, options),
+    }
+}
+
+/// Numbered by rank, assessments show their course's code
+pub fn search_table(hits: &[SearchHit]) -> TableView {
+    TableView {
+        header: vec!["#", "Code", "Name", "Type", "ID"],
+        rows: hits
+            .iter()
+            .enumerate()
+            .map(|(index, hit)| {
+                let kind = match hit.kind.as_str() {
+                    "course" => "Course",
+                    _ => "Assessment",
+                };
+
+                vec![
+                    Cell::plain((index + 1).to_string()),
+                    Cell::plain(&hit.code),
+                    Cell::plain(&hit.name),
+                    Cell::plain(kind),
+                    Cell::plain(hit.entity_id.to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+pub fn search_page(query: &str, hits: &[SearchHit]) -> PageView {
+    PageView {
+        title: format!("Search results for \"{}\"", query),
+        page: 1,
+        page_count: 1,
+        table: search_table(hits),
+    }
+}
+
+fn purge_date(deleted_at: &Option<String>, retention_days: i64) -> String {
+    deleted_at
+        .as_deref()
+        .and_then(|date| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").ok())
+        .map(|date| {
+            (date + chrono::Duration::days(retention_days))
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn trash_table(courses: &[Course], assessments: &[Assessment], retention_days: i64) -> TableView {
+    let course_rows = courses.iter().map(|course| {
+        vec![
+            Cell::plain("Course"),
+            Cell::plain(course.id.to_string()),
+            Cell::plain(format!("{} {}", course.code, course.name)),
+            Cell::plain(course.deleted_at.as_deref().unwrap_or_default()),
+            Cell::plain(purge_date(&course.deleted_at, retention_days)),
+        ]
+    });
+    let assessment_rows = assessments.iter().map(|assessment| {
+        vec![
+            Cell::plain("Assessment"),
+            Cell::plain(assessment.id.to_string()),
+            Cell::plain(&assessment.name),
+            Cell::plain(assessment.deleted_at.as_deref().unwrap_or_default()),
+            Cell::plain(purge_date(&assessment.deleted_at, retention_days)),
+        ]
+    });
+
+    TableView {
+        header: vec!["Type", "ID", "Name", "Deleted", "Purged on"],
+        rows: course_rows.chain(assessment_rows).collect(),
+    }
+}
+
+/// The `/trash` reply, rows that do not fit in one message are counted instead
+pub fn trash_message(
+    courses: &[Course],
+    assessments: &[Assessment],
+    retention_days: i64,
+    theme: &Theme,
+) -> String {
+    fit_rows(
+        trash_table(courses, assessments, retention_days),
+        |table, hidden| format!("# Trash\n{}{}", table.render(theme), not_shown(hidden)),
+    )
+}
+
+pub fn courses_page(
+    courses: &[Course],
+    page: usize,
+    per_page: usize,
+    term: Option<&Term>,
+) -> PageView {
+    let title = match term {
+        Some(term) if term.archived => format!("Courses list - {} (archived)", term.name),
+        Some(term) => format!("Courses list - {}", term.name),
+        None => String::from("Courses list"),
+    };
+
+    PageView {
+        title,
+        page,
+        page_count: page_count(courses.len(), per_page),
+        table: courses_table(page_rows(courses, page, per_page)),
+    }
+}
+
+/// Titled with the course name when everything fits on one page
+pub fn assessments_page(
+    assessments: &[Assessment],
+    page: usize,
+    per_page: usize,
+    course_name: &str,
+    options: &RenderOptions,
+) -> PageView {
+    let page_count = page_count(assessments.len(), per_page);
+    let title = match page_count {
+        1 => course_name.to_string(),
+        _ => String::from("Assessments list"),
+    };
+
+    PageView {
+        title,
+        page,
+        page_count,
+        table: assessments_table(page_rows(assessments, page, per_page), options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    const THRESHOLDS: Thresholds = Thresholds {
+        this_week_days: 7,
+        next_week_days: 14,
+    };
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            today: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            thresholds: THRESHOLDS,
+            theme: Theme::DEFAULT,
+        }
+    }
+
+    fn courses(count: usize) -> Vec<Course> {
+        (0..count)
+            .map(|index| Course {
+                id: index as i64 + 1,
+                ..course(&format!("C{:02}", index), None)
+            })
+            .collect()
+    }
+
+    fn dated(id: i64, name: &str, take1: &str, retake1: &str, retake2: &str) -> Assessment {
+        Assessment {
+            id,
+            take1: take1.to_string(),
+            retake1: retake1.to_string(),
+            retake2: retake2.to_string(),
+            ..assessment(name, 1)
+        }
+    }
+
+    #[test]
+    fn trash_is_cut_to_one_message() {
+        let mut courses = courses(20);
+        for course in &mut courses {
+            course.name = "Introduction to ".repeat(5);
+            course.deleted_at = Some(String::from("2024-03-01 10:00:00"));
+        }
+
+        let content = trash_message(&courses, &[], 30, &Theme::default());
+        assert!(content.chars().count() <= MESSAGE_LIMIT);
+        assert!(content.contains("C00 Introduction"));
+        assert!(!content.contains("C19 Introduction"));
+        assert!(content.ends_with("more not shown"));
+
+        let content = trash_message(&courses[..2], &[], 30, &Theme::default());
+        assert!(content.ends_with("```"));
+    }
+
+    #[test]
+    fn full_assessment_page_fits_in_one_message() {
+        let options = options();
+        // The most rows `pages.assessments` allows, with names as long as `/insert_assessment` takes
+        let assessments: Vec<Assessment> = (0..15)
+            .map(|index| Assessment {
+                id: 1000 + index,
+                ..dated(0, &"x".repeat(80), "2024-03-14", "2024-03-20", "2024-03-28")
+            })
+            .collect();
+
+        let content =
+            assessments_page(&assessments, 1, 15, &"C".repeat(80), &options).render(&options.theme);
+        assert!(content.chars().count() <= MESSAGE_LIMIT);
+        assert!(content.contains("1000"));
+        assert!(content.ends_with("more not shown"));
+    }
+
+    #[test]
+    fn empty_lists() {
+        let options = options();
+        insta::assert_snapshot!(
+            "empty_courses",
+            courses_page(&[], 1, 5, None).render(&options.theme)
+        );
+        insta::assert_snapshot!(
+            "empty_assessments",
+            assessments_page(&[], 1, 9, "ABC name", &options).render(&options.theme)
+        );
+    }
+
+    #[test]
+    fn long_names_wrap_inside_the_table() {
+        let options = options();
+        let mut courses = courses(2);
+        courses[0].name = "Introduction to ".repeat(8).trim_end().to_string();
+        courses[1].code = "VERYLONGCOURSECODE-2024-SPRING".to_string();
+        let assessments = [dated(
+            1,
+            &"Group project presentation and report ".repeat(3),
+            "2024-03-20",
+            "2024-04-20",
+            "2024-05-20",
+        )];
+
+        insta::assert_snapshot!(
+            "long_course_names",
+            courses_page(&courses, 1, 5, None).render(&options.theme)
+        );
+        insta::assert_snapshot!(
+            "long_assessment_names",
+            assessments_page(&assessments, 1, 9, "ABC name", &options).render(&options.theme)
+        );
+    }
+
+    #[test]
+    fn every_urgency_bucket() {
+        let options = options();
+        let assessments = [
+            dated(1, "Past", "2024-03-14", "2024-03-15", "2024-03-22"),
+            dated(2, "Next week", "2024-03-23", "2024-03-29", "2024-03-30"),
+            dated(3, "Unparsed", "TBA", "", "2024-13-01"),
+        ];
+
+        let table = assessments_table(&assessments, &options);
+        let urgencies: Vec<Vec<Option<DateUrgency>>> = table
+            .rows
+            .iter()
+            .map(|row| row[2..5].iter().map(|cell| cell.urgency).collect())
+            .collect();
+        assert_eq!(
+            urgencies,
+            vec![
+                vec![
+                    Some(DateUrgency::Past),
+                    Some(DateUrgency::ThisWeek),
+                    Some(DateUrgency::ThisWeek)
+                ],
+                vec![
+                    Some(DateUrgency::NextWeek),
+                    Some(DateUrgency::NextWeek),
+                    Some(DateUrgency::Later)
+                ],
+                vec![None, None, None],
+            ]
+        );
+
+        insta::assert_snapshot!("urgency_buckets", table.render(&options.theme));
+    }
+
+    #[test]
+    fn markers_count_days_left() {
+        assert_eq!(
+            urgency_marker(DateUrgency::ThisWeek, 0),
+            Some(String::from("⚠ today"))
+        );
+        assert_eq!(
+            urgency_marker(DateUrgency::ThisWeek, 3),
+            Some(String::from("⚠ 3d"))
+        );
+        assert_eq!(
+            urgency_marker(DateUrgency::NextWeek, 10),
+            Some(String::from("10d"))
+        );
+        assert_eq!(urgency_marker(DateUrgency::Later, 30), None);
+    }
+
+    #[test]
+    fn countdown_reads_naturally() {
+        let today = options().today;
+        let in_days = |days: i64| countdown(today + chrono::Duration::days(days), today);
+
+        assert_eq!(in_days(0), "today");
+        assert_eq!(in_days(1), "tomorrow");
+        assert_eq!(in_days(-1), "yesterday");
+        assert_eq!(in_days(3), "in 3 days");
+        assert_eq!(in_days(14), "in 2 weeks");
+        assert_eq!(in_days(-15), "2 weeks ago");
+        assert_eq!(in_days(90), "in 3 months");
+        assert_eq!(in_days(-800), "2 years ago");
+    }
+
+    #[test]
+    fn every_theme_legend() {
+        for name in [
+            ThemeName::Default,
+            ThemeName::HighContrast,
+            ThemeName::ColorBlind,
+            ThemeName::NoColor,
+        ] {
+            let options = RenderOptions {
+                theme: name.theme(),
+                ..options()
+            };
+            insta::assert_snapshot!(format!("legend_{}", name.name()), legend(&options));
+        }
+    }
+
+    #[test]
+    fn no_colour_theme_has_no_escape_codes() {
+        let options = RenderOptions {
+            theme: Theme::NO_COLOR,
+            ..options()
+        };
+        let assessments = [dated(1, "Exam", "2024-03-14", "2024-03-18", "2024-04-30")];
+
+        let rendered = assessments_table(&assessments, &options).render(&options.theme);
+        assert!(!rendered.contains('\u{001b}'));
+        assert!(rendered.contains("2024-03-18 ⚠ 3d"));
+    }
+
+    #[test]
+    fn page_boundaries() {
+        let options = options();
+        let courses = courses(10);
+
+        assert_eq!(page_count(0, 5), 1);
+        assert_eq!(page_count(5, 5), 1);
+        assert_eq!(page_count(6, 5), 2);
+        assert_eq!(page_count(10, 5), 2);
+
+        let last = courses_page(&courses, 2, 5, None);
+        assert_eq!(last.page_count, 2);
+        assert_eq!(last.table.rows.len(), 5);
+        insta::assert_snapshot!("courses_last_full_page", last.render(&options.theme));
+
+        let partial = courses_page(&courses[..6], 2, 5, Some(&term("Spring", false)));
+        assert_eq!(partial.table.rows.len(), 1);
+        insta::assert_snapshot!("courses_partial_last_page", partial.render(&options.theme));
+
+        assert!(courses_page(&courses, 3, 5, None).table.rows.is_empty());
+    }
+
+    #[test]
+    fn course_dashboard_sums_weights_and_lists_sessions() {
+        let options = options();
+        let course = Course {
+            id: 1,
+            ..course("MTH101", Some(1))
+        };
+        let mut assessments = [
+            dated(1, "Midterm", "2024-03-01", "2024-03-20", ""),
+            dated(2, "Final exam", "2024-06-01", "", ""),
+        ];
+        assessments[0].weight = 40.0;
+        let sessions = [Session {
+            id: 3,
+            weekday: 2,
+            start_time: String::from("10:00"),
+            end_time: String::from("11:30"),
+            location: String::from("Room 101"),
+            fk_course_id: 1,
+        }];
+
+        insta::assert_snapshot!(
+            "course_dashboard",
+            course_dashboard(
+                &course,
+                Some(&term("Spring", true)),
+                &assessments,
+                &sessions,
+                &options
+            )
+        );
+
+        assessments[0].weight = 50.0;
+        let without_sessions = course_dashboard(&course, None, &assessments, &[], &options);
+        assert!(without_sessions.contains("┆ 100 "));
+        assert!(!without_sessions.contains("not 100"));
+        assert!(!without_sessions.contains("Weekly sessions"));
+        assert!(without_sessions.contains("**Next deadline:** Midterm on 2024-03-20 (in 5 days)"));
+    }
+
+    #[test]
+    fn busy_course_dashboard_fits_in_one_message() {
+        let options = options();
+        let course = Course {
+            id: 1,
+            name: "x".repeat(80),
+            ..course("MTH101", None)
+        };
+        let assessments: Vec<Assessment> = (0..20)
+            .map(|index| Assessment {
+                weight: 5.0,
+                ..dated(index, &"x".repeat(80), "2024-03-18", "", "")
+            })
+            .collect();
+        let sessions: Vec<Session> = (0..10)
+            .map(|index| Session {
+                id: index,
+                weekday: index % 7 + 1,
+                start_time: String::from("10:00"),
+                end_time: String::from("11:30"),
+                location: "x".repeat(40),
+                fk_course_id: 1,
+            })
+            .collect();
+
+        let content = course_dashboard(&course, None, &assessments, &sessions, &options);
+        assert!(content.chars().count() <= MESSAGE_LIMIT);
+        assert!(content.contains("more, see `/list_assessments`"));
+        assert!(content.contains("┆ 100 "));
+    }
+
+    #[test]
+    fn deadlines_are_upcoming_attempts_of_the_given_courses() {
+        let options = options();
+        let courses = courses(2);
+        let mut assessments = [
+            dated(1, "Midterm", "2024-03-01", "2024-04-02", ""),
+            dated(2, "Final exam", "2024-03-15", "TBA", "2024-05-01"),
+            dated(3, "Other course", "2024-03-20", "", ""),
+        ];
+        assessments[1].fk_course_id = 2;
+        assessments[2].fk_course_id = 3;
+
+        let table = deadlines_table(&courses, &assessments, &options);
+        let dates: Vec<&str> = table.rows.iter().map(|row| row[0].text.as_str()).collect();
+        assert_eq!(dates, ["2024-03-15", "2024-04-02", "2024-05-01"]);
+
+        insta::assert_snapshot!(
+            "deadlines",
+            deadlines_page(&courses, &assessments, &options).render(&Theme::NO_COLOR)
+        );
+    }
+
+    #[test]
+    fn many_deadlines_fit_in_one_message() {
+        let options = options();
+        let courses = courses(5);
+        let assessments: Vec<Assessment> = (0..40)
+            .map(|index| Assessment {
+                fk_course_id: index % 5 + 1,
+                ..dated(
+                    index,
+                    &"x".repeat(80),
+                    "2024-03-18",
+                    "2024-04-02",
+                    "2024-05-01",
+                )
+            })
+            .collect();
+
+        let content = deadlines_page(&courses, &assessments, &options).render(&options.theme);
+        assert!(content.chars().count() <= MESSAGE_LIMIT);
+        assert!(content.contains("2024-03-18"));
+        assert!(!content.contains("2024-05-01"));
+        assert!(content.ends_with("more not shown"));
+    }
+
+    #[test]
+    fn search_results_keep_their_rank() {
+        let hit = |kind: &str, entity_id: i64, name: &str| SearchHit {
+            kind: kind.to_string(),
+            entity_id,
+            course_id: 1,
+            name: name.to_string(),
+            code: String::from("MTH101"),
+        };
+        let hits = [
+            hit("course", 1, "Calculus one"),
+            hit("assessment", 4, "Calculus midterm"),
+            hit("assessment", 9, "Final exam"),
+        ];
+
+        insta::assert_snapshot!(
+            "search_results",
+            search_page("mth calc", &hits).render(&Theme::DEFAULT)
+        );
+    }
+}
@@ -0,0 +1,2225 @@
+use std::sync::Arc;
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+
+use crate::audit::{self, Actor, AuditEntry};
+use crate::config;
+use crate::database_utils::{
+    self, Assessment, ClonedCourse, Cohort, CohortDates, Course, CourseEditor, CourseRole,
+    SearchHit, Session, Term,
+};
+use crate::error::Error;
+#[cfg(feature = "postgres")]
+use crate::postgres_utils;
+
+/// Data access used by commands and component handlers.
+/// Deletes move rows to the trash, so deleted rows are never returned.
+/// Courses and terms belong to a guild, rows of other guilds are neither returned nor changed.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn all_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error>;
+    async fn term_courses(&self, guild_id: i64, fk_term_id: i64) -> Result<Vec<Course>, Error>;
+    async fn course(&self, guild_id: i64, id: i64) -> Result<Option<Course>, Error>;
+    /// Returns the number of inserted rows, the course is added to the actor's guild
+    /// and its id is ignored
+    async fn insert_course(&self, course: &Course, actor: &Actor) -> Result<u64, Error>;
+    /// Updates everything but the id, term and guild, `None` if the course does not exist
+    async fn update_course(&self, course: &Course, actor: &Actor) -> Result<Option<Course>, Error>;
+    /// Also deletes the course's assessments
+    async fn delete_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error>;
+    /// Copies the actor's guild's live courses of a (semester, year) with their assessments
+    /// and sessions, codes already taken in the target (semester, year) are skipped.
+    /// Assessment dates are moved by `shift_days` or cleared when it is `None`
+    async fn clone_courses(
+        &self,
+        from: (i64, i64),
+        to: (i64, i64),
+        fk_term_id: Option<i64>,
+        shift_days: Option<i64>,
+        actor: &Actor,
+    ) -> Result<Vec<ClonedCourse>, Error>;
+
+    async fn all_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error>;
+    async fn assessment(&self, guild_id: i64, id: i64) -> Result<Option<Assessment>, Error>;
+    async fn course_assessments(&self, fk_course_id: i64) -> Result<Vec<Assessment>, Error>;
+    /// Returns the number of inserted rows, the id of `assessment` is ignored.
+    /// Nothing is inserted if the course is not a live course of the actor's guild
+    async fn insert_assessment(&self, assessment: &Assessment, actor: &Actor)
+        -> Result<u64, Error>;
+    async fn delete_assessment(&self, id: i64, actor: &Actor) -> Result<Option<Assessment>, Error>;
+
+    /// Sessions of live courses
+    async fn all_sessions(&self, guild_id: i64) -> Result<Vec<Session>, Error>;
+    async fn session(&self, guild_id: i64, id: i64) -> Result<Option<Session>, Error>;
+    /// Weekly sessions ordered by weekday and start time
+    async fn course_sessions(&self, fk_course_id: i64) -> Result<Vec<Session>, Error>;
+    /// Returns the number of inserted rows, none for deleted courses or those of other guilds.
+    /// The id of `session` is ignored
+    async fn insert_session(&self, session: &Session, actor: &Actor) -> Result<u64, Error>;
+    async fn delete_session(&self, id: i64, actor: &Actor) -> Result<Option<Session>, Error>;
+
+    /// Newest first
+    async fn deleted_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error>;
+    /// Assessments deleted on their own, those deleted with their course are restored with it
+    async fn deleted_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error>;
+    /// Also restores the assessments deleted along with the course
+    async fn restore_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error>;
+    async fn restore_assessment(&self, id: i64, actor: &Actor)
+        -> Result<Option<Assessment>, Error>;
+    /// Permanently deletes what has been in the trash for longer than the retention period,
+    /// returns the number of purged courses and assessments
+    async fn purge_trash(&self, retention_days: i64) -> Result<u64, Error>;
+
+    async fn is_subscribed(&self, user_id: i64, fk_course_id: i64) -> Result<bool, Error>;
+    /// Live courses of the guild the user is enrolled in, ordered by id
+    async fn subscribed_courses(&self, guild_id: i64, user_id: i64) -> Result<Vec<Course>, Error>;
+    /// Enrolls or unenrolls the actor
+    async fn set_subscribed(
+        &self,
+        fk_course_id: i64,
+        subscribed: bool,
+        actor: &Actor,
+    ) -> Result<(), Error>;
+
+    /// Ordered by start date
+    async fn all_terms(&self, guild_id: i64) -> Result<Vec<Term>, Error>;
+    async fn term(&self, guild_id: i64, id: i64) -> Result<Option<Term>, Error>;
+    async fn active_term(&self, guild_id: i64) -> Result<Option<Term>, Error>;
+    /// The term is added to the actor's guild, inserting an active term deactivates the previous one
+    async fn insert_term(&self, term: &Term, actor: &Actor) -> Result<u64, Error>;
+    /// Makes the term the only active one, unarchiving it if needed
+    async fn activate_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error>;
+    async fn archive_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error>;
+
+    /// Grants of the guild ordered by course
+    async fn course_editors(&self, guild_id: i64) -> Result<Vec<CourseEditor>, Error>;
+    async fn insert_course_editor(
+        &self,
+        editor: &CourseEditor,
+        actor: &Actor,
+    ) -> Result<u64, Error>;
+    /// Only grants of the actor's guild are deleted
+    async fn delete_course_editor(
+        &self,
+        id: i64,
+        actor: &Actor,
+    ) -> Result<Option<CourseEditor>, Error>;
+
+    /// Newest entries of the guild first, filters that are `None` match everything
+    async fn audit_entries(
+        &self,
+        guild_id: i64,
+        entity: Option<&str>,
+        entity_id: Option<i64>,
+        actor_id: Option<i64>,
+    ) -> Result<Vec<AuditEntry>, Error>;
+    /// Reverts an entry of the actor's guild, the revert itself is recorded as a new entry
+    async fn undo(&self, id: i64, actor: &Actor) -> Result<AuditEntry, Error>;
+
+    /// Links of the guild ordered by course
+    async fn course_roles(&self, guild_id: i64) -> Result<Vec<CourseRole>, Error>;
+    /// Links of every guild that have a reminder channel
+    async fn reminder_channels(&self) -> Result<Vec<CourseRole>, Error>;
+    /// Replaces the course's previous link in the actor's guild
+    async fn set_course_role(&self, role: &CourseRole, actor: &Actor) -> Result<(), Error>;
+    async fn delete_course_role(
+        &self,
+        fk_course_id: i64,
+        actor: &Actor,
+    ) -> Result<Option<CourseRole>, Error>;
+    /// (user id, course id) of everyone enrolled in a live course linked to a role of the guild
+    async fn linked_subscriptions(&self, guild_id: i64) -> Result<Vec<(i64, i64)>, Error>;
+    async fn is_reminder_sent(
+        &self,
+        guild_id: i64,
+        fk_assessment_id: i64,
+        date: &str,
+    ) -> Result<bool, Error>;
+    async fn record_reminder(
+        &self,
+        guild_id: i64,
+        fk_assessment_id: i64,
+        date: &str,
+    ) -> Result<(), Error>;
+
+    /// Cohorts of the guild ordered by name
+    async fn cohorts(&self, guild_id: i64) -> Result<Vec<Cohort>, Error>;
+    /// Names are matched ignoring case
+    async fn find_cohort(&self, guild_id: i64, name: &str) -> Result<Option<Cohort>, Error>;
+    async fn insert_cohort(&self, name: &str, actor: &Actor) -> Result<Cohort, Error>;
+    /// Also removes the cohort's members and dates, only cohorts of the actor's guild are deleted
+    async fn delete_cohort(&self, id: i64, actor: &Actor) -> Result<(), Error>;
+    async fn member_cohort(&self, guild_id: i64, user_id: i64) -> Result<Option<Cohort>, Error>;
+    /// `None` leaves the member's cohort
+    async fn set_member_cohort(
+        &self,
+        guild_id: i64,
+        user_id: i64,
+        fk_cohort_id: Option<i64>,
+    ) -> Result<(), Error>;
+    /// Assessment assignments of the guild's cohorts
+    async fn cohort_dates(&self, guild_id: i64) -> Result<Vec<CohortDates>, Error>;
+    /// Replaces the previous dates of the assessment for the cohort
+    async fn set_cohort_dates(&self, dates: &CohortDates, actor: &Actor) -> Result<(), Error>;
+    /// Returns whether the assessment was assigned to the cohort
+    async fn delete_cohort_dates(
+        &self,
+        fk_assessment_id: i64,
+        fk_cohort_id: i64,
+        actor: &Actor,
+    ) -> Result<bool, Error>;
+
+    /// The user's own theme name, otherwise the guild's
+    async fn theme_preference(&self, guild_id: i64, user_id: i64) -> Result<Option<String>, Error>;
+    /// Guild defaults use user id 0, personal choices use guild id 0. `None` clears the preference
+    async fn set_theme_preference(
+        &self,
+        guild_id: i64,
+        user_id: i64,
+        theme: Option<&str>,
+    ) -> Result<(), Error>;
+
+    /// Live courses and assessments matching every word of the query as a prefix,
+    /// best matches first. Code matches rank above name matches
+    async fn search(&self, guild_id: i64, query: &str, limit: i64)
+        -> Result<Vec<SearchHit>, Error>;
+
+    /// Courses of the guild's given term, or of its active term if none is given.
+    /// Falls back to all of the guild's courses when there is no active term yet.
+    async fn viewed_courses(
+        &self,
+        guild_id: i64,
+        fk_term_id: Option<i64>,
+    ) -> Result<(Option<Term>, Vec<Course>), Error> {
+        let term = match fk_term_id {
+            Some(id) => self.term(guild_id, id).await?,
+            None => self.active_term(guild_id).await?,
+        };
+
+        let courses = match &term {
+            Some(term) => self.term_courses(guild_id, term.id).await?,
+            None if fk_term_id.is_some() => Vec::new(),
+            None => self.all_courses(guild_id).await?,
+        };
+
+        Ok((term, courses))
+    }
+}
+
+/// Picks the backend by the scheme of the configured database url
+pub async fn connect() -> Result<Arc<dyn Storage>, Error> {
+    let database_url = &config::get().database_url;
+
+    if config::is_postgres_url(database_url) {
+        #[cfg(feature = "postgres")]
+        {
+            let pool = postgres_utils::establish_connection(database_url).await?;
+            return Ok(Arc::new(postgres_utils::PostgresStorage::new(pool)));
+        }
+        #[cfg(not(feature = "postgres"))]
+        return Err(Error::Validation(String::from(
+            "PostgreSQL support requires building with --features postgres",
+        )));
+    }
+
+    let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    sqlx::migrate!("migrations/sqlite").run(&pool).await?;
+    Ok(Arc::new(SqliteStorage::new(pool)))
+}
+
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub fn new(pool: SqlitePool) -> Self {
+        SqliteStorage { pool }
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn all_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error> {
+        Ok(database_utils::get_all_courses(&self.pool, guild_id).await?)
+    }
+
+    async fn term_courses(&self, guild_id: i64, fk_term_id: i64) -> Result<Vec<Course>, Error> {
+        Ok(database_utils::get_term_courses(&self.pool, guild_id, fk_term_id).await?)
+    }
+
+    async fn course(&self, guild_id: i64, id: i64) -> Result<Option<Course>, Error> {
+        Ok(database_utils::get_course(&self.pool, guild_id, id).await?)
+    }
+
+    async fn insert_course(&self, course: &Course, actor: &Actor) -> Result<u64, Error> {
+        Ok(database_utils::insert_course(&self.pool, course, actor).await?)
+    }
+
+    async fn update_course(&self, course: &Course, actor: &Actor) -> Result<Option<Course>, Error> {
+        Ok(database_utils::update_course(&self.pool, course, actor).await?)
+    }
+
+    async fn delete_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error> {
+        Ok(database_utils::delete_course(&self.pool, id, actor).await?)
+    }
+
+    async fn clone_courses(
+        &self,
+        from: (i64, i64),
+        to: (i64, i64),
+        fk_term_id: Option<i64>,
+        shift_days: Option<i64>,
+        actor: &Actor,
+    ) -> Result<Vec<ClonedCourse>, Error> {
+        Ok(
+            database_utils::clone_courses(&self.pool, from, to, fk_term_id, shift_days, actor)
+                .await?,
+        )
+    }
+
+    async fn all_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error> {
+        Ok(database_utils::get_all_assessments(&self.pool, guild_id).await?)
+    }
+
+    async fn assessment(&self, guild_id: i64, id: i64) -> Result<Option<Assessment>, Error> {
+        Ok(database_utils::get_assessment(&self.pool, guild_id, id).await?)
+    }
+
+    async fn course_assessments(&self, fk_course_id: i64) -> Result<Vec<Assessment>, Error> {
+        Ok(database_utils::get_course_assessments(&self.pool, fk_course_id).await?)
+    }
+
+    async fn insert_assessment(
+        &self,
+        assessment: &Assessment,
+        actor: &Actor,
+    ) -> Result<u64, Error> {
+        Ok(database_utils::insert_assessment(&self.pool, assessment, actor).await?)
+    }
+
+    async fn delete_assessment(&self, id: i64, actor: &Actor) -> Result<Option<Assessment>, Error> {
+        Ok(database_utils::delete_assessment(&self.pool, id, actor).await?)
+    }
+
+    async fn all_sessions(&self, guild_id: i64) -> Result<Vec<Session>, Error> {
+        Ok(database_utils::get_all_sessions(&self.pool, guild_id).await?)
+    }
+
+    async fn session(&self, guild_id: i64, id: i64) -> Result<Option<Session>, Error> {
+        Ok(database_utils::get_session(&self.pool, guild_id, id).await?)
+    }
+
+    async fn course_sessions(&self, fk_course_id: i64) -> Result<Vec<Session>, Error> {
+        Ok(database_utils::get_course_sessions(&self.pool, fk_course_id).await?)
+    }
+
+    async fn insert_session(&self, session: &Session, actor: &Actor) -> Result<u64, Error> {
+        Ok(database_utils::insert_session(&self.pool, session, actor).await?)
+    }
+
+    async fn delete_session(&self, id: i64, actor: &Actor) -> Result<Option<Session>, Error> {
+        Ok(database_utils::delete_session(&self.pool, id, actor).await?)
+    }
+
+    async fn deleted_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error> {
+        Ok(database_utils::get_deleted_courses(&self.pool, guild_id).await?)
+    }
+
+    async fn deleted_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error> {
+        Ok(database_utils::get_deleted_assessments(&self.pool, guild_id).await?)
+    }
+
+    async fn restore_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error> {
+        Ok(database_utils::restore_deleted_course(&self.pool, id, actor).await?)
+    }
+
+    async fn restore_assessment(
+        &self,
+        id: i64,
+        actor: &Actor,
+    ) -> Result<Option<Assessment>, Error> {
+        Ok(database_utils::restore_deleted_assessment(&self.pool, id, actor).await?)
+    }
+
+    async fn purge_trash(&self, retention_days: i64) -> Result<u64, Error> {
+        Ok(database_utils::purge_trash(&self.pool, retention_days).await?)
+    }
+
+    async fn is_subscribed(&self, user_id: i64, fk_course_id: i64) -> Result<bool, Error> {
+        Ok(database_utils::is_subscribed(&self.pool, user_id, fk_course_id).await?)
+    }
+
+    async fn subscribed_courses(&self, guild_id: i64, user_id: i64) -> Result<Vec<Course>, Error> {
+        Ok(database_utils::get_subscribed_courses(&self.pool, guild_id, user_id).await?)
+    }
+
+    async fn set_subscribed(
+        &self,
+        fk_course_id: i64,
+        subscribed: bool,
+        actor: &Actor,
+    ) -> Result<(), Error> {
+        Ok(database_utils::set_subscribed(&self.pool, fk_course_id, subscribed, actor).await?)
+    }
+
+    async fn all_terms(&self, guild_id: i64) -> Result<Vec<Term>, Error> {
+        Ok(database_utils::get_all_terms(&self.pool, guild_id).await?)
+    }
+
+    async fn term(&self, guild_id: i64, id: i64) -> Result<Option<Term>, Error> {
+        Ok(database_utils::get_term(&self.pool, guild_id, id).await?)
+    }
+
+    async fn active_term(&self, guild_id: i64) -> Result<Option<Term>, Error> {
+        Ok(database_utils::get_active_term(&self.pool, guild_id).await?)
+    }
+
+    async fn insert_term(&self, term: &Term, actor: &Actor) -> Result<u64, Error> {
+        Ok(database_utils::insert_term(&self.pool, term, actor).await?)
+    }
+
+    async fn activate_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error> {
+        Ok(database_utils::activate_term(&self.pool, id, actor).await?)
+    }
+
+    async fn archive_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error> {
+        Ok(database_utils::archive_term(&self.pool, id, actor).await?)
+    }
+
+    async fn course_editors(&self, guild_id: i64) -> Result<Vec<CourseEditor>, Error> {
+        Ok(database_utils::get_course_editors(&self.pool, guild_id).await?)
+    }
+
+    async fn insert_course_editor(
+        &self,
+        editor: &CourseEditor,
+        actor: &Actor,
+    ) -> Result<u64, Error> {
+        Ok(database_utils::insert_course_editor(&self.pool, editor, actor).await?)
+    }
+
+    async fn delete_course_editor(
+        &self,
+        id: i64,
+        actor: &Actor,
+    ) -> Result<Option<CourseEditor>, Error> {
+        Ok(database_utils::delete_course_editor(&self.pool, id, actor).await?)
+    }
+
+    async fn audit_entries(
+        &self,
+        guild_id: i64,
+        entity: Option<&str>,
+        entity_id: Option<i64>,
+        actor_id: Option<i64>,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        Ok(audit::get_audit_entries(&self.pool, guild_id, entity, entity_id, actor_id).await?)
+    }
+
+    async fn undo(&self, id: i64, actor: &Actor) -> Result<AuditEntry, Error> {
+        audit::undo(&self.pool, id, actor).await
+    }
+
+    async fn course_roles(&self, guild_id: i64) -> Result<Vec<CourseRole>, Error> {
+        Ok(database_utils::get_course_roles(&self.pool, guild_id).await?)
+    }
+
+    async fn reminder_channels(&self) -> Result<Vec<CourseRole>, Error> {
+        Ok(database_utils::get_reminder_channels(&self.pool).await?)
+    }
+
+    async fn set_course_role(&self, role: &CourseRole, actor: &Actor) -> Result<(), Error> {
+        Ok(database_utils::set_course_role(&self.pool, role, actor).await?)
+    }
+
+    async fn delete_course_role(
+        &self,
+        fk_course_id: i64,
+        actor: &Actor,
+    ) -> Result<Option<CourseRole>, Error> {
+        Ok(database_utils::delete_course_role(&self.pool, fk_course_id, actor).await?)
+    }
+
+    async fn linked_subscriptions(&self, guild_id: i64) -> Result<Vec<(i64, i64)>, Error> {
+        Ok(database_utils::get_linked_subscriptions(&self.pool, guild_id).await?)
+    }
+
+    async fn is_reminder_sent(
+        &self,
+        guild_id: i64,
+        fk_assessment_id: i64,
+        date: &str,
+    ) -> Result<bool, Error> {
+        Ok(database_utils::is_reminder_sent(&self.pool, guild_id, fk_assessment_id, date).await?)
+    }
+
+    async fn record_reminder(
+        &self,
+        guild_id: i64,
+        fk_assessment_id: i64,
+        date: &str,
+    ) -> Result<(), Error> {
+        Ok(database_utils::record_reminder(&self.pool, guild_id, fk_assessment_id, date).await?)
+    }
+
+    async fn cohorts(&self, guild_id: i64) -> Result<Vec<Cohort>, Error> {
+        Ok(database_utils::get_cohorts(&self.pool, guild_id).await?)
+    }
+
+    async fn find_cohort(&self, guild_id: i64, name: &str) -> Result<Option<Cohort>, Error> {
+        Ok(database_utils::find_cohort(&self.pool, guild_id, name).await?)
+    }
+
+    async fn insert_cohort(&self, name: &str, actor: &Actor) -> Result<Cohort, Error> {
+        Ok(database_utils::insert_cohort(&self.pool, name, actor).await?)
+    }
+
+    async fn delete_cohort(&self, id: i64, actor: &Actor) -> Result<(), Error> {
+        Ok(database_utils::delete_cohort(&self.pool, id, actor).await?)
+    }
+
+    async fn member_cohort(&self, guild_id: i64, user_id: i64) -> Result<Option<Cohort>, Error> {
+        Ok(database_utils::get_member_cohort(&self.pool, guild_id, user_id).await?)
+    }
+
+    async fn set_member_cohort(
+        &self,
+        guild_id: i64,
+        user_id: i64,
+        fk_cohort_id: Option<i64>,
+    ) -> Result<(), Error> {
+        Ok(database_utils::set_member_cohort(&self.pool, guild_id, user_id, fk_cohort_id).await?)
+    }
+
+    async fn cohort_dates(&self, guild_id: i64) -> Result<Vec<CohortDates>, Error> {
+        Ok(database_utils::get_cohort_dates(&self.pool, guild_id).await?)
+    }
+
+    async fn set_cohort_dates(&self, dates: &CohortDates, actor: &Actor) -> Result<(), Error> {
+        Ok(database_utils::set_cohort_dates(&self.pool, dates, actor).await?)
+    }
+
+    async fn delete_cohort_dates(
+        &self,
+        fk_assessment_id: i64,
+        fk_cohort_id: i64,
+        actor: &Actor,
+    ) -> Result<bool, Error> {
+        Ok(
+            database_utils::delete_cohort_dates(&self.pool, fk_assessment_id, fk_cohort_id, actor)
+                .await?,
+        )
+    }
+
+    async fn theme_preference(&self, guild_id: i64, user_id: i64) -> Result<Option<String>, Error> {
+        Ok(database_utils::get_theme_preference(&self.pool, guild_id, user_id).await?)
+    }
+
+    async fn set_theme_preference(
+        &self,
+        guild_id: i64,
+        user_id: i64,
+        theme: Option<&str>,
+    ) -> Result<(), Error> {
+        Ok(database_utils::set_theme_preference(&self.pool, guild_id, user_id, theme).await?)
+    }
+
+    async fn search(
+        &self,
+        guild_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Error> {
+        Ok(database_utils::search(&self.pool, guild_id, query, limit).await?)
+    }
+}
+
+/// In-memory backend for tests, changes are not recorded in the audit log
+#[cfg(test)]
+pub mod memory {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use chrono::Utc;
+
+    use super::Storage;
+    use crate::audit::{Actor, AuditEntry};
+    use crate::database_utils::{
+        self, Assessment, ClonedCourse, Cohort, CohortDates, Course, CourseEditor, CourseRole,
+        SearchHit, Session, Term,
+    };
+    use crate::error::Error;
+    use crate::utils;
+
+    #[derive(Default)]
+    struct MemoryState {
+        courses: Vec<Course>,
+        assessments: Vec<Assessment>,
+        terms: Vec<Term>,
+        sessions: Vec<Session>,
+        editors: Vec<CourseEditor>,
+        subscriptions: HashSet<(i64, i64)>,
+        course_roles: Vec<CourseRole>,
+        // (guild id, assessment id, date)
+        sent_reminders: HashSet<(i64, i64, String)>,
+        cohorts: Vec<Cohort>,
+        // (guild id, user id) -> cohort id
+        cohort_members: HashMap<(i64, i64), i64>,
+        cohort_dates: Vec<CohortDates>,
+        themes: HashMap<(i64, i64), String>,
+        last_id: i64,
+    }
+
+    impl MemoryState {
+        fn next_id(&mut self) -> i64 {
+            self.last_id += 1;
+            self.last_id
+        }
+
+        /// Whether the course, live or in the trash, belongs to the guild
+        fn is_guild_course(&self, fk_course_id: i64, guild_id: i64) -> bool {
+            self.courses
+                .iter()
+                .any(|course| course.id == fk_course_id && course.guild_id == guild_id)
+        }
+
+        fn is_guild_assessment(&self, id: i64, guild_id: i64) -> bool {
+            self.assessments.iter().any(|assessment| {
+                assessment.id == id && self.is_guild_course(assessment.fk_course_id, guild_id)
+            })
+        }
+
+        fn is_guild_session(&self, id: i64, guild_id: i64) -> bool {
+            self.sessions.iter().any(|session| {
+                session.id == id && self.is_guild_course(session.fk_course_id, guild_id)
+            })
+        }
+    }
+
+    #[derive(Default)]
+    pub struct MemoryStorage {
+        state: Mutex<MemoryState>,
+    }
+
+    impl MemoryStorage {
+        pub fn new() -> Self {
+            MemoryStorage::default()
+        }
+
+        /// Adds a term with a fresh id and returns it
+        pub fn add_term(&self, term: Term) -> Term {
+            self.with_state(|state| {
+                let term = Term {
+                    id: state.next_id(),
+                    ..term
+                };
+                state.terms.push(term.clone());
+                term
+            })
+        }
+
+        /// Adds a session with a fresh id and returns it
+        pub fn add_session(&self, session: Session) -> Session {
+            self.with_state(|state| {
+                let session = Session {
+                    id: state.next_id(),
+                    ..session
+                };
+                state.sessions.push(session.clone());
+                session
+            })
+        }
+
+        /// Adds a cohort with a fresh id and returns it
+        pub fn add_cohort(&self, guild_id: i64, name: &str) -> Cohort {
+            self.with_state(|state| {
+                let cohort = Cohort {
+                    id: state.next_id(),
+                    guild_id,
+                    name: name.to_string(),
+                };
+                state.cohorts.push(cohort.clone());
+                cohort
+            })
+        }
+
+        pub fn join_cohort(&self, cohort: &Cohort, user_id: i64) {
+            self.with_state(|state| {
+                state
+                    .cohort_members
+                    .insert((cohort.guild_id, user_id), cohort.id)
+            });
+        }
+
+        pub fn add_cohort_dates(&self, dates: CohortDates) {
+            self.with_state(|state| state.cohort_dates.push(dates));
+        }
+
+        fn with_state<T>(&self, f: impl FnOnce(&mut MemoryState) -> T) -> T {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut state)
+        }
+    }
+
+    fn now() -> String {
+        Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    fn set_active(
+        state: &mut MemoryState,
+        guild_id: i64,
+        id: i64,
+        active: bool,
+        archived: bool,
+    ) -> Option<Term> {
+        let term = state
+            .terms
+            .iter_mut()
+            .find(|term| term.id == id && term.guild_id == guild_id)?;
+        term.active = active;
+        term.archived = archived;
+        Some(term.clone())
+    }
+
+    #[async_trait]
+    impl Storage for MemoryStorage {
+        async fn all_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .courses
+                    .iter()
+                    .filter(|course| course.guild_id == guild_id && course.deleted_at.is_none())
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn term_courses(&self, guild_id: i64, fk_term_id: i64) -> Result<Vec<Course>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .courses
+                    .iter()
+                    .filter(|course| course.guild_id == guild_id && course.deleted_at.is_none())
+                    .filter(|course| course.fk_term_id == Some(fk_term_id))
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn course(&self, guild_id: i64, id: i64) -> Result<Option<Course>, Error> {
+            Ok(self
+                .all_courses(guild_id)
+                .await?
+                .into_iter()
+                .find(|course| course.id == id))
+        }
+
+        async fn insert_course(&self, course: &Course, actor: &Actor) -> Result<u64, Error> {
+            self.with_state(|state| {
+                let course = Course {
+                    id: state.next_id(),
+                    deleted_at: None,
+                    guild_id: actor.guild_id,
+                    ..course.clone()
+                };
+                state.courses.push(course);
+            });
+
+            Ok(1)
+        }
+
+        async fn update_course(
+            &self,
+            course: &Course,
+            actor: &Actor,
+        ) -> Result<Option<Course>, Error> {
+            Ok(self.with_state(|state| {
+                let current = state.courses.iter_mut().find(|current| {
+                    current.id == course.id
+                        && current.guild_id == actor.guild_id
+                        && current.deleted_at.is_none()
+                })?;
+                *current = Course {
+                    fk_term_id: current.fk_term_id,
+                    deleted_at: None,
+                    guild_id: current.guild_id,
+                    ..course.clone()
+                };
+                Some(current.clone())
+            }))
+        }
+
+        async fn delete_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error> {
+            Ok(self.with_state(|state| {
+                let deleted_at = now();
+                let course = state.courses.iter_mut().find(|course| {
+                    course.id == id
+                        && course.guild_id == actor.guild_id
+                        && course.deleted_at.is_none()
+                })?;
+                course.deleted_at = Some(deleted_at.clone());
+                let course = course.clone();
+
+                for assessment in state.assessments.iter_mut() {
+                    if assessment.fk_course_id == id && assessment.deleted_at.is_none() {
+                        assessment.deleted_at = Some(deleted_at.clone());
+                    }
+                }
+
+                Some(course)
+            }))
+        }
+
+        async fn clone_courses(
+            &self,
+            from: (i64, i64),
+            to: (i64, i64),
+            fk_term_id: Option<i64>,
+            shift_days: Option<i64>,
+            actor: &Actor,
+        ) -> Result<Vec<ClonedCourse>, Error> {
+            let shift = |date: &str| match shift_days {
+                Some(days) => utils::shift_date(date, days),
+                None => String::new(),
+            };
+
+            Ok(self.with_state(|state| {
+                let courses: Vec<Course> = state
+                    .courses
+                    .iter()
+                    .filter(|course| course.guild_id == actor.guild_id)
+                    .filter(|course| course.deleted_at.is_none())
+                    .filter(|course| (course.semester, course.year) == from)
+                    .cloned()
+                    .collect();
+
+                let mut cloned = Vec::new();
+                for course in courses {
+                    let exists = state.courses.iter().any(|existing| {
+                        existing.guild_id == actor.guild_id
+                            && existing.deleted_at.is_none()
+                            && existing.code.eq_ignore_ascii_case(&course.code)
+                            && (existing.semester, existing.year) == to
+                    });
+                    if exists {
+                        cloned.push(ClonedCourse {
+                            old_id: course.id,
+                            new_id: None,
+                            code: course.code,
+                            assessments: 0,
+                            sessions: 0,
+                        });
+                        continue;
+                    }
+
+                    let new_id = state.next_id();
+                    state.courses.push(Course {
+                        id: new_id,
+                        semester: to.0,
+                        year: to.1,
+                        fk_term_id,
+                        ..course.clone()
+                    });
+
+                    let assessments: Vec<Assessment> = state
+                        .assessments
+                        .iter()
+                        .filter(|assessment| {
+                            assessment.fk_course_id == course.id && assessment.deleted_at.is_none()
+                        })
+                        .cloned()
+                        .collect();
+                    for assessment in &assessments {
+                        let id = state.next_id();
+                        state.assessments.push(Assessment {
+                            id,
+                            take1: shift(&assessment.take1),
+                            retake1: shift(&assessment.retake1),
+                            retake2: shift(&assessment.retake2),
+                            fk_course_id: new_id,
+                            ..assessment.clone()
+                        });
+                    }
+
+                    let sessions: Vec<Session> = state
+                        .sessions
+                        .iter()
+                        .filter(|session| session.fk_course_id == course.id)
+                        .cloned()
+                        .collect();
+                    for session in &sessions {
+                        let id = state.next_id();
+                        state.sessions.push(Session {
+                            id,
+                            fk_course_id: new_id,
+                            ..session.clone()
+                        });
+                    }
+
+                    cloned.push(ClonedCourse {
+                        old_id: course.id,
+                        new_id: Some(new_id),
+                        code: course.code,
+                        assessments: assessments.len(),
+                        sessions: sessions.len(),
+                    });
+                }
+
+                cloned
+            }))
+        }
+
+        async fn all_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error> {
+            let courses = self.all_courses(guild_id).await?;
+            Ok(self.with_state(|state| {
+                state
+                    .assessments
+                    .iter()
+                    .filter(|assessment| assessment.deleted_at.is_none())
+                    .filter(|assessment| {
+                        courses
+                            .iter()
+                            .any(|course| course.id == assessment.fk_course_id)
+                    })
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn assessment(&self, guild_id: i64, id: i64) -> Result<Option<Assessment>, Error> {
+            Ok(self
+                .all_assessments(guild_id)
+                .await?
+                .into_iter()
+                .find(|assessment| assessment.id == id))
+        }
+
+        async fn course_assessments(&self, fk_course_id: i64) -> Result<Vec<Assessment>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .assessments
+                    .iter()
+                    .filter(|assessment| assessment.deleted_at.is_none())
+                    .filter(|assessment| assessment.fk_course_id == fk_course_id)
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn insert_assessment(
+            &self,
+            assessment: &Assessment,
+            actor: &Actor,
+        ) -> Result<u64, Error> {
+            if self
+                .course(actor.guild_id, assessment.fk_course_id)
+                .await?
+                .is_none()
+            {
+                return Ok(0);
+            }
+            self.with_state(|state| {
+                let assessment = Assessment {
+                    id: state.next_id(),
+                    deleted_at: None,
+                    ..assessment.clone()
+                };
+                state.assessments.push(assessment);
+            });
+
+            Ok(1)
+        }
+
+        async fn delete_assessment(
+            &self,
+            id: i64,
+            actor: &Actor,
+        ) -> Result<Option<Assessment>, Error> {
+            Ok(self.with_state(|state| {
+                if !state.is_guild_assessment(id, actor.guild_id) {
+                    return None;
+                }
+                let assessment = state
+                    .assessments
+                    .iter_mut()
+                    .find(|assessment| assessment.id == id && assessment.deleted_at.is_none())?;
+                assessment.deleted_at = Some(now());
+                Some(assessment.clone())
+            }))
+        }
+
+        async fn all_sessions(&self, guild_id: i64) -> Result<Vec<Session>, Error> {
+            let courses = self.all_courses(guild_id).await?;
+            Ok(self.with_state(|state| {
+                state
+                    .sessions
+                    .iter()
+                    .filter(|session| {
+                        courses
+                            .iter()
+                            .any(|course| course.id == session.fk_course_id)
+                    })
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn session(&self, guild_id: i64, id: i64) -> Result<Option<Session>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .sessions
+                    .iter()
+                    .find(|session| session.id == id)
+                    .filter(|session| state.is_guild_course(session.fk_course_id, guild_id))
+                    .cloned()
+            }))
+        }
+
+        async fn insert_session(&self, session: &Session, actor: &Actor) -> Result<u64, Error> {
+            if self
+                .course(actor.guild_id, session.fk_course_id)
+                .await?
+                .is_none()
+            {
+                return Ok(0);
+            }
+            self.add_session(session.clone());
+
+            Ok(1)
+        }
+
+        async fn delete_session(&self, id: i64, actor: &Actor) -> Result<Option<Session>, Error> {
+            Ok(self.with_state(|state| {
+                if !state.is_guild_session(id, actor.guild_id) {
+                    return None;
+                }
+                let index = state.sessions.iter().position(|session| session.id == id)?;
+                Some(state.sessions.remove(index))
+            }))
+        }
+
+        async fn deleted_courses(&self, guild_id: i64) -> Result<Vec<Course>, Error> {
+            Ok(self.with_state(|state| {
+                let mut courses: Vec<Course> = state
+                    .courses
+                    .iter()
+                    .filter(|course| course.guild_id == guild_id && course.deleted_at.is_some())
+                    .cloned()
+                    .collect();
+                courses.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+                courses
+            }))
+        }
+
+        async fn deleted_assessments(&self, guild_id: i64) -> Result<Vec<Assessment>, Error> {
+            let courses = self.all_courses(guild_id).await?;
+            Ok(self.with_state(|state| {
+                let mut assessments: Vec<Assessment> = state
+                    .assessments
+                    .iter()
+                    .filter(|assessment| assessment.deleted_at.is_some())
+                    .filter(|assessment| {
+                        courses
+                            .iter()
+                            .any(|course| course.id == assessment.fk_course_id)
+                    })
+                    .cloned()
+                    .collect();
+                assessments.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+                assessments
+            }))
+        }
+
+        async fn restore_course(&self, id: i64, actor: &Actor) -> Result<Option<Course>, Error> {
+            Ok(self.with_state(|state| {
+                let course = state.courses.iter_mut().find(|course| {
+                    course.id == id
+                        && course.guild_id == actor.guild_id
+                        && course.deleted_at.is_some()
+                })?;
+                let deleted_at = course.deleted_at.take();
+                let course = course.clone();
+
+                for assessment in state.assessments.iter_mut() {
+                    if assessment.fk_course_id == id && assessment.deleted_at == deleted_at {
+                        assessment.deleted_at = None;
+                    }
+                }
+
+                Some(course)
+            }))
+        }
+
+        async fn restore_assessment(
+            &self,
+            id: i64,
+            actor: &Actor,
+        ) -> Result<Option<Assessment>, Error> {
+            Ok(self.with_state(|state| {
+                if !state.is_guild_assessment(id, actor.guild_id) {
+                    return None;
+                }
+                let assessment = state
+                    .assessments
+                    .iter_mut()
+                    .find(|assessment| assessment.id == id && assessment.deleted_at.is_some())?;
+                assessment.deleted_at = None;
+                Some(assessment.clone())
+            }))
+        }
+
+        async fn purge_trash(&self, retention_days: i64) -> Result<u64, Error> {
+            let cutoff = (Utc::now() - chrono::Duration::days(retention_days))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string();
+            let expired = |deleted_at: &Option<String>| {
+                deleted_at
+                    .as_ref()
+                    .is_some_and(|deleted_at| *deleted_at < cutoff)
+            };
+
+            Ok(self.with_state(|state| {
+                let purged: Vec<i64> = state
+                    .courses
+                    .iter()
+                    .filter(|course| expired(&course.deleted_at))
+                    .map(|course| course.id)
+                    .collect();
+                let before = state.courses.len() + state.assessments.len();
+
+                state.courses.retain(|course| !purged.contains(&course.id));
+                state.assessments.retain(|assessment| {
+                    !purged.contains(&assessment.fk_course_id) && !expired(&assessment.deleted_at)
+                });
+                let assessments = &state.assessments;
+                state.cohort_dates.retain(|dates| {
+                    assessments
+                        .iter()
+                        .any(|assessment| assessment.id == dates.fk_assessment_id)
+                });
+                state
+                    .sessions
+                    .retain(|session| !purged.contains(&session.fk_course_id));
+                state
+                    .editors
+                    .retain(|editor| !purged.contains(&editor.fk_course_id));
+                state
+                    .subscriptions
+                    .retain(|(_, fk_course_id)| !purged.contains(fk_course_id));
+                state
+                    .course_roles
+                    .retain(|role| !purged.contains(&role.fk_course_id));
+
+                (before - state.courses.len() - state.assessments.len()) as u64
+            }))
+        }
+
+        async fn course_sessions(&self, fk_course_id: i64) -> Result<Vec<Session>, Error> {
+            Ok(self.with_state(|state| {
+                let mut sessions: Vec<Session> = state
+                    .sessions
+                    .iter()
+                    .filter(|session| session.fk_course_id == fk_course_id)
+                    .cloned()
+                    .collect();
+                sessions
+                    .sort_by(|a, b| (a.weekday, &a.start_time).cmp(&(b.weekday, &b.start_time)));
+                sessions
+            }))
+        }
+
+        async fn is_subscribed(&self, user_id: i64, fk_course_id: i64) -> Result<bool, Error> {
+            Ok(self.with_state(|state| state.subscriptions.contains(&(user_id, fk_course_id))))
+        }
+
+        async fn subscribed_courses(
+            &self,
+            guild_id: i64,
+            user_id: i64,
+        ) -> Result<Vec<Course>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .courses
+                    .iter()
+                    .filter(|course| course.guild_id == guild_id && course.deleted_at.is_none())
+                    .filter(|course| state.subscriptions.contains(&(user_id, course.id)))
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn set_subscribed(
+            &self,
+            fk_course_id: i64,
+            subscribed: bool,
+            actor: &Actor,
+        ) -> Result<(), Error> {
+            let subscription = (actor.user_id, fk_course_id);
+            self.with_state(|state| match subscribed {
+                true => state.subscriptions.insert(subscription),
+                false => state.subscriptions.remove(&subscription),
+            });
+
+            Ok(())
+        }
+
+        async fn all_terms(&self, guild_id: i64) -> Result<Vec<Term>, Error> {
+            Ok(self.with_state(|state| {
+                let mut terms: Vec<Term> = state
+                    .terms
+                    .iter()
+                    .filter(|term| term.guild_id == guild_id)
+                    .cloned()
+                    .collect();
+                terms.sort_by(|a, b| a.start_date.cmp(&b.start_date));
+                terms
+            }))
+        }
+
+        async fn term(&self, guild_id: i64, id: i64) -> Result<Option<Term>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .terms
+                    .iter()
+                    .find(|term| term.id == id && term.guild_id == guild_id)
+                    .cloned()
+            }))
+        }
+
+        async fn active_term(&self, guild_id: i64) -> Result<Option<Term>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .terms
+                    .iter()
+                    .find(|term| term.active && term.guild_id == guild_id)
+                    .cloned()
+            }))
+        }
+
+        async fn insert_term(&self, term: &Term, actor: &Actor) -> Result<u64, Error> {
+            if term.active {
+                self.with_state(|state| {
+                    for term in state.terms.iter_mut() {
+                        if term.guild_id == actor.guild_id {
+                            term.active = false;
+                        }
+                    }
+                });
+            }
+            self.add_term(Term {
+                guild_id: actor.guild_id,
+                ..term.clone()
+            });
+
+            Ok(1)
+        }
+
+        async fn activate_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .terms
+                    .iter()
+                    .find(|term| term.id == id && term.guild_id == actor.guild_id)?;
+                for term in state.terms.iter_mut() {
+                    if term.guild_id == actor.guild_id {
+                        term.active = false;
+                    }
+                }
+                set_active(state, actor.guild_id, id, true, false)
+            }))
+        }
+
+        async fn archive_term(&self, id: i64, actor: &Actor) -> Result<Option<Term>, Error> {
+            Ok(self.with_state(|state| set_active(state, actor.guild_id, id, false, true)))
+        }
+
+        async fn course_editors(&self, guild_id: i64) -> Result<Vec<CourseEditor>, Error> {
+            Ok(self.with_state(|state| {
+                let mut editors: Vec<CourseEditor> = state
+                    .editors
+                    .iter()
+                    .filter(|editor| editor.guild_id == guild_id)
+                    .cloned()
+                    .collect();
+                editors.sort_by_key(|editor| editor.fk_course_id);
+                editors
+            }))
+        }
+
+        async fn insert_course_editor(
+            &self,
+            editor: &CourseEditor,
+            _actor: &Actor,
+        ) -> Result<u64, Error> {
+            self.with_state(|state| {
+                let editor = CourseEditor {
+                    id: state.next_id(),
+                    ..editor.clone()
+                };
+                state.editors.push(editor);
+            });
+
+            Ok(1)
+        }
+
+        async fn delete_course_editor(
+            &self,
+            id: i64,
+            actor: &Actor,
+        ) -> Result<Option<CourseEditor>, Error> {
+            Ok(self.with_state(|state| {
+                let index = state
+                    .editors
+                    .iter()
+                    .position(|editor| editor.id == id && editor.guild_id == actor.guild_id)?;
+                Some(state.editors.remove(index))
+            }))
+        }
+
+        async fn audit_entries(
+            &self,
+            _guild_id: i64,
+            _entity: Option<&str>,
+            _entity_id: Option<i64>,
+            _actor_id: Option<i64>,
+        ) -> Result<Vec<AuditEntry>, Error> {
+            Ok(Vec::new())
+        }
+
+        async fn undo(&self, id: i64, _actor: &Actor) -> Result<AuditEntry, Error> {
+            Err(Error::NotFound(format!(
+                "Audit entry not found with id: {}",
+                id
+            )))
+        }
+
+        async fn course_roles(&self, guild_id: i64) -> Result<Vec<CourseRole>, Error> {
+            Ok(self.with_state(|state| {
+                let mut roles: Vec<CourseRole> = state
+                    .course_roles
+                    .iter()
+                    .filter(|role| role.guild_id == guild_id)
+                    .cloned()
+                    .collect();
+                roles.sort_by_key(|role| role.fk_course_id);
+                roles
+            }))
+        }
+
+        async fn reminder_channels(&self) -> Result<Vec<CourseRole>, Error> {
+            Ok(self.with_state(|state| {
+                let mut roles: Vec<CourseRole> = state
+                    .course_roles
+                    .iter()
+                    .filter(|role| role.channel_id.is_some())
+                    .cloned()
+                    .collect();
+                roles.sort_by_key(|role| (role.guild_id, role.fk_course_id));
+                roles
+            }))
+        }
+
+        async fn set_course_role(&self, role: &CourseRole, actor: &Actor) -> Result<(), Error> {
+            self.with_state(|state| {
+                state.course_roles.retain(|current| {
+                    (current.guild_id, current.fk_course_id) != (actor.guild_id, role.fk_course_id)
+                });
+                state.course_roles.push(CourseRole {
+                    guild_id: actor.guild_id,
+                    ..role.clone()
+                });
+            });
+
+            Ok(())
+        }
+
+        async fn delete_course_role(
+            &self,
+            fk_course_id: i64,
+            actor: &Actor,
+        ) -> Result<Option<CourseRole>, Error> {
+            Ok(self.with_state(|state| {
+                let index = state.course_roles.iter().position(|role| {
+                    role.guild_id == actor.guild_id && role.fk_course_id == fk_course_id
+                })?;
+                Some(state.course_roles.remove(index))
+            }))
+        }
+
+        async fn linked_subscriptions(&self, guild_id: i64) -> Result<Vec<(i64, i64)>, Error> {
+            let courses = self.all_courses(guild_id).await?;
+            Ok(self.with_state(|state| {
+                state
+                    .subscriptions
+                    .iter()
+                    .filter(|(_, fk_course_id)| {
+                        courses.iter().any(|course| course.id == *fk_course_id)
+                            && state.course_roles.iter().any(|role| {
+                                role.guild_id == guild_id && role.fk_course_id == *fk_course_id
+                            })
+                    })
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn is_reminder_sent(
+            &self,
+            guild_id: i64,
+            fk_assessment_id: i64,
+            date: &str,
+        ) -> Result<bool, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .sent_reminders
+                    .contains(&(guild_id, fk_assessment_id, date.to_string()))
+            }))
+        }
+
+        async fn record_reminder(
+            &self,
+            guild_id: i64,
+            fk_assessment_id: i64,
+            date: &str,
+        ) -> Result<(), Error> {
+            self.with_state(|state| {
+                state
+                    .sent_reminders
+                    .insert((guild_id, fk_assessment_id, date.to_string()))
+            });
+
+            Ok(())
+        }
+
+        async fn cohorts(&self, guild_id: i64) -> Result<Vec<Cohort>, Error> {
+            Ok(self.with_state(|state| {
+                let mut cohorts: Vec<Cohort> = state
+                    .cohorts
+                    .iter()
+                    .filter(|cohort| cohort.guild_id == guild_id)
+                    .cloned()
+                    .collect();
+                cohorts.sort_by(|a, b| a.name.cmp(&b.name));
+                cohorts
+            }))
+        }
+
+        async fn find_cohort(&self, guild_id: i64, name: &str) -> Result<Option<Cohort>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .cohorts
+                    .iter()
+                    .find(|cohort| {
+                        cohort.guild_id == guild_id && cohort.name.eq_ignore_ascii_case(name)
+                    })
+                    .cloned()
+            }))
+        }
+
+        async fn insert_cohort(&self, name: &str, actor: &Actor) -> Result<Cohort, Error> {
+            Ok(self.add_cohort(actor.guild_id, name))
+        }
+
+        async fn delete_cohort(&self, id: i64, actor: &Actor) -> Result<(), Error> {
+            self.with_state(|state| {
+                if !state
+                    .cohorts
+                    .iter()
+                    .any(|cohort| cohort.id == id && cohort.guild_id == actor.guild_id)
+                {
+                    return;
+                }
+                state.cohort_dates.retain(|dates| dates.fk_cohort_id != id);
+                state
+                    .cohort_members
+                    .retain(|_, fk_cohort_id| *fk_cohort_id != id);
+                state.cohorts.retain(|cohort| cohort.id != id);
+            });
+
+            Ok(())
+        }
+
+        async fn member_cohort(
+            &self,
+            guild_id: i64,
+            user_id: i64,
+        ) -> Result<Option<Cohort>, Error> {
+            Ok(self.with_state(|state| {
+                let cohort_id = state.cohort_members.get(&(guild_id, user_id))?;
+                state
+                    .cohorts
+                    .iter()
+                    .find(|cohort| cohort.id == *cohort_id)
+                    .cloned()
+            }))
+        }
+
+        async fn set_member_cohort(
+            &self,
+            guild_id: i64,
+            user_id: i64,
+            fk_cohort_id: Option<i64>,
+        ) -> Result<(), Error> {
+            self.with_state(|state| match fk_cohort_id {
+                Some(fk_cohort_id) => state
+                    .cohort_members
+                    .insert((guild_id, user_id), fk_cohort_id),
+                None => state.cohort_members.remove(&(guild_id, user_id)),
+            });
+
+            Ok(())
+        }
+
+        async fn cohort_dates(&self, guild_id: i64) -> Result<Vec<CohortDates>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .cohort_dates
+                    .iter()
+                    .filter(|dates| {
+                        state.cohorts.iter().any(|cohort| {
+                            cohort.id == dates.fk_cohort_id && cohort.guild_id == guild_id
+                        })
+                    })
+                    .cloned()
+                    .collect()
+            }))
+        }
+
+        async fn set_cohort_dates(&self, dates: &CohortDates, _actor: &Actor) -> Result<(), Error> {
+            self.with_state(|state| {
+                state.cohort_dates.retain(|current| {
+                    (current.fk_assessment_id, current.fk_cohort_id)
+                        != (dates.fk_assessment_id, dates.fk_cohort_id)
+                });
+                state.cohort_dates.push(dates.clone());
+            });
+
+            Ok(())
+        }
+
+        async fn delete_cohort_dates(
+            &self,
+            fk_assessment_id: i64,
+            fk_cohort_id: i64,
+            _actor: &Actor,
+        ) -> Result<bool, Error> {
+            Ok(self.with_state(|state| {
+                let before = state.cohort_dates.len();
+                state.cohort_dates.retain(|dates| {
+                    (dates.fk_assessment_id, dates.fk_cohort_id) != (fk_assessment_id, fk_cohort_id)
+                });
+                state.cohort_dates.len() < before
+            }))
+        }
+
+        async fn theme_preference(
+            &self,
+            guild_id: i64,
+            user_id: i64,
+        ) -> Result<Option<String>, Error> {
+            Ok(self.with_state(|state| {
+                state
+                    .themes
+                    .get(&(0, user_id))
+                    .or(state.themes.get(&(guild_id, 0)))
+                    .cloned()
+            }))
+        }
+
+        async fn set_theme_preference(
+            &self,
+            guild_id: i64,
+            user_id: i64,
+            theme: Option<&str>,
+        ) -> Result<(), Error> {
+            self.with_state(|state| match theme {
+                Some(theme) => {
+                    state.themes.insert((guild_id, user_id), theme.to_string());
+                }
+                None => {
+                    state.themes.remove(&(guild_id, user_id));
+                }
+            });
+
+            Ok(())
+        }
+
+        async fn search(
+            &self,
+            guild_id: i64,
+            query: &str,
+            limit: i64,
+        ) -> Result<Vec<SearchHit>, Error> {
+            let words: Vec<String> = database_utils::search_words(query)
+                .into_iter()
+                .map(str::to_lowercase)
+                .collect();
+            if words.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            // Counts the words matching a word of the text
+            let matches = |text: &str| {
+                let text = text.to_lowercase();
+                let text_words = database_utils::search_words(&text);
+                words
+                    .iter()
+                    .filter(|word| {
+                        text_words
+                            .iter()
+                            .any(|text_word| text_word.starts_with(*word))
+                    })
+                    .count()
+            };
+
+            let courses = self.all_courses(guild_id).await?;
+            let mut hits: Vec<(usize, SearchHit)> = courses
+                .iter()
+                .map(|course| (course.id, course.id, "course", &course.name, &course.code))
+                .chain(
+                    self.all_assessments(guild_id)
+                        .await?
+                        .iter()
+                        .filter_map(|assessment| {
+                            let course = courses
+                                .iter()
+                                .find(|course| course.id == assessment.fk_course_id)?;
+                            Some((
+                                assessment.id,
+                                course.id,
+                                "assessment",
+                                &assessment.name,
+                                &course.code,
+                            ))
+                        }),
+                )
+                .filter(|(_, _, _, name, code)| {
+                    matches(&format!("{} {}", name, code)) == words.len()
+                })
+                .map(|(entity_id, course_id, kind, name, code)| {
+                    let score = matches(code) * 5 + matches(name);
+                    let hit = SearchHit {
+                        kind: kind.to_string(),
+                        entity_id,
+                        course_id,
+                        name: name.clone(),
+                        code: code.clone(),
+                    };
+                    (score, hit)
+                })
+                .collect();
+
+            hits.sort_by(|(a_score, a), (b_score, b)| {
+                b_score.cmp(a_score).then_with(|| b.kind.cmp(&a.kind))
+            });
+            Ok(hits
+                .into_iter()
+                .map(|(_, hit)| hit)
+                .take(limit as usize)
+                .collect())
+        }
+    }
+}
+
+/// Builders shared by the tests of modules that use `MemoryStorage`
+#[cfg(test)]
+pub mod fixtures {
+    use crate::audit::Actor;
+    use crate::database_utils::{Assessment, Course, Term};
+
+    pub const ACTOR: Actor = Actor {
+        guild_id: 1,
+        user_id: 2,
+    };
+
+    pub fn course(code: &str, fk_term_id: Option<i64>) -> Course {
+        Course {
+            id: 0,
+            name: format!("{} name", code),
+            code: code.to_string(),
+            semester: 1,
+            year: 2024,
+            credit: 6.0,
+            fk_term_id,
+            deleted_at: None,
+            guild_id: ACTOR.guild_id,
+        }
+    }
+
+    pub fn assessment(name: &str, fk_course_id: i64) -> Assessment {
+        Assessment {
+            id: 0,
+            name: name.to_string(),
+            weight: 50.0,
+            take1: String::from("2024-01-10"),
+            retake1: String::from("2024-01-20"),
+            retake2: String::from("2024-01-30"),
+            fk_course_id,
+            deleted_at: None,
+        }
+    }
+
+    pub fn term(name: &str, active: bool) -> Term {
+        Term {
+            id: 0,
+            name: name.to_string(),
+            start_date: String::from("2024-09-01"),
+            end_date: String::from("2025-01-31"),
+            active,
+            archived: false,
+            guild_id: ACTOR.guild_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::*;
+    use super::memory::MemoryStorage;
+    use super::*;
+
+    #[tokio::test]
+    async fn inserted_rows_get_fresh_ids() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        storage
+            .insert_course(&course("DEF", None), &ACTOR)
+            .await
+            .unwrap();
+
+        let courses = storage.all_courses(ACTOR.guild_id).await.unwrap();
+        assert_eq!(courses.len(), 2);
+        assert_ne!(courses[0].id, courses[1].id);
+    }
+
+    #[tokio::test]
+    async fn deleting_a_course_hides_its_assessments() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        storage
+            .insert_assessment(&assessment("Exam", course_id), &ACTOR)
+            .await
+            .unwrap();
+
+        let deleted = storage.delete_course(course_id, &ACTOR).await.unwrap();
+        assert_eq!(deleted.map(|course| course.code), Some(String::from("ABC")));
+
+        assert!(storage
+            .course(ACTOR.guild_id, course_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .all_assessments(ACTOR.guild_id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .delete_course(course_id, &ACTOR)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn courses_of_other_guilds_are_hidden_and_kept() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        let other = Actor {
+            guild_id: 5,
+            ..ACTOR
+        };
+
+        assert!(storage
+            .all_courses(other.guild_id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .course(other.guild_id, course_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .search(other.guild_id, "abc", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage
+                .insert_assessment(&assessment("Exam", course_id), &other)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(storage
+            .delete_course(course_id, &other)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .course(ACTOR.guild_id, course_id)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn sessions_are_not_added_to_deleted_courses() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+        let course_id = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        let session = Session {
+            id: 0,
+            weekday: 1,
+            start_time: String::from("10:00"),
+            end_time: String::from("12:00"),
+            location: String::from("Room 1"),
+            fk_course_id: course_id,
+        };
+        assert_eq!(storage.insert_session(&session, &ACTOR).await.unwrap(), 1);
+
+        storage.delete_course(course_id, &ACTOR).await.unwrap();
+
+        assert_eq!(storage.insert_session(&session, &ACTOR).await.unwrap(), 0);
+        assert!(storage
+            .all_sessions(ACTOR.guild_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn cloning_copies_sessions_and_skips_taken_codes() {
+        let storage = MemoryStorage::new();
+        for code in ["ABC", "DEF"] {
+            storage
+                .insert_course(&course(code, None), &ACTOR)
+                .await
+                .unwrap();
+        }
+        let abc = storage.all_courses(ACTOR.guild_id).await.unwrap()[0].id;
+        storage.add_session(Session {
+            id: 0,
+            weekday: 1,
+            start_time: String::from("10:00"),
+            end_time: String::from("12:00"),
+            location: String::from("Room 1"),
+            fk_course_id: abc,
+        });
+        storage
+            .insert_course(
+                &Course {
+                    year: 2025,
+                    ..course("def", None)
+                },
+                &ACTOR,
+            )
+            .await
+            .unwrap();
+
+        let cloned = storage
+            .clone_courses((1, 2024), (1, 2025), None, None, &ACTOR)
+            .await
+            .unwrap();
+
+        assert_eq!(cloned.len(), 2);
+        let new_abc = cloned[0].new_id.unwrap();
+        assert_eq!(cloned[0].sessions, 1);
+        assert_eq!(storage.course_sessions(new_abc).await.unwrap().len(), 1);
+        assert_eq!(cloned[1].code, "DEF");
+        assert!(cloned[1].new_id.is_none());
+        assert_eq!(storage.all_courses(ACTOR.guild_id).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn term_courses_stay_in_their_guild() {
+        let storage = MemoryStorage::new();
+        let fall = storage.add_term(term("Fall", true));
+        storage
+            .insert_course(&course("ABC", Some(fall.id)), &ACTOR)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage
+                .term_courses(ACTOR.guild_id, fall.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage.term_courses(5, fall.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn viewed_courses_default_to_the_active_term() {
+        let storage = MemoryStorage::new();
+        let old = storage.add_term(term("Old", false));
+        let current = storage.add_term(term("Current", true));
+        storage
+            .insert_course(&course("OLD", Some(old.id)), &ACTOR)
+            .await
+            .unwrap();
+        storage
+            .insert_course(&course("NEW", Some(current.id)), &ACTOR)
+            .await
+            .unwrap();
+
+        let (term, courses) = storage.viewed_courses(ACTOR.guild_id, None).await.unwrap();
+        assert_eq!(term.map(|term| term.id), Some(current.id));
+        assert_eq!(courses.len(), 1);
+        assert_eq!(courses[0].code, "NEW");
+
+        let (_term, courses) = storage
+            .viewed_courses(ACTOR.guild_id, Some(old.id))
+            .await
+            .unwrap();
+        assert_eq!(courses[0].code, "OLD");
+
+        let (term, courses) = storage
+            .viewed_courses(ACTOR.guild_id, Some(999))
+            .await
+            .unwrap();
+        assert!(term.is_none());
+        assert!(courses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn personal_theme_wins_over_the_guild_theme() {
+        let storage = MemoryStorage::new();
+        assert!(storage.theme_preference(1, 2).await.unwrap().is_none());
+
+        storage
+            .set_theme_preference(1, 0, Some("high-contrast"))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.theme_preference(1, 2).await.unwrap().as_deref(),
+            Some("high-contrast")
+        );
+
+        storage
+            .set_theme_preference(0, 2, Some("no-colour"))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.theme_preference(1, 2).await.unwrap().as_deref(),
+            Some("no-colour")
+        );
+
+        storage.set_theme_preference(0, 2, None).await.unwrap();
+        assert_eq!(
+            storage.theme_preference(1, 2).await.unwrap().as_deref(),
+            Some("high-contrast")
+        );
+    }
+
+    #[tokio::test]
+    async fn search_ranks_code_matches_first_and_skips_deleted_rows() {
+        let storage = MemoryStorage::new();
+        for code in ["PHY101", "MTH101", "MTH102"] {
+            storage
+                .insert_course(&course(code, None), &ACTOR)
+                .await
+                .unwrap();
+        }
+        let courses = storage.all_courses(ACTOR.guild_id).await.unwrap();
+        storage
+            .insert_assessment(&assessment("Mth quiz", courses[0].id), &ACTOR)
+            .await
+            .unwrap();
+        storage
+            .insert_assessment(&assessment("Final exam", courses[1].id), &ACTOR)
+            .await
+            .unwrap();
+        storage.delete_course(courses[2].id, &ACTOR).await.unwrap();
+
+        let hits = storage.search(ACTOR.guild_id, "mth", 10).await.unwrap();
+        let found: Vec<(&str, &str)> = hits
+            .iter()
+            .map(|hit| (hit.kind.as_str(), hit.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("course", "MTH101 name"),
+                ("assessment", "Final exam"),
+                ("assessment", "Mth quiz"),
+            ]
+        );
+
+        let hits = storage
+            .search(ACTOR.guild_id, "MTH101, exam", 10)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].code, "MTH101");
+        assert!(storage
+            .search(ACTOR.guild_id, " ,", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn migrations_upgrade_a_database_with_the_original_schema() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(include_str!(
+            "../migrations/sqlite/0001_courses_and_assessments.sql"
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO courses (name, code, semester, year, credit) VALUES
+                ('Algebra', 'MTH101', 1, 2024, 6), ('Algebra again', 'mth101', 1, 2024, 6);
+            INSERT INTO assessments (name, weight, take1, retake1, retake2, fk_course_id)
+            VALUES ('Exam', 100, '2024-01-10', '', '', 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        sqlx::migrate!("migrations/sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let storage = SqliteStorage::new(pool);
+        // Without audit entries the old rows cannot be traced to a guild
+        let codes: Vec<String> = storage
+            .all_courses(0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|course| course.code)
+            .collect();
+        assert_eq!(codes, ["MTH101", "mth101-2"]);
+        assert_eq!(storage.course_assessments(1).await.unwrap().len(), 1);
+        assert_eq!(storage.search(0, "exam", 10).await.unwrap().len(), 1);
+    }
+
+    async fn sqlite_storage() -> SqliteStorage {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("migrations/sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+
+        SqliteStorage::new(pool)
+    }
+
+    #[tokio::test]
+    async fn undoing_a_course_role_change_restores_the_previous_link() {
+        let storage = sqlite_storage().await;
+        let course_id = storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap() as i64;
+        let link = |role_id| CourseRole {
+            guild_id: ACTOR.guild_id,
+            fk_course_id: course_id,
+            role_id,
+            channel_id: None,
+        };
+        storage.set_course_role(&link(10), &ACTOR).await.unwrap();
+        storage.set_course_role(&link(20), &ACTOR).await.unwrap();
+
+        let entries = storage
+            .audit_entries(ACTOR.guild_id, Some(audit::COURSE_ROLE), None, None)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        storage.undo(entries[0].id, &ACTOR).await.unwrap();
+        assert_eq!(
+            storage.course_roles(ACTOR.guild_id).await.unwrap(),
+            [link(10)]
+        );
+
+        storage.undo(entries[1].id, &ACTOR).await.unwrap();
+        assert!(storage
+            .course_roles(ACTOR.guild_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn undoing_a_term_insert_reactivates_the_previous_term() {
+        let storage = sqlite_storage().await;
+        storage
+            .insert_term(&term("Fall", true), &ACTOR)
+            .await
+            .unwrap();
+        storage
+            .insert_term(&term("Spring", true), &ACTOR)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .active_term(ACTOR.guild_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .name,
+            "Spring"
+        );
+
+        let entries = storage
+            .audit_entries(ACTOR.guild_id, Some(audit::TERM), None, None)
+            .await
+            .unwrap();
+        assert_eq!(entries[0].action, audit::INSERT);
+        storage.undo(entries[0].id, &ACTOR).await.unwrap();
+        assert_eq!(
+            storage
+                .active_term(ACTOR.guild_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .name,
+            "Fall"
+        );
+    }
+
+    #[tokio::test]
+    async fn undoing_a_course_delete_fails_once_its_code_is_taken() {
+        let storage = sqlite_storage().await;
+        let id = storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap() as i64;
+        storage.delete_course(id, &ACTOR).await.unwrap();
+        storage
+            .insert_course(&course("abc", None), &ACTOR)
+            .await
+            .unwrap();
+
+        let entries = storage
+            .audit_entries(ACTOR.guild_id, Some(audit::COURSE), Some(id), None)
+            .await
+            .unwrap();
+        assert_eq!(entries[0].action, audit::DELETE);
+        assert!(matches!(
+            storage.undo(entries[0].id, &ACTOR).await,
+            Err(Error::Validation(message)) if message.contains("already used by")
+        ));
+    }
+
+    #[tokio::test]
+    async fn enrolling_is_recorded_once_and_can_be_undone() {
+        let storage = sqlite_storage().await;
+        let course_id = storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap() as i64;
+        storage
+            .set_subscribed(course_id, true, &ACTOR)
+            .await
+            .unwrap();
+        storage
+            .set_subscribed(course_id, true, &ACTOR)
+            .await
+            .unwrap();
+
+        let entries = storage
+            .audit_entries(ACTOR.guild_id, Some(audit::SUBSCRIPTION), None, None)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        storage.undo(entries[0].id, &ACTOR).await.unwrap();
+        assert!(!storage
+            .is_subscribed(ACTOR.user_id, course_id)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn undoing_a_cohort_deletion_brings_back_its_members_and_dates() {
+        let storage = sqlite_storage().await;
+        let course_id = storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap() as i64;
+        let assessment_id = storage
+            .insert_assessment(&assessment("Lab", course_id), &ACTOR)
+            .await
+            .unwrap() as i64;
+        let cohort = storage.insert_cohort("Lab A", &ACTOR).await.unwrap();
+        storage
+            .set_member_cohort(ACTOR.guild_id, ACTOR.user_id, Some(cohort.id))
+            .await
+            .unwrap();
+        let dates = CohortDates {
+            fk_assessment_id: assessment_id,
+            fk_cohort_id: cohort.id,
+            take1: Some(String::from("2024-03-01")),
+            retake1: None,
+            retake2: None,
+        };
+        storage.set_cohort_dates(&dates, &ACTOR).await.unwrap();
+
+        storage.delete_cohort(cohort.id, &ACTOR).await.unwrap();
+        assert!(storage.cohorts(ACTOR.guild_id).await.unwrap().is_empty());
+
+        let entries = storage
+            .audit_entries(ACTOR.guild_id, Some(audit::COHORT), Some(cohort.id), None)
+            .await
+            .unwrap();
+        assert_eq!(entries[0].action, audit::DELETE);
+        storage.undo(entries[0].id, &ACTOR).await.unwrap();
+        assert_eq!(
+            storage
+                .member_cohort(ACTOR.guild_id, ACTOR.user_id)
+                .await
+                .unwrap(),
+            Some(cohort.clone())
+        );
+        assert_eq!(storage.cohorts(ACTOR.guild_id).await.unwrap(), [cohort]);
+        assert_eq!(storage.cohort_dates(ACTOR.guild_id).await.unwrap(), [dates]);
+    }
+
+    #[tokio::test]
+    async fn viewed_courses_fall_back_to_all_without_an_active_term() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert_course(&course("ABC", None), &ACTOR)
+            .await
+            .unwrap();
+
+        let (term, courses) = storage.viewed_courses(ACTOR.guild_id, None).await.unwrap();
+        assert!(term.is_none());
+        assert_eq!(courses.len(), 1);
+    }
+}
@@ -0,0 +1,420 @@
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use embedded_graphics::mono_font::iso_8859_13::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Line, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+
+use crate::config;
+use crate::database_utils::{Assessment, Course, Session};
+use crate::rendering::{date_urgency, DateUrgency, Thresholds};
+use crate::utils::{assessment_dates, week_start};
+
+const TIME_COLUMN_WIDTH: u32 = 44;
+const DAY_COLUMN_WIDTH: u32 = 130;
+const HEADER_HEIGHT: u32 = 32;
+const MARKER_HEIGHT: u32 = 14;
+const HOUR_HEIGHT: u32 = 40;
+const CHAR_WIDTH: u32 = 6;
+
+// Hours that are always shown, extended if a session starts earlier or ends later
+const FIRST_HOUR: u32 = 8;
+const LAST_HOUR: u32 = 18;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const BACKGROUND: Rgb888 = Rgb888::new(0x31, 0x33, 0x38);
+const GRID: Rgb888 = Rgb888::new(0x4e, 0x50, 0x58);
+const TEXT: Rgb888 = Rgb888::new(0xf2, 0xf3, 0xf5);
+const TODAY: Rgb888 = Rgb888::new(0x40, 0x44, 0x4b);
+
+// Session blocks cycle through these, picked by course id
+const COURSE_COLORS: [Rgb888; 6] = [
+    Rgb888::new(0x58, 0x65, 0xf2),
+    Rgb888::new(0x9b, 0x59, 0xb6),
+    Rgb888::new(0x1a, 0xbc, 0x9c),
+    Rgb888::new(0xe6, 0x7e, 0x22),
+    Rgb888::new(0x34, 0x98, 0xdb),
+    Rgb888::new(0xe9, 0x1e, 0x63),
+];
+
+fn urgency_color(urgency: DateUrgency) -> Rgb888 {
+    match urgency {
+        DateUrgency::Past => Rgb888::new(0x80, 0x84, 0x8e),
+        DateUrgency::ThisWeek => Rgb888::new(0xed, 0x42, 0x45),
+        DateUrgency::NextWeek => Rgb888::new(0xfe, 0xe7, 0x5c),
+        DateUrgency::Later => Rgb888::new(0x57, 0xf2, 0x87),
+    }
+}
+
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, background: Rgb888) -> Self {
+        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+        for _ in 0..width * height {
+            pixels.extend_from_slice(&[background.r(), background.g(), background.b()]);
+        }
+
+        Canvas {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn encode_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = Rgb888;
+    type Error = std::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0
+                || point.y < 0
+                || point.x as u32 >= self.width
+                || point.y as u32 >= self.height
+            {
+                continue;
+            }
+
+            let index = ((point.y as u32 * self.width + point.x as u32) * 3) as usize;
+            self.pixels[index..index + 3].copy_from_slice(&[color.r(), color.g(), color.b()]);
+        }
+
+        Ok(())
+    }
+}
+
+struct Marker {
+    label: String,
+    urgency: DateUrgency,
+}
+
+fn truncate(text: &str, width: u32) -> String {
+    let max_chars = (width / CHAR_WIDTH) as usize;
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    text.chars()
+        .take(max_chars.saturating_sub(1))
+        .chain(std::iter::once('~'))
+        .collect()
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+fn draw_text(canvas: &mut Canvas, text: &str, x: i32, y: i32, width: u32, color: Rgb888) {
+    let style = MonoTextStyle::new(&FONT_6X10, color);
+    Text::with_baseline(
+        &truncate(text, width),
+        Point::new(x, y),
+        style,
+        Baseline::Top,
+    )
+    .draw(canvas)
+    .ok();
+}
+
+fn fill(canvas: &mut Canvas, x: i32, y: i32, width: u32, height: u32, color: Rgb888) {
+    Rectangle::new(Point::new(x, y), Size::new(width, height))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(canvas)
+        .ok();
+}
+
+fn line(canvas: &mut Canvas, start: Point, end: Point) {
+    Line::new(start, end)
+        .into_styled(PrimitiveStyle::with_stroke(GRID, 1))
+        .draw(canvas)
+        .ok();
+}
+
+/// Collects assessment dates falling into the week, grouped by weekday
+fn collect_markers(
+    assessments: &[Assessment],
+    courses: &[Course],
+    monday: NaiveDate,
+    today: NaiveDate,
+) -> Vec<Vec<Marker>> {
+    let thresholds = Thresholds::from(&config::get().colors);
+    let mut markers: Vec<Vec<Marker>> = (0..7).map(|_| Vec::new()).collect();
+
+    for assessment in assessments {
+        let code = courses
+            .iter()
+            .find(|course| course.id == assessment.fk_course_id)
+            .map(|course| course.code.clone())
+            .unwrap_or_default();
+
+        for date in assessment_dates(assessment) {
+            let offset = (date - monday).num_days();
+            if !(0..7).contains(&offset) {
+                continue;
+            }
+
+            markers[offset as usize].push(Marker {
+                label: format!("{} {}", code, assessment.name),
+                urgency: date_urgency(date, today, thresholds),
+            });
+        }
+    }
+
+    markers
+}
+
+/// Renders the week containing `date` as a PNG image.
+/// Output only depends on the arguments, `today` is used for urgency colors and highlighting.
+pub fn render_week(
+    courses: &[Course],
+    sessions: &[Session],
+    assessments: &[Assessment],
+    date: NaiveDate,
+    today: NaiveDate,
+) -> Result<Vec<u8>, png::EncodingError> {
+    let monday = week_start(date);
+    let markers = collect_markers(assessments, courses, monday, today);
+    let marker_rows = markers.iter().map(|day| day.len()).max().unwrap_or(0) as u32;
+
+    let mut first_hour = FIRST_HOUR;
+    let mut last_hour = LAST_HOUR;
+    for session in sessions {
+        if let (Some(start), Some(end)) = (
+            parse_time(&session.start_time),
+            parse_time(&session.end_time),
+        ) {
+            first_hour = first_hour.min(start.hour());
+            last_hour = last_hour.max(if end.minute() > 0 {
+                end.hour() + 1
+            } else {
+                end.hour()
+            });
+        }
+    }
+    let last_hour = last_hour.min(24);
+
+    let grid_top = HEADER_HEIGHT + marker_rows * MARKER_HEIGHT;
+    let width = TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * 7 + 1;
+    let height = grid_top + (last_hour - first_hour) * HOUR_HEIGHT + 1;
+
+    let mut canvas = Canvas::new(width, height, BACKGROUND);
+
+    // Header with day names and dates, today's column highlighted
+    for (index, weekday) in WEEKDAYS.iter().enumerate() {
+        let day = monday + chrono::Duration::days(index as i64);
+        let x = (TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * index as u32) as i32;
+
+        if day == today {
+            fill(&mut canvas, x, 0, DAY_COLUMN_WIDTH, height, TODAY);
+        }
+
+        draw_text(&mut canvas, weekday, x + 4, 4, DAY_COLUMN_WIDTH - 8, TEXT);
+        draw_text(
+            &mut canvas,
+            &day.format("%Y-%m-%d").to_string(),
+            x + 4,
+            16,
+            DAY_COLUMN_WIDTH - 8,
+            TEXT,
+        );
+
+        for (row, marker) in markers[index].iter().enumerate() {
+            let y = (HEADER_HEIGHT + row as u32 * MARKER_HEIGHT) as i32;
+            fill(
+                &mut canvas,
+                x + 2,
+                y + 1,
+                DAY_COLUMN_WIDTH - 4,
+                MARKER_HEIGHT - 2,
+                urgency_color(marker.urgency),
+            );
+            draw_text(
+                &mut canvas,
+                &marker.label,
+                x + 4,
+                y + 2,
+                DAY_COLUMN_WIDTH - 8,
+                BACKGROUND,
+            );
+        }
+    }
+
+    // Hour lines and labels
+    for hour in first_hour..=last_hour {
+        let y = (grid_top + (hour - first_hour) * HOUR_HEIGHT) as i32;
+        line(
+            &mut canvas,
+            Point::new(0, y),
+            Point::new(width as i32 - 1, y),
+        );
+        if hour < last_hour {
+            draw_text(
+                &mut canvas,
+                &format!("{:02}:00", hour),
+                4,
+                y + 3,
+                TIME_COLUMN_WIDTH - 4,
+                TEXT,
+            );
+        }
+    }
+
+    for column in 0..=7 {
+        let x = (TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * column) as i32;
+        line(
+            &mut canvas,
+            Point::new(x, 0),
+            Point::new(x, height as i32 - 1),
+        );
+    }
+
+    // Session blocks
+    for session in sessions {
+        let (start, end) = match (
+            parse_time(&session.start_time),
+            parse_time(&session.end_time),
+        ) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => continue,
+        };
+        if !(1..=7).contains(&session.weekday) {
+            continue;
+        }
+
+        let minutes_from_top = |time: NaiveTime| (time.hour() - first_hour) * 60 + time.minute();
+        let top = grid_top + minutes_from_top(start) * HOUR_HEIGHT / 60;
+        let bottom = grid_top + minutes_from_top(end) * HOUR_HEIGHT / 60;
+        let x = (TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * (session.weekday as u32 - 1)) as i32;
+
+        let code = courses
+            .iter()
+            .find(|course| course.id == session.fk_course_id)
+            .map(|course| course.code.clone())
+            .unwrap_or_default();
+        let color =
+            COURSE_COLORS[session.fk_course_id.unsigned_abs() as usize % COURSE_COLORS.len()];
+
+        fill(
+            &mut canvas,
+            x + 2,
+            top as i32 + 1,
+            DAY_COLUMN_WIDTH - 4,
+            bottom - top - 1,
+            color,
+        );
+
+        let lines = [
+            code,
+            format!("{}-{}", session.start_time, session.end_time),
+            session.location.clone(),
+        ];
+        for (row, text) in lines.iter().enumerate() {
+            let y = top + 3 + row as u32 * 11;
+            if y + 10 > bottom {
+                break;
+            }
+            draw_text(
+                &mut canvas,
+                text,
+                x + 5,
+                y as i32,
+                DAY_COLUMN_WIDTH - 10,
+                TEXT,
+            );
+        }
+    }
+
+    canvas.encode_png()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::*;
+
+    fn decode(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(bytes);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        pixels.truncate(info.buffer_size());
+
+        (info.width, info.height, pixels)
+    }
+
+    fn pixel(image: &(u32, u32, Vec<u8>), x: u32, y: u32) -> Rgb888 {
+        let index = ((y * image.0 + x) * 3) as usize;
+        Rgb888::new(image.2[index], image.2[index + 1], image.2[index + 2])
+    }
+
+    #[test]
+    fn week_rendering_is_deterministic() {
+        let courses = vec![Course {
+            id: 1,
+            ..course("ABC", None)
+        }];
+        let sessions = vec![Session {
+            id: 1,
+            weekday: 2,
+            start_time: String::from("07:00"),
+            end_time: String::from("09:30"),
+            location: String::from("Room 1"),
+            fk_course_id: 1,
+        }];
+        let assessments = vec![assessment("Exam", 1)];
+        let date = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 12).unwrap();
+
+        let first = render_week(&courses, &sessions, &assessments, date, today).unwrap();
+        let second = render_week(&courses, &sessions, &assessments, date, today).unwrap();
+        assert_eq!(first, second);
+
+        // The session starts before the default first hour and the exam adds one marker row
+        let image = decode(&first);
+        let grid_top = HEADER_HEIGHT + MARKER_HEIGHT;
+        assert_eq!(image.0, TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * 7 + 1);
+        assert_eq!(image.1, grid_top + (LAST_HOUR - 7) * HOUR_HEIGHT + 1);
+
+        let tuesday = TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH;
+        let session_pixel = pixel(&image, tuesday + 3, grid_top + 2 * HOUR_HEIGHT);
+        assert_eq!(session_pixel, COURSE_COLORS[1]);
+
+        let friday = TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * 4;
+        assert_eq!(pixel(&image, friday + 1, image.1 - 2), TODAY);
+        let wednesday = TIME_COLUMN_WIDTH + DAY_COLUMN_WIDTH * 2;
+        assert_eq!(
+            pixel(&image, wednesday + 3, HEADER_HEIGHT + 2),
+            urgency_color(DateUrgency::Past)
+        );
+    }
+}
@@ -1,155 +1,80 @@
-use chrono::{NaiveDate, Utc};
-use comfy_table::modifiers::UTF8_ROUND_CORNERS;
-use comfy_table::presets::UTF8_FULL;
-use comfy_table::Cell;
-use comfy_table::*;
+use chrono::{Datelike, NaiveDate};
 use serenity::{
-    builder::{CreateActionRow, CreateButton},
+    builder::{CreateActionRow, CreateButton, CreateSelectMenuOptions},
     model::application::component::ButtonStyle,
 };
 
-use crate::database_utils::{Assessment, Course};
-
-// Discord API limits to 2000 characters per message
-pub static COURSES_PER_PAGE: usize = 5;
-pub static ASSESSMENTS_PER_PAGE: usize = 9;
-
-pub fn build_courses_table(courses: Vec<Course>) -> String {
-    let mut table = Table::new();
-    table
-        .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_width(100)
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_header(vec!["ID", "Name", "Code", "Semester", "Year", "Credit"]);
-
-    for course in courses {
-        table.add_row(vec![
-            Cell::new(&course.id.to_string()),
-            Cell::new(&course.name),
-            Cell::new(&course.code),
-            Cell::new(&course.semester.to_string()),
-            Cell::new(&course.year.to_string()),
-            Cell::new(&course.credit.to_string()),
-        ]);
-    }
-
-    String::from("```ansi\n") + &table.to_string() + "```"
-}
+use crate::config;
+use crate::database_utils::{Assessment, Course, SearchHit};
+use crate::rendering::{self, RenderOptions};
 
-pub fn build_assessments_table(assessments: Vec<Assessment>) -> String {
-    let mut table = Table::new();
+// Discord API limits select menus to 25 options
+pub static SELECT_MENU_OPTIONS: usize = 25;
+// Calendar pages are months counted from year 0, buttons stop at the end of year 9999
+pub static CALENDAR_LAST_MONTH: usize = 9999 * 12 + 11;
+// Search replies show this many results, their courses fit in two rows of buttons
+pub static SEARCH_RESULTS: i64 = 10;
+// Discord API limits action rows to 5 buttons
+pub static ROW_BUTTONS: usize = 5;
 
-    table
-        .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_width(100)
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_header(vec![
-            "ID",
-            "Name",
-            "Take 1",
-            "Retake 1",
-            "Retake 2",
-            "Weight",
-            "Course ID",
-        ]);
+pub fn create_confirmation_buttons(action: &str, id: i64) -> (CreateButton, CreateButton) {
+    let confirm_button = CreateButton::default()
+        .style(ButtonStyle::Danger)
+        .label("Confirm")
+        .custom_id(format!("confirm;{action};{id}"))
+        .to_owned();
 
-    for assessment in assessments {
-        let take1 = add_date_color(assessment.take1.clone());
-        let retake1 = add_date_color(assessment.retake1.clone());
-        let retake2 = add_date_color(assessment.retake2.clone());
-        // let take1 = console::style(assessment.take1.clone()).red();
-
-        table.add_row(vec![
-            Cell::new(&assessment.id.to_string()),
-            Cell::new(&assessment.name),
-            Cell::new(&take1),
-            Cell::new(&retake1),
-            Cell::new(&retake2),
-            Cell::new(&assessment.weight.to_string()),
-            Cell::new(&assessment.fk_course_id.to_string()),
-        ]);
-    }
+    let cancel_button = CreateButton::default()
+        .style(ButtonStyle::Secondary)
+        .label("Cancel")
+        .custom_id(format!("cancel;{action};{id}"))
+        .to_owned();
 
-    String::from("```ansi\n") + &table.to_string() + "```"
+    (confirm_button, cancel_button)
 }
 
-pub fn calculate_range(
-    current_page: usize,
-    per_page: usize,
-    total: usize,
-) -> std::ops::Range<usize> {
-    let start = (current_page - 1) * per_page;
-    let end = if start + per_page > total {
-        total
-    } else {
-        start + per_page
-    };
-
-    start..end
-}
+pub fn format_remove_course_confirmation(course: &Course, assessments: &[Assessment]) -> String {
+    let mut content = format!(
+        "# Remove course?\n**{} {}** (ID: {}) and its {} assessment(s) will be moved to the trash",
+        course.code,
+        course.name,
+        course.id,
+        assessments.len()
+    );
 
-fn add_date_color(date: String) -> String {
-    let today = Utc::now().naive_utc().date();
-    let date = match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
-        Ok(date) => date,
-        Err(_) => return date,
-    };
+    for assessment in assessments {
+        content += &format!("\n- {} (ID: {})", assessment.name, assessment.id);
+    }
 
-    let result = if date < today {
-        format!(
-            "{}{}{}",
-            "\u{001b}[30m",
-            date.format("%Y-%m-%d").to_string(),
-            "\u{001b}[0m"
-        )
-    } else if date <= today + chrono::Duration::days(7) {
-        format!(
-            "{}{}{}",
-            "\u{001b}[31m",
-            date.format("%Y-%m-%d").to_string(),
-            "\u{001b}[0m"
-        )
-    } else if date <= today + chrono::Duration::days(14) {
-        format!(
-            "{}{}{}",
-            "\u{001b}[33m",
-            date.format("%Y-%m-%d").to_string(),
-            "\u{001b}[0m"
-        )
-    } else {
-        format!(
-            "{}{}{}",
-            "\u{001b}[32m",
-            date.format("%Y-%m-%d").to_string(),
-            "\u{001b}[0m"
+    content
+        + &format!(
+            "\nConfirmation expires in {} seconds",
+            config::get().scheduler.confirmation_timeout_seconds
         )
-    };
-
-    result
 }
 
+// The view is appended to the button ids so handlers can rebuild the same listing
 pub fn create_buttons(
     page_num: usize,
     quotient: usize,
+    view: &str,
 ) -> (CreateButton, CreateButton, CreateButton) {
     let mut previous_button = CreateButton::default()
         .style(ButtonStyle::Primary)
         .label("Previous")
-        .custom_id(format!("previous_page;{page_num}"))
+        .custom_id(format!("previous_page;{page_num};{view}"))
         .to_owned();
 
     let mut next_button = CreateButton::default()
         .style(ButtonStyle::Primary)
         .label("Next")
-        .custom_id(format!("next_page;{page_num}"))
+        .custom_id(format!("next_page;{page_num};{view}"))
         .to_owned();
 
     let refresh_button = CreateButton::default()
         .style(ButtonStyle::Primary)
         .label("↻")
-        .custom_id(format!("refresh_page;{page_num}"))
+        .custom_id(format!("refresh_page;{page_num};{view}"))
         .to_owned();
 
     if page_num == quotient + 1 {
@@ -169,47 +94,19 @@ pub fn create_buttons(
     (previous_button, next_button, refresh_button)
 }
 
-pub fn format_assessment_response(
-    assessments: &Vec<Assessment>,
-    page: usize,
-    course_name: &String,
-) -> Result<String, String> {
-    let range = calculate_range(page, ASSESSMENTS_PER_PAGE, assessments.len());
-    let assessments_table = build_assessments_table(assessments[range].to_vec());
-
-    let content = match assessments.len() <= ASSESSMENTS_PER_PAGE {
-        true => format!("# {}\n{}", course_name, assessments_table),
-        false => format!(
-            "# Assessments list (Page {}/{})\n{}",
-            page,
-            (assessments.len() / ASSESSMENTS_PER_PAGE) + 1,
-            assessments_table
-        ),
-    };
-
-    Ok(content)
-}
-
-pub fn format_course_response(courses: &Vec<Course>, page: usize) -> Result<String, String> {
-    let range = calculate_range(page, COURSES_PER_PAGE, courses.len());
-    let courses_table = build_courses_table(courses[range].to_vec());
+/// Previous and next month buttons, `create_buttons` disables next on the page after its quotient
+pub fn create_calendar_buttons(month: usize) -> (CreateButton, CreateButton) {
+    let (previous_button, next_button, _refresh_button) =
+        create_buttons(month, CALENDAR_LAST_MONTH - 1, "");
 
-    let content = match courses.len() <= COURSES_PER_PAGE {
-        true => format!("# Courses list\n{}", courses_table),
-        false => format!(
-            "# Courses list (Page {}/{})\n{}",
-            page,
-            (courses.len() / COURSES_PER_PAGE) + 1,
-            courses_table
-        ),
-    };
-
-    Ok(content)
+    (previous_button, next_button)
 }
 
+// The view is appended to the menu id so the listing keeps its sort order when switching courses
 pub fn create_courses_select_menu(
-    courses: &Vec<Course>,
+    courses: &[Course],
     current_course_id: i64,
+    view: &str,
 ) -> Result<CreateActionRow, CreateActionRow> {
     let current_course_name = match courses.iter().find(|course| course.id == current_course_id) {
         Some(course) => course.name.clone(),
@@ -218,13 +115,245 @@ pub fn create_courses_select_menu(
 
     let action_row = CreateActionRow::default()
         .create_select_menu(|menu| {
-            menu.custom_id(format!("select_course;{current_course_id}"));
+            menu.custom_id(format!("select_course;{current_course_id};{view}"));
             menu.placeholder(&current_course_name);
+            menu.options(|f| course_options(f, courses))
+        })
+        .to_owned();
+
+    if &current_course_name == "No course selected" {
+        return Err(action_row);
+    }
+    Ok(action_row)
+}
+
+fn course_options<'a>(
+    options: &'a mut CreateSelectMenuOptions,
+    courses: &[Course],
+) -> &'a mut CreateSelectMenuOptions {
+    for course in courses.iter().take(SELECT_MENU_OPTIONS) {
+        options.create_option(|o| {
+            o.label(&course.name);
+            o.value(course.id.to_string());
+            o
+        });
+    }
+    options
+}
+
+// One button per course in rank order, each opens the course's assessments
+pub fn create_search_buttons(hits: &[SearchHit]) -> Vec<CreateActionRow> {
+    let mut buttons: Vec<CreateButton> = Vec::new();
+    let mut course_ids: Vec<i64> = Vec::new();
+    for hit in hits {
+        if course_ids.contains(&hit.course_id) {
+            continue;
+        }
+        course_ids.push(hit.course_id);
+        buttons.push(
+            CreateButton::default()
+                .style(ButtonStyle::Secondary)
+                .label(&hit.code)
+                .custom_id(format!("open_course;{}", hit.course_id))
+                .to_owned(),
+        );
+    }
+
+    buttons
+        .chunks(ROW_BUTTONS)
+        .map(|row| {
+            let mut action_row = CreateActionRow::default();
+            for button in row {
+                action_row.add_button(button.clone());
+            }
+            action_row
+        })
+        .collect()
+}
+
+// Enrollment toggles for the caller, the same as `/enroll`. Edit is only offered to course editors
+pub fn create_course_buttons(course_id: i64, subscribed: bool, can_edit: bool) -> CreateActionRow {
+    let (style, label, action) = match subscribed {
+        true => (ButtonStyle::Secondary, "Unenroll", "unsubscribe"),
+        false => (ButtonStyle::Success, "Enroll", "subscribe"),
+    };
+
+    let mut action_row = CreateActionRow::default();
+    action_row
+        .create_button(|b| {
+            b.style(style)
+                .label(label)
+                .custom_id(format!("{action};{course_id}"))
+        })
+        .create_button(|b| {
+            b.style(ButtonStyle::Secondary)
+                .label("Export calendar")
+                .custom_id(format!("export_course;{course_id}"))
+        });
+    if can_edit {
+        action_row.create_button(|b| {
+            b.style(ButtonStyle::Primary)
+                .label("Edit")
+                .custom_id(format!("edit_course;{course_id}"))
+        });
+    }
+
+    action_row
+}
+
+pub fn assessment_dates(assessment: &Assessment) -> Vec<NaiveDate> {
+    [&assessment.take1, &assessment.retake1, &assessment.retake2]
+        .iter()
+        .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .collect()
+}
+
+/// Attempts with a date, labelled as in the assessment tables
+pub fn assessment_attempts(assessment: &Assessment) -> Vec<(&'static str, NaiveDate)> {
+    [
+        ("Take 1", &assessment.take1),
+        ("Retake 1", &assessment.retake1),
+        ("Retake 2", &assessment.retake2),
+    ]
+    .into_iter()
+    .filter_map(|(attempt, date)| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .map(|date| (attempt, date))
+    })
+    .collect()
+}
+
+/// The nearest date that is today or later, otherwise the latest past one
+pub fn next_attempt(assessment: &Assessment, today: NaiveDate) -> Option<NaiveDate> {
+    let dates = assessment_dates(assessment);
+
+    dates
+        .iter()
+        .filter(|date| **date >= today)
+        .min()
+        .or(dates.iter().max())
+        .copied()
+}
+
+// Dates that can't be parsed are left as they are
+pub fn shift_date(date: &str, days: i64) -> String {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => (date + chrono::Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string(),
+        Err(_) => date.to_string(),
+    }
+}
+
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// `None` for dates before year 0, which have no calendar page
+pub fn month_index(date: NaiveDate) -> Option<usize> {
+    let year = usize::try_from(date.year()).ok()?;
+    Some(year * 12 + date.month0() as usize)
+}
+
+pub fn month_from_index(index: usize) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt((index / 12) as i32, (index % 12) as u32 + 1, 1)
+}
+
+fn days_in_month(first_day: NaiveDate) -> u32 {
+    (28..=31)
+        .rev()
+        .find(|day| first_day.with_day(*day).is_some())
+        .unwrap_or(28)
+}
+
+pub fn build_calendar(
+    assessments: &[Assessment],
+    first_day: NaiveDate,
+    options: &RenderOptions,
+) -> String {
+    let dates: Vec<NaiveDate> = assessments.iter().flat_map(assessment_dates).collect();
+
+    let mut calendar = String::from(" Mo Tu We Th Fr Sa Su\n");
+    let offset = first_day.weekday().num_days_from_monday();
+    calendar += &"   ".repeat(offset as usize);
+
+    for day in 1..=days_in_month(first_day) {
+        let date = first_day.with_day(day).unwrap();
+        let mut cell = format!("{:>3}", day);
+
+        if dates.contains(&date) {
+            cell = options.theme.paint(&cell, options.urgency(date));
+        }
+        if date == options.today {
+            cell = format!("{}{}{}", "\u{001b}[4m", cell, "\u{001b}[0m");
+        }
+
+        calendar += &cell;
+        if (offset + day).is_multiple_of(7) {
+            calendar += "\n";
+        }
+    }
+
+    String::from("```ansi\n") + calendar.trim_end() + "\n```"
+}
+
+pub fn format_calendar_response(
+    assessments: &[Assessment],
+    first_day: NaiveDate,
+    selected_day: Option<NaiveDate>,
+    options: &RenderOptions,
+) -> String {
+    let mut content = format!(
+        "# {}\n{}",
+        first_day.format("%B %Y"),
+        build_calendar(assessments, first_day, options)
+    );
+
+    if let Some(day) = selected_day {
+        let day_assessments: Vec<Assessment> = assessments
+            .iter()
+            .filter(|assessment| assessment_dates(assessment).contains(&day))
+            .cloned()
+            .collect();
+
+        content += &format!(
+            "## {}\n{}",
+            day.format("%Y-%m-%d"),
+            rendering::assessments_table(&day_assessments, options).render(&options.theme)
+        );
+    }
+
+    content
+}
+
+pub fn create_calendar_day_select_menu(
+    assessments: &[Assessment],
+    first_day: NaiveDate,
+) -> Option<CreateActionRow> {
+    let mut days: Vec<NaiveDate> = assessments
+        .iter()
+        .flat_map(assessment_dates)
+        .filter(|date| (date.year(), date.month()) == (first_day.year(), first_day.month()))
+        .collect();
+    days.sort();
+    days.dedup();
+    days.truncate(SELECT_MENU_OPTIONS);
+
+    let month = month_index(first_day)?;
+    if days.is_empty() {
+        return None;
+    }
+
+    let action_row = CreateActionRow::default()
+        .create_select_menu(|menu| {
+            menu.custom_id(format!("select_day;{}", month));
+            menu.placeholder("Select a day to list its assessments");
             menu.options(|f| {
-                for course in courses {
+                for day in &days {
                     f.create_option(|o| {
-                        o.label(&course.name);
-                        o.value(course.id.to_string());
+                        o.label(day.format("%Y-%m-%d, %A"));
+                        o.value(day.format("%Y-%m-%d"));
                         o
                     });
                 }
@@ -233,8 +362,5 @@ pub fn create_courses_select_menu(
         })
         .to_owned();
 
-    if &current_course_name == "No course selected" {
-        return Err(action_row);
-    }
-    Ok(action_row)
+    Some(action_row)
 }